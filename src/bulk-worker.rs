@@ -1,23 +1,36 @@
 mod config;
 mod models;
+mod worker_metrics;
 
 use bson::DateTime as BsonDateTime;
 use chrono::Utc;
-use futures::StreamExt;
+use futures::{stream, StreamExt};
 use governor::{Quota, RateLimiter};
 use lapin::{message::Delivery, options::*, types::FieldTable};
 use mongodb::Collection;
-use serde_json;
 use std::env;
 use std::num::NonZeroU32;
 use std::time::{Duration, Instant};
 use tokio::time::{interval, sleep};
 
+use crate::models::dedup::{Deduplicator, DEDUP_WINDOW_SECS};
+use crate::models::encryption::FieldCipher;
 use crate::models::hog_record::HogRecord;
+use crate::models::lag::ClockSkewLimits;
+use crate::models::log_metrics::{LogMetricSamples, LogMetrics};
+use crate::models::pattern::{
+    HogPatternRecord, PatternMiner, PATTERN_MAX_GROUPS, PATTERN_SIMILARITY,
+};
+use crate::models::pipeline::{Pipeline, PipelineOutcome};
+use crate::models::redaction::Redactor;
+use crate::models::stream_alert::{
+    StreamAlerter, ALERT_EVENT_RETENTION_SECS, STREAM_ALERT_REFRESH_SECS,
+};
 
 const BULK_SIZE: usize = 1000;
 const TIMING_THRESHOLD_SECS: u64 = 1;
 const MAX_RETRIES: usize = 5;
+const DEDUP_CONCURRENCY: usize = 16;
 
 #[tokio::main]
 async fn main() {
@@ -62,11 +75,64 @@ async fn main() {
 
     println!("✅ Connected to MongoDB and initialized indexes");
 
+    worker_metrics::spawn_server();
+
     let collection: Collection<HogRecord> = db.collection("hog");
+    let patterns: Collection<HogPatternRecord> = db.collection("hog_pattern");
     let limiter = RateLimiter::direct(Quota::per_second(NonZeroU32::new(400).unwrap()));
+    let pipeline = match env::var("HOG_PIPELINE_FILE") {
+        Ok(path) => Some(
+            Pipeline::load(&path)
+                .await
+                .unwrap_or_else(|e| panic!("Failed to load ingest pipeline {}", e)),
+        ),
+        Err(_) => None,
+    };
+    // The pipeline can copy or lift text into new fields after the API
+    // redacted it, so the same rules run again on what it produces.
+    let redactor =
+        Redactor::from_env().unwrap_or_else(|e| panic!("Failed to load redaction rules {}", e));
+    let cipher = FieldCipher::from_env()
+        .unwrap_or_else(|e| panic!("Failed to load field encryption keys: {}", e));
+    let metrics = match env::var("HOG_METRICS_FILE") {
+        Ok(path) => Some(
+            LogMetrics::load(&path, &cipher.encrypted_fields())
+                .await
+                .unwrap_or_else(|e| panic!("Failed to load log metrics {}", e)),
+        ),
+        Err(_) => None,
+    };
+    let cipher = cipher.is_enabled().then_some(cipher);
+    let clock_skew = ClockSkewLimits::from_env();
+    let dedup = config::env_or("HOG_DEDUP_ENABLED", false)
+        .then(|| Deduplicator::new(config::env_or("HOG_DEDUP_WINDOW_SECS", DEDUP_WINDOW_SECS)));
+    let mut miner = config::env_or("HOG_PATTERNS_ENABLED", false).then(|| {
+        PatternMiner::new(
+            config::env_or("HOG_PATTERN_SIMILARITY", PATTERN_SIMILARITY),
+            config::env_or("HOG_PATTERN_MAX_GROUPS", PATTERN_MAX_GROUPS),
+        )
+    });
+    let mut alerter = if config::env_or("STREAM_ALERTS_ENABLED", false) {
+        Some(
+            StreamAlerter::new(
+                &db,
+                channel.clone(),
+                Duration::from_secs(config::env_or(
+                    "STREAM_ALERT_REFRESH_SECS",
+                    STREAM_ALERT_REFRESH_SECS,
+                )),
+                config::env_or("ALERT_EVENT_RETENTION_SECS", ALERT_EVENT_RETENTION_SECS),
+            )
+            .await
+            .expect("Failed to declare the alerts exchange"),
+        )
+    } else {
+        None
+    };
 
     let mut bulk_order = Vec::with_capacity(BULK_SIZE);
     let mut bulk_acks = Vec::with_capacity(BULK_SIZE);
+    let mut bulk_samples = Vec::with_capacity(BULK_SIZE);
     let mut _timing_start = Instant::now();
 
     let mut flush_interval = interval(Duration::from_secs(TIMING_THRESHOLD_SECS));
@@ -76,9 +142,50 @@ async fn main() {
                 match delivery_result {
                     Some(Ok(delivery)) => {
                         match serde_json::from_slice::<HogRecord>(&delivery.data) {
-                            Ok(hog_record) => {
+                            Ok(mut hog_record) => {
+                                if let Some(ref pipeline) = pipeline
+                                    && pipeline.process(&mut hog_record) == PipelineOutcome::Drop
+                                {
+                                    if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+                                        eprintln!("Failed to ack message: {:?}", e);
+                                    }
+                                    continue;
+                                }
+                                if let Some(ref redactor) = redactor {
+                                    worker_metrics::observe_redactions(redactor.redact(&mut hog_record));
+                                }
+                                if let Some(skew) = clock_skew.flag(&mut hog_record) {
+                                    worker_metrics::observe_clock_skew(&hog_record, skew);
+                                }
+                                if let Some(miner) = miner.as_mut() {
+                                    hog_record.pattern_id = Some(miner.add(&hog_record).pattern_id);
+                                }
+                                // Match rules run as soon as the hog arrives,
+                                // not when its batch gets flushed.
+                                if let Some(alerter) = alerter.as_mut() {
+                                    alerter.evaluate(&hog_record).await;
+                                }
+                                let samples = metrics
+                                    .as_ref()
+                                    .map(|metrics| metrics.sample(&hog_record))
+                                    .unwrap_or_default();
+                                if let Some(ref dedup) = dedup {
+                                    dedup.stamp(&mut hog_record);
+                                }
+                                if let Some(ref cipher) = cipher
+                                    && let Err(e) = cipher.encrypt(&mut hog_record)
+                                {
+                                    eprintln!("Failed to encrypt fields: {}", e);
+                                    // Retrying can't fix it, so don't requeue.
+                                    worker_metrics::observe_rejected("encryption");
+                                    if let Err(e) = delivery.nack(BasicNackOptions { requeue: false, ..Default::default() }).await {
+                                        eprintln!("Failed to nack message: {:?}", e);
+                                    }
+                                    continue;
+                                }
                                 bulk_order.push(hog_record);
                                 bulk_acks.push(delivery);
+                                bulk_samples.push(samples);
                             }
                             Err(e) => {
                                 eprintln!("Failed to deserialize Hog: {:?}", e);
@@ -87,7 +194,7 @@ async fn main() {
                         }
                         if bulk_order.len() >= BULK_SIZE {
                             limiter.until_ready().await;
-                            if let Err(e) = process_message(&collection, &bulk_acks, &bulk_order).await {
+                            if let Err(e) = process_message(&collection, &patterns, miner.as_mut(), dedup.as_ref(), metrics.as_ref(), &bulk_acks, &bulk_order, std::mem::take(&mut bulk_samples)).await {
                                 eprintln!("Error processing message batch: {:?}", e);
                             }
                             bulk_acks.clear();
@@ -107,7 +214,7 @@ async fn main() {
             _ = flush_interval.tick() => {
                 if !bulk_order.is_empty() {
                     limiter.until_ready().await;
-                    if let Err(e) = process_message(&collection, &bulk_acks, &bulk_order).await {
+                    if let Err(e) = process_message(&collection, &patterns, miner.as_mut(), dedup.as_ref(), metrics.as_ref(), &bulk_acks, &bulk_order, std::mem::take(&mut bulk_samples)).await {
                         eprintln!("Error processing message batch: {:?}", e);
                     }
                     bulk_acks.clear();
@@ -119,25 +226,52 @@ async fn main() {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_message(
     collection: &Collection<HogRecord>,
+    patterns: &Collection<HogPatternRecord>,
+    miner: Option<&mut PatternMiner>,
+    dedup: Option<&Deduplicator>,
+    metrics: Option<&LogMetrics>,
     deliveries: &[Delivery],
     hogs: &[HogRecord],
+    samples: Vec<LogMetricSamples>,
 ) -> anyhow::Result<()> {
     for attempt in 1..=MAX_RETRIES {
         let now = BsonDateTime::from_chrono(Utc::now());
         let mut hog_records = hogs.to_vec();
         for record in &mut hog_records {
-            record.created_at = Some(now.clone());
+            record.created_at = Some(now);
         }
-        match collection.insert_many(hog_records).await {
+        let stored = match dedup {
+            Some(dedup) => store_folded(collection, dedup, &hog_records, now).await,
+            None => collection
+                .insert_many(hog_records)
+                .await
+                .map(|_| ())
+                .map_err(Into::into),
+        };
+        match stored {
             Ok(_) => {
+                // Repeats count too, whether or not they were folded.
+                if let Some(metrics) = metrics {
+                    for samples in samples {
+                        metrics.record(samples);
+                    }
+                }
                 for d in deliveries {
                     if let Err(e) = d.ack(BasicAckOptions::default()).await {
                         eprintln!("Failed to ack message: {:?}", e);
-                    } else {
                     }
                 }
+                for hog in hogs {
+                    worker_metrics::observe_lag(hog, now);
+                }
+                if let Some(miner) = miner
+                    && let Err(e) = miner.flush(patterns, collection).await
+                {
+                    eprintln!("Failed to store log patterns: {:?}", e);
+                }
                 return Ok(());
             }
             Err(e) => {
@@ -148,7 +282,7 @@ async fn process_message(
                             eprintln!("Failed to nack message: {:?}", e);
                         }
                     }
-                    return Err(e.into());
+                    return Err(e);
                 }
                 sleep(Duration::from_millis(500 * attempt as u64)).await;
             }
@@ -156,3 +290,20 @@ async fn process_message(
     }
     Ok(())
 }
+
+/// Repeats inside the batch are counted once here instead of being inserted
+/// side by side, then each distinct hog is folded into what's stored. Every
+/// one is tried even when another fails, and the first error is returned.
+async fn store_folded(
+    collection: &Collection<HogRecord>,
+    dedup: &Deduplicator,
+    hogs: &[HogRecord],
+    now: BsonDateTime,
+) -> anyhow::Result<()> {
+    let results: Vec<anyhow::Result<()>> = stream::iter(dedup.fold(hogs))
+        .map(|(record, uuids)| async move { dedup.store(collection, &record, &uuids, now).await })
+        .buffer_unordered(DEDUP_CONCURRENCY)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
@@ -1,6 +1,9 @@
 use bson::doc;
 use lapin::{Channel, Connection, ConnectionProperties};
-use mongodb::{Client, Database, options::ClientOptions};
+use mongodb::{
+    options::{ClientOptions, IndexOptions},
+    Client, Database,
+};
 use std::{env, time::Duration};
 use tokio::time::sleep;
 
@@ -9,6 +12,15 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 static INDEXED: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 
+/// Reads an optional env var, falling back to `default` when unset or unparsable.
+#[allow(dead_code)]
+pub fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
 pub async fn init_db() -> mongodb::error::Result<Database> {
     let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
     let db_name = env::var("DATABASE_NAME").expect("DATABASE_NAME must be set");
@@ -44,9 +56,138 @@ pub async fn init_db_indexes(db: &Database) -> mongodb::error::Result<()> {
         IndexModel::builder()
             .keys(doc! { "log_source": 1, "log_timestamp": -1 })
             .build(),
+        IndexModel::builder()
+            .keys(doc! { "log_source": 1, "hog_timestamp": -1 })
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "log_source": 1, "log_source_id": 1, "hog_timestamp": -1 })
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "pattern_id": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "content_hash": 1, "dedup_until": -1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "repeat_hog_uuids": 1 })
+            .options(IndexOptions::builder().sparse(true).build())
+            .build(),
     ];
 
     collection.create_indexes(indexes).await?;
+
+    let search_job_indexes = vec![IndexModel::builder()
+        .keys(doc! { "expires_at": 1 })
+        .options(
+            IndexOptions::builder()
+                .expire_after(Duration::from_secs(0))
+                .build(),
+        )
+        .build()];
+    db.collection::<serde_json::Value>("search_job")
+        .create_indexes(search_job_indexes)
+        .await?;
+
+    let search_job_result_indexes = vec![
+        IndexModel::builder()
+            .keys(doc! { "job_id": 1, "seq": 1 })
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(Duration::from_secs(0))
+                    .build(),
+            )
+            .build(),
+    ];
+    db.collection::<serde_json::Value>("search_job_result")
+        .create_indexes(search_job_result_indexes)
+        .await?;
+
+    let saved_search_indexes = vec![IndexModel::builder()
+        .keys(doc! { "owner": 1, "name": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build()];
+    db.collection::<serde_json::Value>("saved_search")
+        .create_indexes(saved_search_indexes)
+        .await?;
+
+    db.collection::<serde_json::Value>("hog_pattern")
+        .create_index(IndexModel::builder().keys(doc! { "count": -1 }).build())
+        .await?;
+
+    let anomaly_indexes = vec![
+        IndexModel::builder()
+            .keys(doc! { "detected_at": -1 })
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "log_source": 1, "detected_at": -1 })
+            .build(),
+    ];
+    db.collection::<serde_json::Value>("anomaly")
+        .create_indexes(anomaly_indexes)
+        .await?;
+
+    db.collection::<serde_json::Value>("anomaly_baseline")
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "log_source": 1, "log_level": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+        )
+        .await?;
+
+    let alert_indexes = vec![
+        IndexModel::builder()
+            .keys(doc! { "rule_id": 1, "group_key": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(Duration::from_secs(0))
+                    .build(),
+            )
+            .build(),
+    ];
+    db.collection::<serde_json::Value>("alert")
+        .create_indexes(alert_indexes)
+        .await?;
+
+    let alert_event_indexes = vec![
+        IndexModel::builder()
+            .keys(doc! { "rule_id": 1, "hog_uuid": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { "hog_uuid": { "$type": "string" } })
+                    .build(),
+            )
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "matched_at": -1 })
+            .build(),
+        IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(Duration::from_secs(0))
+                    .build(),
+            )
+            .build(),
+    ];
+    db.collection::<serde_json::Value>("alert_event")
+        .create_indexes(alert_event_indexes)
+        .await?;
+
+    db.collection::<serde_json::Value>("alert_silence")
+        .create_index(IndexModel::builder().keys(doc! { "ends_at": -1 }).build())
+        .await?;
+
     Ok(())
 }
 
@@ -0,0 +1,517 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Query};
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::alert::{
+    validate_alert_rule, validate_silence, AlertEventQuery, AlertQuery, SilenceQuery,
+};
+use crate::models::date_math;
+use crate::models::notification::validate_notification_channel;
+use crate::services::alert_service::AlertService;
+
+const ALERT_EVENTS_DEFAULT_LIMIT: i64 = 100;
+const ALERT_EVENTS_MAX_LIMIT: i64 = 1000;
+
+pub async fn list_alerts(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Query(query): Query<AlertQuery>,
+) -> impl IntoResponse {
+    match alert_service.list_alerts(&query).await {
+        Ok(alerts) => Json(alerts).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch alerts: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_alert_events(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Query(query): Query<AlertEventQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(ALERT_EVENTS_DEFAULT_LIMIT);
+    if !(1..=ALERT_EVENTS_MAX_LIMIT).contains(&limit) {
+        return api_error!(
+            BadRequest,
+            "Validation error",
+            "limit",
+            format!("limit must be between 1 and {}", ALERT_EVENTS_MAX_LIMIT)
+        )
+        .into_response();
+    }
+
+    let since = match query
+        .since
+        .as_deref()
+        .map(|since| date_math::resolve(since, chrono::Utc::now(), false))
+        .transpose()
+    {
+        Ok(since) => since,
+        Err(message) => {
+            return api_error!(BadRequest, "Validation error", "since", message).into_response();
+        }
+    };
+
+    match alert_service
+        .list_events(query.rule_id.as_deref(), since, limit)
+        .await
+    {
+        Ok(events) => Json(events).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch alert events: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn create_alert_rule(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_alert_rule(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match alert_service
+        .missing_channels(&valid_request.channels)
+        .await
+    {
+        Ok(missing) if !missing.is_empty() => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: missing
+                    .into_iter()
+                    .map(|id| ApiErrorField {
+                        field: "channels".to_string(),
+                        message: format!("No notification channel with id {}", id),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+        Ok(_) => {}
+        Err(err) => {
+            let error_message = format!("Failed to fetch notification channels: {}", err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response();
+        }
+    }
+
+    match alert_service.create_rule(valid_request).await {
+        Ok(rule) => (StatusCode::CREATED, Json(rule)).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create alert rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_alert_rules(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+) -> impl IntoResponse {
+    match alert_service.list_rules().await {
+        Ok(rules) => Json(rules).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch alert rules: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_alert_rule(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.get_rule(&id).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => alert_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch alert rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn update_alert_rule(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_alert_rule(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match alert_service
+        .missing_channels(&valid_request.channels)
+        .await
+    {
+        Ok(missing) if !missing.is_empty() => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: missing
+                    .into_iter()
+                    .map(|id| ApiErrorField {
+                        field: "channels".to_string(),
+                        message: format!("No notification channel with id {}", id),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+        Ok(_) => {}
+        Err(err) => {
+            let error_message = format!("Failed to fetch notification channels: {}", err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response();
+        }
+    }
+
+    match alert_service.update_rule(&id, valid_request).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => alert_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to update alert rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_alert_rule(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.delete_rule(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => alert_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete alert rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn create_notification_channel(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_notification_channel(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match alert_service.create_channel(valid_request).await {
+        Ok(channel) => (StatusCode::CREATED, Json(channel)).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create notification channel: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_notification_channels(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+) -> impl IntoResponse {
+    match alert_service.list_channels().await {
+        Ok(channels) => Json(channels).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch notification channels: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_notification_channel(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.get_channel(&id).await {
+        Ok(Some(channel)) => Json(channel).into_response(),
+        Ok(None) => notification_channel_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch notification channel: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn update_notification_channel(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_notification_channel(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match alert_service.update_channel(&id, valid_request).await {
+        Ok(Some(channel)) => Json(channel).into_response(),
+        Ok(None) => notification_channel_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to update notification channel: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_notification_channel(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.delete_channel(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => notification_channel_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete notification channel: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn test_notification_channel(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.test_channel(&id).await {
+        Ok(Some(Ok(()))) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Some(Err(message))) => ApiError::Other {
+            status_code: 502,
+            message: "Test notification failed".to_string(),
+            fields: vec![ApiErrorField {
+                field: "target".to_string(),
+                message,
+            }]
+            .into(),
+        }
+        .into_response(),
+        Ok(None) => notification_channel_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch notification channel: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn create_silence(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_silence(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match alert_service.create_silence(valid_request).await {
+        Ok(silence) => (StatusCode::CREATED, Json(silence)).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create silence: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_silences(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Query(query): Query<SilenceQuery>,
+) -> impl IntoResponse {
+    match alert_service
+        .list_silences(query.active.unwrap_or(true))
+        .await
+    {
+        Ok(silences) => Json(silences).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch silences: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_silence(
+    Extension(alert_service): Extension<Arc<AlertService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match alert_service.delete_silence(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => api_error!(
+            NotFound,
+            "Silence not found",
+            "id",
+            format!("No silence with id {}", id)
+        )
+        .into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete silence: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+fn alert_rule_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Alert rule not found",
+        "id",
+        format!("No alert rule with id {}", id)
+    )
+}
+
+fn notification_channel_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Notification channel not found",
+        "id",
+        format!("No notification channel with id {}", id)
+    )
+}
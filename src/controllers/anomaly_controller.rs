@@ -0,0 +1,62 @@
+use axum::extract::Query;
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::anomaly::AnomalyQuery;
+use crate::models::date_math;
+use crate::services::anomaly_service::AnomalyService;
+
+const ANOMALIES_DEFAULT_LIMIT: i64 = 100;
+const ANOMALIES_MAX_LIMIT: i64 = 1000;
+
+pub async fn list_anomalies(
+    Extension(anomaly_service): Extension<Arc<AnomalyService>>,
+    Query(query): Query<AnomalyQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(ANOMALIES_DEFAULT_LIMIT);
+    if !(1..=ANOMALIES_MAX_LIMIT).contains(&limit) {
+        return api_error!(
+            BadRequest,
+            "Validation error",
+            "limit",
+            format!("limit must be between 1 and {}", ANOMALIES_MAX_LIMIT)
+        )
+        .into_response();
+    }
+
+    let since = match query
+        .since
+        .as_deref()
+        .map(|since| date_math::resolve(since, chrono::Utc::now(), false))
+        .transpose()
+    {
+        Ok(since) => since,
+        Err(message) => {
+            return api_error!(BadRequest, "Validation error", "since", message).into_response();
+        }
+    };
+
+    match anomaly_service.list_anomalies(&query, since, limit).await {
+        Ok(anomalies) => Json(anomalies).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch anomalies: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_baselines(
+    Extension(anomaly_service): Extension<Arc<AnomalyService>>,
+    Query(query): Query<AnomalyQuery>,
+) -> impl IntoResponse {
+    match anomaly_service.list_baselines(&query).await {
+        Ok(baselines) => Json(baselines).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch volume baselines: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Query};
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::heartbeat::{validate_heartbeat_rule, SourceStatusQuery};
+use crate::services::heartbeat_service::HeartbeatService;
+
+pub async fn create_heartbeat_rule(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_heartbeat_rule(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match heartbeat_service.create_rule(valid_request).await {
+        Ok(rule) => (StatusCode::CREATED, Json(rule)).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create heartbeat rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_heartbeat_rules(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+) -> impl IntoResponse {
+    match heartbeat_service.list_rules().await {
+        Ok(rules) => Json(rules).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch heartbeat rules: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_heartbeat_rule(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match heartbeat_service.get_rule(&id).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => heartbeat_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch heartbeat rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn update_heartbeat_rule(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+    Path(id): Path<String>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_heartbeat_rule(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match heartbeat_service.update_rule(&id, valid_request).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => heartbeat_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to update heartbeat rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_heartbeat_rule(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match heartbeat_service.delete_rule(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => heartbeat_rule_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete heartbeat rule: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn source_status(
+    Extension(heartbeat_service): Extension<Arc<HeartbeatService>>,
+    Query(query): Query<SourceStatusQuery>,
+) -> impl IntoResponse {
+    match heartbeat_service.source_statuses(query.state).await {
+        Ok(statuses) => Json(statuses).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch source status: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+fn heartbeat_rule_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Heartbeat rule not found",
+        "id",
+        format!("No heartbeat rule with id {}", id)
+    )
+}
@@ -1,5 +1,15 @@
+use async_compression::tokio::bufread::GzipEncoder;
+use axum::body::Body;
 use axum::extract::rejection::JsonRejection;
+use axum::extract::ws::{
+    rejection::WebSocketUpgradeRejection, Message, WebSocket, WebSocketUpgrade,
+};
+use axum::extract::{Path, Query};
+use axum::http::{header, HeaderMap};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+use futures::{Stream, StreamExt};
+use tokio_util::io::{ReaderStream, StreamReader};
 
 use crate::metrics::{
     HOGS_CREATED_TOTAL, HOGS_FETCHED_TOTAL, HOGS_SEARCHED_TOTAL, REQUEST_DURATION_SECONDS,
@@ -7,18 +17,45 @@ use crate::metrics::{
 use prometheus::{gather, Encoder, TextEncoder};
 
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::api_error;
 use crate::errors::{ApiError, ApiErrorField};
+use crate::models::annotations::validate_annotations;
+use crate::models::context::ContextQuery;
+use crate::models::date_math;
+use crate::models::export::{ExportFormat, ExportQuery};
 use crate::models::hog_client_schema::validate;
+use crate::models::lag::SourceLagQuery;
 use crate::models::options::validate_options;
+use crate::models::tail::TailEvent;
 use crate::services::hog_service::HogService;
+use crate::services::saved_search_service::SavedSearchService;
+
+use super::saved_search_controller::resolve_saved_search;
+
+/// Whether the caller's `Authorization` header carries the decrypt scope.
+pub fn decrypt_scope(hog_service: &HogService, headers: &HeaderMap) -> bool {
+    hog_service.can_decrypt(
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok()),
+    )
+}
 
-pub async fn get_hogs(Extension(hog_service): Extension<Arc<HogService>>) -> impl IntoResponse {
+pub async fn get_hogs(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
     let timer = REQUEST_DURATION_SECONDS.start_timer();
+    let decrypt = decrypt_scope(&hog_service, &headers);
     match hog_service.get_hogs().await {
-        Ok(hogs) => {
+        Ok(mut hogs) => {
             HOGS_FETCHED_TOTAL.inc_by(hogs.len() as u64);
+            for hog in &mut hogs {
+                hog_service.reveal(hog, decrypt);
+            }
             timer.observe_duration();
             Json(hogs).into_response()
         }
@@ -51,7 +88,7 @@ pub async fn create_hog(
         }
     };
 
-    let valid_request = match validate(payload).await {
+    let valid_request = match validate(payload, hog_service.timestamps()).await {
         Ok(valid_request) => valid_request,
         Err(validation_error) => {
             return ApiError::BadRequest {
@@ -71,11 +108,17 @@ pub async fn create_hog(
     };
 
     match hog_service.create_hog(valid_request).await {
-        Ok(hog) => {
+        Ok(Some(hog)) => {
             timer.observe_duration();
             HOGS_CREATED_TOTAL.inc();
             Json(hog).into_response()
         }
+        // Dropped by a sampling rule or held by a multiline rule: accepted,
+        // just not stored (yet).
+        Ok(None) => {
+            timer.observe_duration();
+            StatusCode::ACCEPTED.into_response()
+        }
         Err(err) => {
             timer.observe_duration();
             ApiError::InternalServerError {
@@ -92,6 +135,8 @@ pub async fn create_hog(
 
 pub async fn handle_search(
     Extension(hog_service): Extension<Arc<HogService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    headers: HeaderMap,
     payload: Result<Json<Value>, JsonRejection>,
 ) -> impl IntoResponse {
     let timer = REQUEST_DURATION_SECONDS.start_timer();
@@ -116,6 +161,14 @@ pub async fn handle_search(
         }
     };
 
+    let payload = match resolve_saved_search(&saved_search_service, payload).await {
+        Ok(payload) => payload,
+        Err(err) => {
+            timer.observe_duration();
+            return err.into_response();
+        }
+    };
+
     let valid_request = match validate_options(payload).await {
         Ok(valid_request) => valid_request,
         Err(validation_error) => {
@@ -139,16 +192,418 @@ pub async fn handle_search(
     };
 
     let response = match hog_service.search_hogs(valid_request).await {
-        Ok(hogs) => Json(hogs).into_response(),
-        Err(err) => {
-            let error_message = format!("Failed to fetch hogs: {}", err);
-            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        Ok(mut hogs) => {
+            let decrypt = decrypt_scope(&hog_service, &headers);
+            for hog in &mut hogs {
+                hog_service.reveal(hog, decrypt);
+            }
+            Json(hogs).into_response()
         }
+        Err(err) => ApiError::from(err).into_response(),
     };
     timer.observe_duration();
     response
 }
 
+pub async fn explain_search(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let payload = match resolve_saved_search(&saved_search_service, payload).await {
+        Ok(payload) => payload,
+        Err(err) => return err.into_response(),
+    };
+
+    let valid_request = match validate_options(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match hog_service.explain_search(&valid_request).await {
+        Ok(explain) => Json(explain).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to explain search: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn export_hogs(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Query(query): Query<ExportQuery>,
+    headers: HeaderMap,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let payload = match resolve_saved_search(&saved_search_service, payload).await {
+        Ok(payload) => payload,
+        Err(err) => return err.into_response(),
+    };
+
+    let valid_request = match validate_options(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let format = query.format.unwrap_or_default();
+    let log_data_columns = match (format, query.columns) {
+        (ExportFormat::Csv, Some(columns)) => Some(
+            columns
+                .split(',')
+                .map(str::trim)
+                .filter(|column| !column.is_empty())
+                .map(String::from)
+                .collect(),
+        ),
+        (ExportFormat::Csv, None) => None,
+        _ => Some(Vec::new()),
+    };
+
+    let stream = match hog_service
+        .export_hogs(
+            &valid_request,
+            format,
+            log_data_columns,
+            decrypt_scope(&hog_service, &headers),
+        )
+        .await
+    {
+        Ok(stream) => stream,
+        Err(err) => {
+            let error_message = format!("Failed to export hogs: {}", err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response();
+        }
+    };
+
+    let filename = format!("hogs.{}", format.extension());
+    if query.gzip.unwrap_or(false) {
+        let gzipped = ReaderStream::new(GzipEncoder::new(StreamReader::new(stream)));
+        (
+            [
+                (header::CONTENT_TYPE, "application/gzip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.gz\"", filename),
+                ),
+            ],
+            Body::from_stream(gzipped),
+        )
+            .into_response()
+    } else {
+        (
+            [
+                (header::CONTENT_TYPE, format.content_type().to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ],
+            Body::from_stream(stream),
+        )
+            .into_response()
+    }
+}
+
+pub async fn get_hog(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match hog_service.get_hog(&id).await {
+        Ok(Some(mut hog)) => {
+            HOGS_FETCHED_TOTAL.inc();
+            hog_service.reveal(&mut hog, decrypt_scope(&hog_service, &headers));
+            Json(hog).into_response()
+        }
+        Ok(None) => hog_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch hog: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_hog(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match hog_service.delete_hog(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => hog_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete hog: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn annotate_hog(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let valid_request = match validate_annotations(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match hog_service.annotate_hog(&id, valid_request).await {
+        Ok(Some(mut hog)) => {
+            hog_service.reveal(&mut hog, decrypt_scope(&hog_service, &headers));
+            Json(hog).into_response()
+        }
+        Ok(None) => hog_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to annotate hog: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+fn hog_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Hog not found",
+        "id",
+        format!("No hog with id or hog_uuid {}", id)
+    )
+}
+
+const CONTEXT_DEFAULT_SIZE: i64 = 10;
+const CONTEXT_MAX_SIZE: i64 = 1000;
+const SOURCE_LAG_DEFAULT_SINCE: &str = "now-1h";
+
+pub async fn hog_context(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Path(id): Path<String>,
+    Query(query): Query<ContextQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let before = query.before.unwrap_or(CONTEXT_DEFAULT_SIZE);
+    let after = query.after.unwrap_or(CONTEXT_DEFAULT_SIZE);
+
+    let mut errors = Vec::new();
+    for (field, value) in [("before", before), ("after", after)] {
+        if !(0..=CONTEXT_MAX_SIZE).contains(&value) {
+            errors.push(ApiErrorField {
+                field: field.to_string(),
+                message: format!("{} must be between 0 and {}", field, CONTEXT_MAX_SIZE),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return ApiError::BadRequest {
+            message: "Validation error".to_string(),
+            fields: Some(errors),
+        }
+        .into_response();
+    }
+
+    match hog_service.hog_context(&id, before, after).await {
+        Ok(Some(mut context)) => {
+            let decrypt = decrypt_scope(&hog_service, &headers);
+            hog_service.reveal(&mut context.anchor, decrypt);
+            for hog in context.before.iter_mut().chain(context.after.iter_mut()) {
+                hog_service.reveal(hog, decrypt);
+            }
+            Json(context).into_response()
+        }
+        Ok(None) => hog_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch hog context: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn tail_hogs(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> impl IntoResponse {
+    let valid_request = match validate_options(query_to_payload(params)).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let events = match hog_service.tail_hogs(valid_request, decrypt_scope(&hog_service, &headers)) {
+        Ok(events) => events,
+        Err(message) => {
+            return api_error!(BadRequest, "Validation error", "log_message", message)
+                .into_response();
+        }
+    };
+
+    match ws {
+        Ok(ws) => ws.on_upgrade(move |socket| tail_over_websocket(socket, events)),
+        Err(_) => Sse::new(events.map(|event| {
+            match event {
+                TailEvent::Hog { hog } => Event::default().event("hog").json_data(hog),
+                TailEvent::Dropped { dropped } => Event::default()
+                    .event("dropped")
+                    .json_data(serde_json::json!({ "dropped": dropped })),
+            }
+        }))
+        .keep_alive(KeepAlive::default())
+        .into_response(),
+    }
+}
+
+async fn tail_over_websocket(mut socket: WebSocket, events: impl Stream<Item = TailEvent>) {
+    let mut events = std::pin::pin!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let text = match serde_json::to_string(&event) {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Query strings only carry strings; lift the non-string search options back
+/// into JSON so they go through `validate_options` like a search body would.
+fn query_to_payload(params: HashMap<String, String>) -> Value {
+    const JSON_FIELDS: [&str; 8] = [
+        "hog_limit",
+        "hog_partial",
+        "hog_case_sensitive",
+        "hog_fields",
+        "log_data",
+        "log_data_value",
+        "log_data_values",
+        "log_data_fields",
+    ];
+
+    let payload = params
+        .into_iter()
+        .map(|(key, value)| {
+            let value = if JSON_FIELDS.contains(&key.as_str()) {
+                serde_json::from_str(&value).unwrap_or(Value::String(value))
+            } else {
+                Value::String(value)
+            };
+            (key, value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    Value::Object(payload)
+}
+
 pub async fn hog_statistics(
     Extension(hog_service): Extension<Arc<HogService>>,
 ) -> impl IntoResponse {
@@ -161,6 +616,33 @@ pub async fn hog_statistics(
     }
 }
 
+pub async fn source_lag(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Query(query): Query<SourceLagQuery>,
+) -> impl IntoResponse {
+    let since = match date_math::resolve(
+        query.since.as_deref().unwrap_or(SOURCE_LAG_DEFAULT_SINCE),
+        chrono::Utc::now(),
+        false,
+    ) {
+        Ok(since) => since,
+        Err(message) => {
+            return api_error!(BadRequest, "Validation error", "since", message).into_response();
+        }
+    };
+
+    match hog_service
+        .source_lag(query.log_source.as_deref(), since)
+        .await
+    {
+        Ok(lags) => Json(lags).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch source lag: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
 pub async fn hog_stats(Extension(hog_service): Extension<Arc<HogService>>) -> impl IntoResponse {
     match hog_service.hog_stats().await {
         Ok(stats) => Json(stats).into_response(),
@@ -1 +1,7 @@
-pub mod hog_controller;
\ No newline at end of file
+pub mod alert_controller;
+pub mod anomaly_controller;
+pub mod heartbeat_controller;
+pub mod hog_controller;
+pub mod pattern_controller;
+pub mod saved_search_controller;
+pub mod search_job_controller;
\ No newline at end of file
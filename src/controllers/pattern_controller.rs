@@ -0,0 +1,111 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::Query;
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::options::validate_options;
+use crate::models::pattern::PatternsQuery;
+use crate::services::pattern_service::PatternService;
+use crate::services::saved_search_service::SavedSearchService;
+
+use super::saved_search_controller::resolve_saved_search;
+
+const PATTERNS_DEFAULT_LIMIT: i64 = 100;
+const PATTERNS_MAX_LIMIT: i64 = 1000;
+
+pub async fn list_patterns(
+    Extension(pattern_service): Extension<Arc<PatternService>>,
+    Query(query): Query<PatternsQuery>,
+) -> impl IntoResponse {
+    let limit = match patterns_limit(&query) {
+        Ok(limit) => limit,
+        Err(err) => return err.into_response(),
+    };
+
+    match pattern_service.list_patterns(limit).await {
+        Ok(patterns) => Json(patterns).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch patterns: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn mine_patterns(
+    Extension(pattern_service): Extension<Arc<PatternService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Query(query): Query<PatternsQuery>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let limit = match patterns_limit(&query) {
+        Ok(limit) => limit,
+        Err(err) => return err.into_response(),
+    };
+
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let payload = match resolve_saved_search(&saved_search_service, payload).await {
+        Ok(payload) => payload,
+        Err(err) => return err.into_response(),
+    };
+
+    let valid_request = match validate_options(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match pattern_service
+        .mine_patterns(valid_request, limit as usize)
+        .await
+    {
+        Ok(patterns) => Json(patterns).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to mine patterns: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+fn patterns_limit(query: &PatternsQuery) -> Result<i64, ApiError> {
+    let limit = query.limit.unwrap_or(PATTERNS_DEFAULT_LIMIT);
+    if !(1..=PATTERNS_MAX_LIMIT).contains(&limit) {
+        return Err(api_error!(
+            BadRequest,
+            "Validation error",
+            "limit",
+            format!("limit must be between 1 and {}", PATTERNS_MAX_LIMIT)
+        ));
+    }
+    Ok(limit)
+}
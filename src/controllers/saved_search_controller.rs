@@ -0,0 +1,244 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Query};
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::options::{validate_options, ApiErrorSchema};
+use crate::models::saved_search::{
+    merge_options, validate_saved_search, SavedSearchListQuery, SAVED_SEARCH_OVERRIDES,
+};
+use crate::services::hog_service::HogService;
+use crate::services::saved_search_service::{is_duplicate_key_error, SavedSearchService};
+
+pub async fn create_saved_search(
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => return invalid_payload(e).into_response(),
+    };
+
+    let valid_request = match validate_saved_search(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return validation_failed(validation_error.errors).into_response();
+        }
+    };
+
+    match saved_search_service
+        .create_saved_search(valid_request)
+        .await
+    {
+        Ok(saved_search) => (StatusCode::CREATED, Json(saved_search)).into_response(),
+        Err(err) if is_duplicate_key_error(&err) => duplicate_name().into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create saved search: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn list_saved_searches(
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Query(query): Query<SavedSearchListQuery>,
+) -> impl IntoResponse {
+    match saved_search_service.list_saved_searches(query.owner).await {
+        Ok(saved_searches) => Json(saved_searches).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch saved searches: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_saved_search(
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match saved_search_service.get_saved_search(&id).await {
+        Ok(Some(saved_search)) => Json(saved_search).into_response(),
+        Ok(None) => saved_search_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch saved search: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn update_saved_search(
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Path(id): Path<String>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => return invalid_payload(e).into_response(),
+    };
+
+    let valid_request = match validate_saved_search(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return validation_failed(validation_error.errors).into_response();
+        }
+    };
+
+    match saved_search_service
+        .update_saved_search(&id, valid_request)
+        .await
+    {
+        Ok(Some(saved_search)) => Json(saved_search).into_response(),
+        Ok(None) => saved_search_not_found(&id).into_response(),
+        Err(err) if is_duplicate_key_error(&err) => duplicate_name().into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to update saved search: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_saved_search(
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match saved_search_service.delete_saved_search(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => saved_search_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete saved search: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+/// Runs a saved search. The body is optional and may only override the time
+/// range and `hog_limit`.
+pub async fn run_saved_search(
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    Path(id): Path<String>,
+    payload: Option<Json<Value>>,
+) -> impl IntoResponse {
+    let saved_search = match saved_search_service.get_saved_search(&id).await {
+        Ok(Some(saved_search)) => saved_search,
+        Ok(None) => return saved_search_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch saved search: {}", err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response();
+        }
+    };
+
+    let overrides = payload.map(|Json(payload)| payload).unwrap_or(Value::Null);
+    let options = match merge_options(
+        &saved_search.options,
+        &overrides,
+        Some(&SAVED_SEARCH_OVERRIDES),
+    ) {
+        Ok(options) => options,
+        Err(errors) => return validation_failed(errors).into_response(),
+    };
+
+    let valid_request = match validate_options(options).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match hog_service.search_hogs(valid_request).await {
+        Ok(hogs) => Json(hogs).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+/// Lets search-style endpoints take a `saved_search_id`: the saved search's
+/// options become the base and the rest of the payload is laid over them.
+pub(crate) async fn resolve_saved_search(
+    saved_search_service: &SavedSearchService,
+    payload: Value,
+) -> Result<Value, ApiError> {
+    let Some(id) = payload.get("saved_search_id") else {
+        return Ok(payload);
+    };
+    let Some(id) = id.as_str() else {
+        return Err(api_error!(
+            BadRequest,
+            "Validation error",
+            "saved_search_id",
+            "saved_search_id must be a string"
+        ));
+    };
+
+    match saved_search_service.get_saved_search(id).await {
+        Ok(Some(saved_search)) => {
+            merge_options(&saved_search.options, &payload, None).map_err(validation_failed)
+        }
+        Ok(None) => Err(saved_search_not_found(id)),
+        Err(err) => Err(ApiError::InternalServerError {
+            message: format!("Failed to fetch saved search: {}", err),
+            fields: None,
+        }),
+    }
+}
+
+fn invalid_payload(e: JsonRejection) -> ApiError {
+    ApiError::BadRequest {
+        message: "Payload must be a valid JSON object".to_string(),
+        fields: vec![ApiErrorField {
+            field: "trace".to_string(),
+            message: e.to_string(),
+        }]
+        .into(),
+    }
+}
+
+fn validation_failed(errors: Vec<ApiErrorSchema>) -> ApiError {
+    ApiError::BadRequest {
+        message: "Validation error".to_string(),
+        fields: errors
+            .into_iter()
+            .map(|e| ApiErrorField {
+                field: e.field,
+                message: e.message,
+            })
+            .collect::<Vec<_>>()
+            .into(),
+    }
+}
+
+fn duplicate_name() -> ApiError {
+    ApiError::Other {
+        status_code: 409,
+        message: "Saved search already exists".to_string(),
+        fields: vec![ApiErrorField {
+            field: "name".to_string(),
+            message: "owner already has a saved search with this name".to_string(),
+        }]
+        .into(),
+    }
+}
+
+fn saved_search_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Saved search not found",
+        "id",
+        format!("No saved search with id {}", id)
+    )
+}
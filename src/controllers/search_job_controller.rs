@@ -0,0 +1,161 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
+use axum::{extract::Extension, http::StatusCode, response::IntoResponse, Json};
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api_error;
+use crate::errors::{ApiError, ApiErrorField};
+use crate::models::options::validate_options;
+use crate::models::search_job::SearchJobResultsQuery;
+use crate::services::hog_service::HogService;
+use crate::services::saved_search_service::SavedSearchService;
+use crate::services::search_job_service::SearchJobService;
+
+use super::hog_controller::decrypt_scope;
+
+use super::saved_search_controller::resolve_saved_search;
+
+const RESULTS_DEFAULT_LIMIT: i64 = 1000;
+const RESULTS_MAX_LIMIT: i64 = 10000;
+
+pub async fn create_search_job(
+    Extension(search_job_service): Extension<Arc<SearchJobService>>,
+    Extension(saved_search_service): Extension<Arc<SavedSearchService>>,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> impl IntoResponse {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => {
+            return ApiError::BadRequest {
+                message: "Payload must be a valid JSON object".to_string(),
+                fields: vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: e.to_string(),
+                }]
+                .into(),
+            }
+            .into_response();
+        }
+    };
+
+    let payload = match resolve_saved_search(&saved_search_service, payload).await {
+        Ok(payload) => payload,
+        Err(err) => return err.into_response(),
+    };
+
+    let valid_request = match validate_options(payload).await {
+        Ok(valid_request) => valid_request,
+        Err(validation_error) => {
+            return ApiError::BadRequest {
+                message: "Validation error".to_string(),
+                fields: validation_error
+                    .errors
+                    .iter()
+                    .map(|e| ApiErrorField {
+                        field: e.field.clone(),
+                        message: e.message.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            }
+            .into_response();
+        }
+    };
+
+    match search_job_service.create_job(valid_request).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to create search job: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_search_job(
+    Extension(search_job_service): Extension<Arc<SearchJobService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match search_job_service.get_job(&id).await {
+        Ok(Some(job)) => Json(job).into_response(),
+        Ok(None) => search_job_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch search job: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn get_search_job_results(
+    Extension(search_job_service): Extension<Arc<SearchJobService>>,
+    Extension(hog_service): Extension<Arc<HogService>>,
+    Path(id): Path<String>,
+    Query(query): Query<SearchJobResultsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let after = query.after.unwrap_or(0);
+    let limit = query.limit.unwrap_or(RESULTS_DEFAULT_LIMIT);
+    if !(1..=RESULTS_MAX_LIMIT).contains(&limit) {
+        return api_error!(
+            BadRequest,
+            "Validation error",
+            "limit",
+            format!("limit must be between 1 and {}", RESULTS_MAX_LIMIT)
+        )
+        .into_response();
+    }
+
+    match search_job_service.get_results(&id, after, limit).await {
+        Ok(Some(mut results)) => {
+            let decrypt = decrypt_scope(&hog_service, &headers);
+            for hog in &mut results.hogs {
+                hog_service.reveal(hog, decrypt);
+            }
+            Json(results).into_response()
+        }
+        Ok(None) => search_job_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to fetch search job results: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn cancel_search_job(
+    Extension(search_job_service): Extension<Arc<SearchJobService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match search_job_service.cancel_job(&id).await {
+        Ok(Some(job)) => Json(job).into_response(),
+        Ok(None) => search_job_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to cancel search job: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+pub async fn delete_search_job(
+    Extension(search_job_service): Extension<Arc<SearchJobService>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match search_job_service.delete_job(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => search_job_not_found(&id).into_response(),
+        Err(err) => {
+            let error_message = format!("Failed to delete search job: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+        }
+    }
+}
+
+fn search_job_not_found(id: &str) -> ApiError {
+    api_error!(
+        NotFound,
+        "Search job not found",
+        "id",
+        format!("No search job with id {}", id)
+    )
+}
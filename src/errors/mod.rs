@@ -60,6 +60,48 @@ impl std::fmt::Display for SomeCreateError {
 
 impl Error for SomeCreateError {}
 
+/// Why a search didn't produce results.
+#[derive(Debug)]
+pub enum SearchError {
+    /// Refused up front by the search guardrails.
+    Rejected {
+        field: String,
+        message: String,
+    },
+    /// Mongo gave up after `maxTimeMS`.
+    TimedOut,
+    Database(mongodb::error::Error),
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::Rejected { message, .. } => write!(f, "{}", message),
+            SearchError::TimedOut => write!(f, "search exceeded its time limit"),
+            SearchError::Database(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SearchError {}
+
+impl From<mongodb::error::Error> for SearchError {
+    fn from(err: mongodb::error::Error) -> Self {
+        match *err.kind {
+            mongodb::error::ErrorKind::Command(ref command_error) if command_error.code == 50 => {
+                SearchError::TimedOut
+            }
+            _ => SearchError::Database(err),
+        }
+    }
+}
+
+impl From<bson::de::Error> for SearchError {
+    fn from(err: bson::de::Error) -> Self {
+        SearchError::Database(err.into())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ApiError {
     BadRequest {
@@ -195,3 +237,30 @@ impl From<SomeCreateError> for ApiError {
         }
     }
 }
+
+impl From<SearchError> for ApiError {
+    fn from(err: SearchError) -> Self {
+        match err {
+            SearchError::Rejected { field, message } => ApiError::BadRequest {
+                message: "Search rejected by guardrails".to_string(),
+                fields: Some(vec![ApiErrorField { field, message }]),
+            },
+            SearchError::TimedOut => ApiError::Other {
+                status_code: StatusCode::GATEWAY_TIMEOUT.as_u16(),
+                message: "Search exceeded SEARCH_MAX_TIME_MS".to_string(),
+                fields: Some(vec![ApiErrorField {
+                    field: "search".to_string(),
+                    message: "Narrow the time range, add an indexed field or use /search-jobs"
+                        .to_string(),
+                }]),
+            },
+            SearchError::Database(err) => ApiError::InternalServerError {
+                message: "Failed to fetch hogs".to_string(),
+                fields: Some(vec![ApiErrorField {
+                    field: "trace".to_string(),
+                    message: err.to_string(),
+                }]),
+            },
+        }
+    }
+}
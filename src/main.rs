@@ -7,9 +7,17 @@ mod utils;
 mod validator;
 mod errors;
 mod metrics;
+mod notifications;
 
+use axum_server::Handle;
 use dotenv::dotenv;
-use std::{env, net::SocketAddr};
+use std::{env, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::signal::unix::{signal, SignalKind};
+
+use services::hog_service::HogService;
+
+/// How long open requests and tails get to finish once a shutdown starts.
+const SHUTDOWN_GRACE_SECS: u64 = 10;
 
 #[tokio::main]
 async fn main() {
@@ -17,7 +25,9 @@ async fn main() {
     let db = config::init_db().await.expect("Failed to connect to DB");
     let rabbit_channel = config::init_rabbitmq().await.expect("Failed to connect to RabbitMQ");
 
-    let app = routes::create_router(db, rabbit_channel);
+    let hog_service = Arc::new(HogService::new(&db, rabbit_channel));
+    hog_service.spawn();
+    let app = routes::create_router(db, Arc::clone(&hog_service));
 
     let port = env::var("PORT")
         .ok()
@@ -26,8 +36,28 @@ async fn main() {
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("Server running at http://{}", addr);
+    let handle = Handle::new();
+    tokio::spawn(shutdown_on_signal(handle.clone()));
     axum_server::bind(addr)
+        .handle(handle)
         .serve(app.into_make_service())
         .await
         .unwrap();
+
+    // Multiline events still open would otherwise be lost with the process.
+    hog_service.flush_multiline().await;
+}
+
+/// Stops taking requests on SIGTERM or Ctrl-C and lets the open ones finish.
+async fn shutdown_on_signal(handle: Handle) {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+    println!("Shutting down...");
+    handle.graceful_shutdown(Some(Duration::from_secs(config::env_or(
+        "SHUTDOWN_GRACE_SECS",
+        SHUTDOWN_GRACE_SECS,
+    ))));
 }
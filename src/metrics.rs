@@ -1,4 +1,7 @@
-use prometheus::{IntCounter, Histogram, register_int_counter, register_histogram};
+use prometheus::{
+    GaugeVec, Histogram, IntCounter, IntCounterVec, IntGaugeVec, register_gauge_vec,
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge_vec,
+};
 
 lazy_static::lazy_static! {
     pub static ref HOGS_FETCHED_TOTAL: IntCounter =
@@ -7,6 +10,22 @@ lazy_static::lazy_static! {
         register_int_counter!("hogs_searched_total", "Total number of hogs searched").unwrap();
     pub static ref HOGS_CREATED_TOTAL: IntCounter =
         register_int_counter!("hogs_created_total", "Total number of hogs created").unwrap();
+    pub static ref HOGS_EXPORTED_TOTAL: IntCounter =
+        register_int_counter!("hogs_exported_total", "Total number of hogs exported").unwrap();
+    pub static ref HOGS_REDACTED_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hogs_redacted_total", "Total number of values redacted before publishing", &["rule"]).unwrap();
+    pub static ref HOGS_SAMPLED_OUT_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hogs_sampled_out_total", "Total number of hogs dropped by sampling rules before publishing", &["rule", "reason"]).unwrap();
+    pub static ref HOGS_TAIL_DROPPED_TOTAL: IntCounter =
+        register_int_counter!("hogs_tail_dropped_total", "Total number of hogs dropped from live tails").unwrap();
+    pub static ref HOG_VOLUME_OBSERVED: GaugeVec =
+        register_gauge_vec!("hog_volume_observed", "Hogs counted in the last anomaly detection window", &["log_source", "log_level"]).unwrap();
+    pub static ref HOG_VOLUME_EXPECTED: GaugeVec =
+        register_gauge_vec!("hog_volume_expected", "Baseline hogs per anomaly detection window", &["log_source", "log_level"]).unwrap();
+    pub static ref HOG_VOLUME_ANOMALOUS: IntGaugeVec =
+        register_int_gauge_vec!("hog_volume_anomalous", "1 while the last window was flagged as anomalous", &["log_source", "log_level", "kind"]).unwrap();
+    pub static ref HOG_ANOMALIES_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hog_anomalies_total", "Total number of log volume anomalies detected", &["kind"]).unwrap();
     pub static ref REQUEST_DURATION_SECONDS: Histogram =
         register_histogram!("request_duration_seconds", "Request duration in seconds").unwrap();
     
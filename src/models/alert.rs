@@ -0,0 +1,722 @@
+use std::collections::BTreeMap;
+
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::date_math;
+use super::options::{validate_options, ApiErrorSchema, ErrorResponse};
+
+/// Default for `repeat_interval` when a rule doesn't set one.
+#[allow(dead_code)]
+pub const ALERT_REPEAT_INTERVAL_SECS: i64 = 4 * 60 * 60;
+
+/// Longest `window`, `for` or `repeat_interval` a rule may have.
+const ALERT_MAX_DURATION_SECS: i64 = 366 * 24 * 60 * 60;
+
+/// Time fields the rule's window takes over.
+#[allow(dead_code)]
+pub const ALERT_WINDOW_FIELDS: [&str; 7] = [
+    "hog_last",
+    "log_timestamp",
+    "log_timestamp_start",
+    "log_timestamp_end",
+    "hog_timestamp",
+    "hog_timestamp_start",
+    "hog_timestamp_end",
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionOp {
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Gte,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Lte,
+    #[serde(rename = "==")]
+    Eq,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertRuleKind {
+    /// Counts matching hogs over a window, evaluated by the API on a schedule.
+    #[default]
+    Threshold,
+    /// Fires on every single matching hog, evaluated by the workers as they
+    /// consume.
+    Match,
+}
+
+/// "count of matching hogs in the window `op` `threshold`".
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AlertCondition {
+    pub op: ConditionOp,
+    pub threshold: i64,
+}
+
+#[allow(dead_code)]
+impl ConditionOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionOp::Gt => ">",
+            ConditionOp::Gte => ">=",
+            ConditionOp::Lt => "<",
+            ConditionOp::Lte => "<=",
+            ConditionOp::Eq => "==",
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl AlertCondition {
+    pub fn matches(&self, count: i64) -> bool {
+        match self.op {
+            ConditionOp::Gt => count > self.threshold,
+            ConditionOp::Gte => count >= self.threshold,
+            ConditionOp::Lt => count < self.threshold,
+            ConditionOp::Lte => count <= self.threshold,
+            ConditionOp::Eq => count == self.threshold,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Search payload as accepted by `/hogs/search`, minus the time fields.
+    pub options: serde_json::Value,
+    #[serde(default)]
+    pub kind: AlertRuleKind,
+    pub window_secs: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<AlertCondition>,
+    /// How long the condition has to hold before the alert fires.
+    pub for_secs: i64,
+    pub repeat_interval_secs: i64,
+    /// Top-level hog fields or `log_data.<key>`, one alert per distinct value.
+    pub group_by: Vec<String>,
+    /// Ids of `notification_channel` documents.
+    pub channels: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub enabled: bool,
+    pub created_at: BsonDateTime,
+    pub updated_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub options: serde_json::Value,
+    #[serde(default)]
+    pub kind: AlertRuleKind,
+    pub window_secs: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<AlertCondition>,
+    pub for_secs: i64,
+    pub repeat_interval_secs: i64,
+    pub group_by: Vec<String>,
+    pub channels: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub options: serde_json::Value,
+    pub kind: AlertRuleKind,
+    pub window_secs: i64,
+    pub condition: Option<AlertCondition>,
+    pub for_secs: i64,
+    pub repeat_interval_secs: i64,
+    pub group_by: Vec<String>,
+    pub channels: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub enabled: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    /// Condition holds, waiting out the rule's `for`.
+    Pending,
+    Firing,
+    Resolved,
+}
+
+/// One rule × group. There is never more than one per `group_key`, a group
+/// that fires again reuses its resolved alert.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub group_key: String,
+    pub labels: BTreeMap<String, String>,
+    pub state: AlertState,
+    pub value: i64,
+    pub active_since: BsonDateTime,
+    pub fired_at: Option<BsonDateTime>,
+    pub resolved_at: Option<BsonDateTime>,
+    pub last_evaluated_at: BsonDateTime,
+    pub last_notified_at: Option<BsonDateTime>,
+    pub silenced: bool,
+    /// Resolved alerts are dropped by a TTL index once this passes.
+    pub expires_at: Option<BsonDateTime>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: String,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub labels: BTreeMap<String, String>,
+    pub state: AlertState,
+    pub value: i64,
+    pub active_since: DateTime<Utc>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub last_evaluated_at: DateTime<Utc>,
+    pub last_notified_at: Option<DateTime<Utc>>,
+    pub silenced: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertQuery {
+    pub state: Option<AlertState>,
+    pub rule_id: Option<String>,
+}
+
+/// A hog that matched a `match` rule, written by the worker that consumed it.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEventRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub labels: BTreeMap<String, String>,
+    pub hog_uuid: Option<String>,
+    pub log_timestamp: BsonDateTime,
+    pub log_level: Option<String>,
+    pub log_type: Option<String>,
+    pub log_source: Option<String>,
+    pub log_source_id: Option<String>,
+    pub log_message: String,
+    pub matched_at: BsonDateTime,
+    pub expires_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+impl AlertEventRecord {
+    /// API shape of the event. Lives here instead of `utils` because the
+    /// workers publish it too.
+    pub fn to_event(&self) -> AlertEvent {
+        AlertEvent {
+            id: self.id.map(|oid| oid.to_hex()),
+            rule_id: self.rule_id.clone(),
+            rule_name: self.rule_name.clone(),
+            labels: self.labels.clone(),
+            hog_uuid: self.hog_uuid.clone(),
+            log_timestamp: self.log_timestamp.to_chrono(),
+            log_level: self.log_level.clone(),
+            log_type: self.log_type.clone(),
+            log_source: self.log_source.clone(),
+            log_source_id: self.log_source_id.clone(),
+            log_message: self.log_message.clone(),
+            matched_at: self.matched_at.to_chrono(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub labels: BTreeMap<String, String>,
+    pub hog_uuid: Option<String>,
+    pub log_timestamp: DateTime<Utc>,
+    pub log_level: Option<String>,
+    pub log_type: Option<String>,
+    pub log_source: Option<String>,
+    pub log_source_id: Option<String>,
+    pub log_message: String,
+    pub matched_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEventQuery {
+    pub rule_id: Option<String>,
+    /// RFC 3339 or a `now`-based expression.
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Mutes notifications for alerts whose labels contain all of `matchers`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub matchers: BTreeMap<String, String>,
+    pub starts_at: BsonDateTime,
+    pub ends_at: BsonDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub created_by: String,
+    pub created_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+impl SilenceRecord {
+    pub fn silences(&self, labels: &BTreeMap<String, String>, now: BsonDateTime) -> bool {
+        self.starts_at <= now
+            && now < self.ends_at
+            && self
+                .matchers
+                .iter()
+                .all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Silence {
+    pub id: String,
+    pub matchers: BTreeMap<String, String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceRequest {
+    pub matchers: BTreeMap<String, String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub comment: Option<String>,
+    pub created_by: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceQuery {
+    /// Only silences that haven't ended yet.
+    pub active: Option<bool>,
+}
+
+#[allow(dead_code)]
+pub async fn validate_alert_rule(
+    req: serde_json::Value,
+) -> Result<AlertRuleRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+
+    let name = match req.get("name").and_then(|v| v.as_str()) {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "name".to_string(),
+                message: "name is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let description = req
+        .get("description")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let options = match req.get("options") {
+        Some(options) if options.is_object() => {
+            for field in ALERT_WINDOW_FIELDS {
+                if options.get(field).is_some() {
+                    errors.push(ApiErrorSchema {
+                        field: format!("options.{}", field),
+                        message: "the rule's window sets the time range".to_string(),
+                    });
+                }
+            }
+            if options.get("saved_search_id").is_some() {
+                errors.push(ApiErrorSchema {
+                    field: "options.saved_search_id".to_string(),
+                    message: "alert rules cannot be based on saved searches".to_string(),
+                });
+            }
+            if let Err(validation_error) = validate_options(options.clone()).await {
+                errors.extend(validation_error.errors.into_iter().map(|e| ApiErrorSchema {
+                    field: format!("options.{}", e.field),
+                    message: e.message,
+                }));
+            }
+            options.clone()
+        }
+        None => serde_json::json!({}),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "options".to_string(),
+                message: "options must be a valid JSON object".to_string(),
+            });
+            serde_json::Value::Null
+        }
+    };
+
+    let kind = match req.get("kind") {
+        Some(kind) => match serde_json::from_value::<AlertRuleKind>(kind.clone()) {
+            Ok(kind) => kind,
+            Err(_) => {
+                errors.push(ApiErrorSchema {
+                    field: "kind".to_string(),
+                    message: "kind must be threshold or match".to_string(),
+                });
+                AlertRuleKind::Threshold
+            }
+        },
+        None => AlertRuleKind::Threshold,
+    };
+
+    if kind == AlertRuleKind::Match {
+        // Every matching hog is its own event, there's nothing to count, wait
+        // out or group.
+        for field in [
+            "window",
+            "condition",
+            "for",
+            "repeat_interval",
+            "group_by",
+            "channels",
+        ] {
+            if req.get(field).is_some() {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message: format!("{} is not supported by match rules", field),
+                });
+            }
+        }
+    }
+    let threshold = kind == AlertRuleKind::Threshold;
+
+    let window_secs = if threshold {
+        duration_secs(&req, "window", None, &mut errors)
+    } else {
+        0
+    };
+    let for_secs = duration_secs(&req, "for", Some(0), &mut errors);
+    let repeat_interval_secs = duration_secs(
+        &req,
+        "repeat_interval",
+        Some(ALERT_REPEAT_INTERVAL_SECS),
+        &mut errors,
+    );
+    if threshold && window_secs == 0 && !errors.iter().any(|e| e.field == "window") {
+        errors.push(ApiErrorSchema {
+            field: "window".to_string(),
+            message: "window must be greater than zero".to_string(),
+        });
+    }
+
+    let condition = match req.get("condition") {
+        Some(condition) => match serde_json::from_value::<AlertCondition>(condition.clone()) {
+            Ok(condition) => Some(condition),
+            Err(e) => {
+                errors.push(ApiErrorSchema {
+                    field: "condition".to_string(),
+                    message: format!(
+                        "expected {{\"op\": \">\" | \">=\" | \"<\" | \"<=\" | \"==\", \"threshold\": <integer>}}: {}",
+                        e
+                    ),
+                });
+                None
+            }
+        },
+        None if threshold => {
+            errors.push(ApiErrorSchema {
+                field: "condition".to_string(),
+                message: "condition is required".to_string(),
+            });
+            None
+        }
+        None => None,
+    };
+
+    let group_by = match string_list(&req, "group_by") {
+        Ok(group_by) => {
+            for field in &group_by {
+                if !is_groupable(field) {
+                    errors.push(ApiErrorSchema {
+                        field: "group_by".to_string(),
+                        message: format!(
+                            "{} is not a hog field, use one of log_level, log_type, log_source, log_source_id, log_message, pattern_id or log_data.<key>",
+                            field
+                        ),
+                    });
+                }
+            }
+            group_by
+        }
+        Err(message) => {
+            errors.push(ApiErrorSchema {
+                field: "group_by".to_string(),
+                message,
+            });
+            Vec::new()
+        }
+    };
+
+    let channels = match string_list(&req, "channels") {
+        Ok(channels) => {
+            if let Some(channel) = channels.iter().find(|c| ObjectId::parse_str(c).is_err()) {
+                errors.push(ApiErrorSchema {
+                    field: "channels".to_string(),
+                    message: format!("{} is not a channel id", channel),
+                });
+            }
+            channels
+        }
+        Err(message) => {
+            errors.push(ApiErrorSchema {
+                field: "channels".to_string(),
+                message,
+            });
+            Vec::new()
+        }
+    };
+
+    let labels = match string_map(&req, "labels") {
+        Ok(labels) => labels,
+        Err(message) => {
+            errors.push(ApiErrorSchema {
+                field: "labels".to_string(),
+                message,
+            });
+            BTreeMap::new()
+        }
+    };
+
+    let enabled = req.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true);
+
+    if !errors.is_empty() {
+        return Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        });
+    }
+
+    Ok(AlertRuleRequest {
+        name,
+        description,
+        options,
+        kind,
+        window_secs,
+        condition,
+        for_secs,
+        repeat_interval_secs,
+        group_by,
+        channels,
+        labels,
+        enabled,
+    })
+}
+
+#[allow(dead_code)]
+pub async fn validate_silence(req: serde_json::Value) -> Result<SilenceRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+    let now = Utc::now();
+
+    let matchers = match string_map(&req, "matchers") {
+        Ok(matchers) if !matchers.is_empty() => matchers,
+        Ok(_) => {
+            errors.push(ApiErrorSchema {
+                field: "matchers".to_string(),
+                message: "matchers is required, e.g. {\"alertname\": \"...\"}".to_string(),
+            });
+            BTreeMap::new()
+        }
+        Err(message) => {
+            errors.push(ApiErrorSchema {
+                field: "matchers".to_string(),
+                message,
+            });
+            BTreeMap::new()
+        }
+    };
+
+    let mut resolve = |field: &str, default: Option<&str>, round_up: bool| {
+        let expr = match req.get(field).and_then(|v| v.as_str()).or(default) {
+            Some(expr) => expr,
+            None => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message: format!("{} is required, e.g. \"now+2h\"", field),
+                });
+                return now;
+            }
+        };
+        match date_math::resolve(expr, now, round_up) {
+            Ok(timestamp) => timestamp,
+            Err(message) => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message,
+                });
+                now
+            }
+        }
+    };
+    let starts_at = resolve("starts_at", Some("now"), false);
+    let ends_at = resolve("ends_at", None, true);
+    if ends_at <= starts_at && !errors.iter().any(|e| e.field.ends_with("_at")) {
+        errors.push(ApiErrorSchema {
+            field: "ends_at".to_string(),
+            message: "ends_at must be after starts_at".to_string(),
+        });
+    }
+
+    let comment = req
+        .get("comment")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let created_by = match req.get("created_by").and_then(|v| v.as_str()) {
+        Some(created_by) if !created_by.trim().is_empty() => created_by.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "created_by".to_string(),
+                message: "created_by is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    if !errors.is_empty() {
+        return Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        });
+    }
+
+    Ok(SilenceRequest {
+        matchers,
+        starts_at,
+        ends_at,
+        comment,
+        created_by,
+    })
+}
+
+fn duration_secs(
+    req: &serde_json::Value,
+    field: &str,
+    default: Option<i64>,
+    errors: &mut Vec<ApiErrorSchema>,
+) -> i64 {
+    match (req.get(field).and_then(|v| v.as_str()), default) {
+        (Some(expr), _) => match date_math::parse_duration(expr) {
+            Ok(duration) if duration.num_seconds() < 0 => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message: format!("{} cannot be negative", field),
+                });
+                0
+            }
+            Ok(duration) if duration.num_seconds() > ALERT_MAX_DURATION_SECS => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message: format!("{} can be at most 366d", field),
+                });
+                0
+            }
+            Ok(duration) => duration.num_seconds(),
+            Err(message) => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message,
+                });
+                0
+            }
+        },
+        (None, Some(default)) => default,
+        (None, None) => {
+            errors.push(ApiErrorSchema {
+                field: field.to_string(),
+                message: format!("{} is required, e.g. \"5m\"", field),
+            });
+            0
+        }
+    }
+}
+
+fn string_list(req: &serde_json::Value, field: &str) -> Result<Vec<String>, String> {
+    match req.get(field) {
+        None => Ok(Vec::new()),
+        Some(value) => value
+            .as_array()
+            .and_then(|values| {
+                values
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| format!("{} must be an array of strings", field)),
+    }
+}
+
+fn string_map(req: &serde_json::Value, field: &str) -> Result<BTreeMap<String, String>, String> {
+    match req.get(field) {
+        None => Ok(BTreeMap::new()),
+        Some(value) => value
+            .as_object()
+            .and_then(|values| {
+                values
+                    .iter()
+                    .map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                    .collect::<Option<BTreeMap<_, _>>>()
+            })
+            .ok_or_else(|| format!("{} must be an object of strings", field)),
+    }
+}
+
+fn is_groupable(field: &str) -> bool {
+    matches!(
+        field,
+        "log_level" | "log_type" | "log_source" | "log_source_id" | "log_message" | "pattern_id"
+    ) || field
+        .strip_prefix("log_data.")
+        .is_some_and(|key| !key.is_empty() && !key.starts_with('$'))
+}
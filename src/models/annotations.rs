@@ -0,0 +1,121 @@
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::options::{ApiErrorSchema, ErrorResponse};
+
+/// Operator-supplied notes kept next to the original log fields, as stored in Mongo.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HogAnnotationsRecord {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub comments: Vec<HogCommentRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogCommentRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub comment: String,
+    pub created_at: BsonDateTime,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HogAnnotations {
+    pub tags: Vec<String>,
+    pub comments: Vec<HogComment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogComment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub comment: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationsRequest {
+    pub tags: Option<Vec<String>>,
+    pub add_tags: Option<Vec<String>>,
+    pub remove_tags: Option<Vec<String>>,
+    pub comment: Option<String>,
+    pub author: Option<String>,
+}
+
+#[allow(dead_code)]
+pub async fn validate_annotations(
+    req: serde_json::Value,
+) -> Result<AnnotationsRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+
+    let mut tag_list = |field: &str| -> Option<Vec<String>> {
+        let value = req.get(field)?;
+        let tags = value.as_array().and_then(|arr| {
+            arr.iter()
+                .map(|v| v.as_str().map(|s| s.trim().to_string()))
+                .collect::<Option<Vec<String>>>()
+        });
+        match tags {
+            Some(tags) if tags.iter().all(|t| !t.is_empty()) => Some(tags),
+            _ => {
+                errors.push(ApiErrorSchema {
+                    field: field.to_string(),
+                    message: format!("{} must be an array of non-empty strings", field),
+                });
+                None
+            }
+        }
+    };
+    let tags = tag_list("tags");
+    let add_tags = tag_list("add_tags");
+    let remove_tags = tag_list("remove_tags");
+
+    let comment = req
+        .get("comment")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    if let Some(ref comment) = comment
+        && comment.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "comment".to_string(),
+            message: "comment cannot be empty".to_string(),
+        });
+    }
+
+    let author = req
+        .get("author")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    if tags.is_none()
+        && add_tags.is_none()
+        && remove_tags.is_none()
+        && comment.is_none()
+        && errors.is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "payload".to_string(),
+            message: "one of tags, add_tags, remove_tags or comment is required".to_string(),
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        });
+    }
+
+    Ok(AnnotationsRequest {
+        tags,
+        add_tags,
+        remove_tags,
+        comment,
+        author,
+    })
+}
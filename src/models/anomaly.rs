@@ -0,0 +1,190 @@
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// Far more hogs than the baseline expects.
+    Spike,
+    /// A normally busy source went (almost) quiet.
+    Drop,
+}
+
+#[allow(dead_code)]
+impl AnomalyKind {
+    pub const ALL: [AnomalyKind; 2] = [AnomalyKind::Spike, AnomalyKind::Drop];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::Spike => "spike",
+            AnomalyKind::Drop => "drop",
+        }
+    }
+}
+
+/// Thresholds for the volume anomaly detector.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalySettings {
+    /// Length of one counting window.
+    pub interval_secs: i64,
+    /// How long to wait after a window closes before counting it, so hogs
+    /// still in the queue make it in.
+    pub settle_secs: i64,
+    /// Weight of the newest window in the moving average.
+    pub ewma_alpha: f64,
+    /// Windows a baseline needs before it can flag anything.
+    pub warmup_windows: i64,
+    /// Windows with fewer hogs never count as a spike, baselines below it
+    /// never count as a drop.
+    pub min_count: f64,
+    pub spike_ratio: f64,
+    pub spike_z_score: f64,
+    pub drop_ratio: f64,
+    /// Windows in a row without a hog after which a baseline is forgotten.
+    pub evict_after_windows: i64,
+    /// Pairs that get their own `hog_volume_*` series before the rest share
+    /// one.
+    pub max_series: usize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeBaselineRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub log_source: Option<String>,
+    pub log_level: Option<String>,
+    pub mean: f64,
+    pub variance: f64,
+    pub windows: i64,
+    pub last_count: i64,
+    /// Windows in a row that counted no hogs.
+    #[serde(default)]
+    pub quiet_windows: i64,
+    pub updated_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+impl VolumeBaselineRecord {
+    pub fn new(log_source: Option<String>, log_level: Option<String>) -> Self {
+        VolumeBaselineRecord {
+            id: None,
+            log_source,
+            log_level,
+            mean: 0.0,
+            variance: 0.0,
+            windows: 0,
+            last_count: 0,
+            quiet_windows: 0,
+            updated_at: BsonDateTime::now(),
+        }
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance.max(0.0).sqrt()
+    }
+
+    /// Compares `count` against the baseline as it was before this window.
+    /// Returns the kind of anomaly and how many standard deviations away the
+    /// count is.
+    pub fn classify(&self, count: i64, settings: &AnomalySettings) -> Option<(AnomalyKind, f64)> {
+        if self.windows < settings.warmup_windows {
+            return None;
+        }
+        let count = count as f64;
+        let score = (count - self.mean) / self.stddev().max(1.0);
+
+        if count >= settings.min_count
+            && count >= self.mean * settings.spike_ratio
+            && score >= settings.spike_z_score
+        {
+            return Some((AnomalyKind::Spike, score));
+        }
+        if self.mean >= settings.min_count && count <= self.mean * settings.drop_ratio {
+            return Some((AnomalyKind::Drop, score));
+        }
+        None
+    }
+
+    /// Folds a window's count into the exponentially weighted mean and
+    /// variance.
+    pub fn observe(&mut self, count: i64, alpha: f64) {
+        let count = count as f64;
+        if self.windows == 0 {
+            self.mean = count;
+            self.variance = 0.0;
+        } else {
+            let diff = count - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.windows += 1;
+        self.last_count = count as i64;
+        self.quiet_windows = if self.last_count == 0 {
+            self.quiet_windows + 1
+        } else {
+            0
+        };
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeBaseline {
+    pub log_source: Option<String>,
+    pub log_level: Option<String>,
+    pub mean: f64,
+    pub stddev: f64,
+    pub windows: i64,
+    pub last_count: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub log_source: Option<String>,
+    pub log_level: Option<String>,
+    pub kind: AnomalyKind,
+    pub count: i64,
+    pub expected: f64,
+    pub stddev: f64,
+    pub score: f64,
+    pub window_start: BsonDateTime,
+    pub window_end: BsonDateTime,
+    pub detected_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub id: Option<String>,
+    pub log_source: Option<String>,
+    pub log_level: Option<String>,
+    pub kind: AnomalyKind,
+    pub count: i64,
+    pub expected: f64,
+    pub stddev: f64,
+    pub score: f64,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyQuery {
+    pub log_source: Option<String>,
+    pub log_level: Option<String>,
+    pub kind: Option<AnomalyKind>,
+    /// RFC 3339 or a `now`-based expression.
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
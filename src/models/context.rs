@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+use super::hog::Hog;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextQuery {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogContext {
+    pub anchor: Hog,
+    pub before: Vec<Hog>,
+    pub after: Vec<Hog>,
+}
@@ -0,0 +1,345 @@
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveTime, TimeZone, Timelike, Utc,
+};
+
+/// Resolves a timestamp expression against `now`.
+///
+/// Accepts RFC 3339 timestamps as before, or `now` followed by any number of
+/// `+N<unit>`/`-N<unit>` offsets and `/<unit>` roundings, e.g. `now-15m`,
+/// `now-1d/d` or `now/h`. Units are `s`, `m`, `h`, `d`, `w`, `M` and `y`.
+/// With `round_up`, roundings land on the last millisecond of the unit instead
+/// of its first, so `now/d` as an end covers the whole day.
+#[allow(dead_code)]
+pub fn resolve(expr: &str, now: DateTime<Utc>, round_up: bool) -> Result<DateTime<Utc>, String> {
+    let expr = expr.trim();
+    let Some(mut rest) = expr.strip_prefix("now") else {
+        return DateTime::parse_from_rfc3339(expr)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|_| {
+                format!(
+                    "'{}' is neither an RFC 3339 timestamp nor a now-based expression",
+                    expr
+                )
+            });
+    };
+
+    let mut resolved = now;
+    while let Some(op) = rest.chars().next() {
+        rest = &rest[op.len_utf8()..];
+        match op {
+            '+' | '-' => {
+                let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+                let amount: u32 = rest[..digits]
+                    .parse()
+                    .map_err(|_| format!("expected a number after '{}' in '{}'", op, expr))?;
+                rest = &rest[digits..];
+                let unit = next_unit(&mut rest, expr)?;
+                resolved = shift(resolved, amount, unit, op == '-')
+                    .ok_or_else(|| format!("'{}' is out of range", expr))?;
+            }
+            '/' => {
+                let unit = next_unit(&mut rest, expr)?;
+                resolved = round(resolved, unit, round_up)
+                    .ok_or_else(|| format!("'{}' is out of range", expr))?;
+            }
+            _ => return Err(format!("unexpected '{}' in '{}'", op, expr)),
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Parses a `hog_last` style duration such as `15m`, `1h` or `7d`.
+#[allow(dead_code)]
+pub fn parse_duration(expr: &str) -> Result<Duration, String> {
+    let expr = expr.trim();
+    let digits = expr.chars().take_while(|c| c.is_ascii_digit()).count();
+    let amount: i64 = expr[..digits]
+        .parse()
+        .map_err(|_| format!("'{}' must look like 15m, 1h or 7d", expr))?;
+    let duration = match &expr[digits..] {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => return Err(format!("'{}' must use one of s, m, h, d or w", expr)),
+    };
+    duration.ok_or_else(|| format!("'{}' is out of range", expr))
+}
+
+fn next_unit(rest: &mut &str, expr: &str) -> Result<char, String> {
+    match rest.chars().next() {
+        Some(unit @ ('s' | 'm' | 'h' | 'd' | 'w' | 'M' | 'y')) => {
+            *rest = &rest[1..];
+            Ok(unit)
+        }
+        _ => Err(format!(
+            "expected one of s, m, h, d, w, M or y in '{}'",
+            expr
+        )),
+    }
+}
+
+fn shift(value: DateTime<Utc>, amount: u32, unit: char, back: bool) -> Option<DateTime<Utc>> {
+    let months = match unit {
+        'M' => Some(amount),
+        'y' => amount.checked_mul(12),
+        _ => None,
+    };
+    if let Some(months) = months {
+        return if back {
+            value.checked_sub_months(Months::new(months))
+        } else {
+            value.checked_add_months(Months::new(months))
+        };
+    }
+
+    let amount = i64::from(amount);
+    let duration = match unit {
+        's' => Duration::try_seconds(amount),
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        _ => Duration::try_weeks(amount),
+    }?;
+    if back {
+        value.checked_sub_signed(duration)
+    } else {
+        value.checked_add_signed(duration)
+    }
+}
+
+fn round(value: DateTime<Utc>, unit: char, round_up: bool) -> Option<DateTime<Utc>> {
+    let date = value.date_naive();
+    let time = value.time();
+    let start = match unit {
+        's' => date.and_time(NaiveTime::from_hms_opt(
+            time.hour(),
+            time.minute(),
+            time.second(),
+        )?),
+        'm' => date.and_time(NaiveTime::from_hms_opt(time.hour(), time.minute(), 0)?),
+        'h' => date.and_time(NaiveTime::from_hms_opt(time.hour(), 0, 0)?),
+        'd' => date.and_time(NaiveTime::MIN),
+        'w' => date
+            .checked_sub_days(Days::new(u64::from(date.weekday().num_days_from_monday())))?
+            .and_time(NaiveTime::MIN),
+        'M' => NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?.and_time(NaiveTime::MIN),
+        _ => NaiveDate::from_ymd_opt(date.year(), 1, 1)?.and_time(NaiveTime::MIN),
+    };
+    let start = Utc.from_utc_datetime(&start);
+    if !round_up {
+        return Some(start);
+    }
+
+    shift(start, 1, unit, false)?.checked_sub_signed(Duration::milliseconds(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Sunday, so `/w` has somewhere to go.
+    fn now() -> DateTime<Utc> {
+        "2025-05-18T15:28:34.549Z".parse().unwrap()
+    }
+
+    fn at(expr: &str, round_up: bool) -> Result<String, String> {
+        resolve(expr, now(), round_up)
+            .map(|datetime| datetime.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+    }
+
+    #[test]
+    fn offsets_move_from_now() {
+        assert_eq!(at("now", false), Ok("2025-05-18T15:28:34.549Z".to_string()));
+        assert_eq!(
+            at("now-15m", false),
+            Ok("2025-05-18T15:13:34.549Z".to_string())
+        );
+        assert_eq!(
+            at("now+1h", false),
+            Ok("2025-05-18T16:28:34.549Z".to_string())
+        );
+        assert_eq!(
+            at("now-30s", false),
+            Ok("2025-05-18T15:28:04.549Z".to_string())
+        );
+        assert_eq!(
+            at("now-2w", false),
+            Ok("2025-05-04T15:28:34.549Z".to_string())
+        );
+        assert_eq!(
+            at(" now-1d ", false),
+            Ok("2025-05-17T15:28:34.549Z".to_string())
+        );
+    }
+
+    #[test]
+    fn months_and_years_are_calendar_units() {
+        assert_eq!(
+            at("now-1M", false),
+            Ok("2025-04-18T15:28:34.549Z".to_string())
+        );
+        assert_eq!(
+            at("now+1y", false),
+            Ok("2026-05-18T15:28:34.549Z".to_string())
+        );
+        let march_31 = "2025-03-31T12:00:00Z".parse().unwrap();
+        assert_eq!(
+            resolve("now-1M", march_31, false).map(|datetime| datetime.to_rfc3339()),
+            Ok("2025-02-28T12:00:00+00:00".to_string())
+        );
+    }
+
+    #[test]
+    fn roundings_land_on_the_start_or_end_of_the_unit() {
+        let cases = [
+            (
+                "now/s",
+                "2025-05-18T15:28:34.000Z",
+                "2025-05-18T15:28:34.999Z",
+            ),
+            (
+                "now/m",
+                "2025-05-18T15:28:00.000Z",
+                "2025-05-18T15:28:59.999Z",
+            ),
+            (
+                "now/h",
+                "2025-05-18T15:00:00.000Z",
+                "2025-05-18T15:59:59.999Z",
+            ),
+            (
+                "now/d",
+                "2025-05-18T00:00:00.000Z",
+                "2025-05-18T23:59:59.999Z",
+            ),
+            (
+                "now/w",
+                "2025-05-12T00:00:00.000Z",
+                "2025-05-18T23:59:59.999Z",
+            ),
+            (
+                "now/M",
+                "2025-05-01T00:00:00.000Z",
+                "2025-05-31T23:59:59.999Z",
+            ),
+            (
+                "now/y",
+                "2025-01-01T00:00:00.000Z",
+                "2025-12-31T23:59:59.999Z",
+            ),
+        ];
+        for (expr, start, end) in cases {
+            assert_eq!(at(expr, false), Ok(start.to_string()), "{}", expr);
+            assert_eq!(at(expr, true), Ok(end.to_string()), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn offsets_and_roundings_chain_left_to_right() {
+        assert_eq!(
+            at("now-1d/d", false),
+            Ok("2025-05-17T00:00:00.000Z".to_string())
+        );
+        assert_eq!(
+            at("now-1d/d", true),
+            Ok("2025-05-17T23:59:59.999Z".to_string())
+        );
+        assert_eq!(
+            at("now-1y+2M/M", false),
+            Ok("2024-07-01T00:00:00.000Z".to_string())
+        );
+        assert_eq!(
+            at("now/d-1h", false),
+            Ok("2025-05-17T23:00:00.000Z".to_string())
+        );
+    }
+
+    #[test]
+    fn rfc3339_timestamps_pass_through() {
+        assert_eq!(
+            at("2025-05-18T17:28:34+02:00", false),
+            Ok("2025-05-18T15:28:34.000Z".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        let cases = [
+            (
+                "yesterday",
+                "'yesterday' is neither an RFC 3339 timestamp nor a now-based expression",
+            ),
+            ("now-m", "expected a number after '-' in 'now-m'"),
+            (
+                "now-15",
+                "expected one of s, m, h, d, w, M or y in 'now-15'",
+            ),
+            (
+                "now-15x",
+                "expected one of s, m, h, d, w, M or y in 'now-15x'",
+            ),
+            ("now/", "expected one of s, m, h, d, w, M or y in 'now/'"),
+            ("now*2", "unexpected '*' in 'now*2'"),
+            (
+                "now-99999999999d",
+                "expected a number after '-' in 'now-99999999999d'",
+            ),
+        ];
+        for (expr, message) in cases {
+            assert_eq!(at(expr, false), Err(message.to_string()), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn offsets_past_the_calendar_are_out_of_range() {
+        for expr in [
+            "now-100000000d",
+            "now+4000000000w",
+            "now+4000000000y",
+            "now-4000000000M",
+        ] {
+            assert_eq!(
+                at(expr, false),
+                Err(format!("'{}' is out of range", expr)),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn durations_take_one_amount_and_unit() {
+        assert_eq!(parse_duration("15m"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_duration(" 2h "), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::seconds(30)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::days(7)));
+        assert_eq!(parse_duration("1w"), Ok(Duration::weeks(1)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::zero()));
+    }
+
+    #[test]
+    fn malformed_durations_are_rejected() {
+        let cases = [
+            ("m", "'m' must look like 15m, 1h or 7d"),
+            ("-5m", "'-5m' must look like 15m, 1h or 7d"),
+            ("", "'' must look like 15m, 1h or 7d"),
+            ("15", "'15' must use one of s, m, h, d or w"),
+            ("15M", "'15M' must use one of s, m, h, d or w"),
+            ("1h30m", "'1h30m' must use one of s, m, h, d or w"),
+            ("99999999999999999w", "'99999999999999999w' is out of range"),
+        ];
+        for (expr, message) in cases {
+            assert_eq!(parse_duration(expr), Err(message.to_string()), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn durations_can_outrun_the_calendar() {
+        // Parses as a duration, but there's no date that far before now.
+        let last = parse_duration("100000000d").unwrap();
+        assert_eq!(now().checked_sub_signed(last), None);
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use bson::DateTime as BsonDateTime;
+use mongodb::bson::doc;
+use mongodb::Collection;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::hog_record::HogRecord;
+
+#[allow(dead_code)]
+pub const DEDUP_WINDOW_SECS: i64 = 5 * 60;
+
+/// Hash of what makes two hogs the same log line: `log_timestamp`,
+/// `log_source`, `log_source_id`, `log_message` and `log_data`. Object keys are
+/// sorted first, so key order doesn't matter.
+#[allow(dead_code)]
+pub fn content_hash(record: &HogRecord) -> String {
+    let content = serde_json::json!([
+        record.log_timestamp.timestamp_millis(),
+        record.log_source,
+        record.log_source_id,
+        record.log_message,
+        record.log_data.as_ref().map(canonical),
+    ]);
+    let digest = Sha256::digest(content.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Folds repeats of the same hog into one record for `window_secs` after it
+/// was first stored. The record keeps the `hog_uuid` of every repeat in
+/// `repeat_hog_uuids`, and `repeat_count` is the size of that set, so storing
+/// the same delivery twice (a retried batch, a redelivery after a failed ack)
+/// doesn't count it twice.
+///
+/// Only the first hog of a record is stored under its own `hog_uuid`; the
+/// `hog_uuid` returned to clients for a folded repeat has no document.
+#[allow(dead_code)]
+pub struct Deduplicator {
+    window_secs: i64,
+}
+
+#[allow(dead_code)]
+impl Deduplicator {
+    pub fn new(window_secs: i64) -> Self {
+        Deduplicator { window_secs }
+    }
+
+    /// Sets `content_hash` from the plaintext. Must run before `FieldCipher`
+    /// encrypts anything: ciphertext is random, so repeats would never match.
+    pub fn stamp(&self, record: &mut HogRecord) {
+        record.content_hash = Some(content_hash(record));
+    }
+
+    /// Collapses exact repeats within a batch, keeping the first of each and
+    /// the `hog_uuid` of every repeat.
+    pub fn fold(&self, records: &[HogRecord]) -> Vec<(HogRecord, Vec<String>)> {
+        let mut folded: Vec<(HogRecord, Vec<String>)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for record in records {
+            let uuids = record.hog_uuid.iter().cloned();
+            match positions.entry(hash_of(record)) {
+                Entry::Occupied(position) => {
+                    folded[*position.get()].1.extend(uuids);
+                }
+                Entry::Vacant(position) => {
+                    position.insert(folded.len());
+                    folded.push((record.clone(), uuids.collect()));
+                }
+            }
+        }
+        folded
+    }
+
+    /// Adds the repeats in `hog_uuids` to the open record with the same
+    /// content, or stores `record` as a new one. Two workers storing the same
+    /// new hog at the same instant can still both insert it.
+    pub async fn store(
+        &self,
+        collection: &Collection<HogRecord>,
+        record: &HogRecord,
+        hog_uuids: &[String],
+        now: BsonDateTime,
+    ) -> anyhow::Result<()> {
+        let hash = hash_of(record);
+        let mut insert = bson::to_document(record)?;
+        for field in [
+            "repeat_count",
+            "repeat_hog_uuids",
+            "first_seen_at",
+            "last_seen_at",
+        ] {
+            insert.remove(field);
+        }
+        insert.insert("content_hash", &hash);
+        insert.insert("first_seen_at", now);
+        insert.insert(
+            "dedup_until",
+            BsonDateTime::from_millis(now.timestamp_millis() + self.window_secs * 1000),
+        );
+        // An update pipeline, so the count can be derived from the set.
+        // `insert` only fills in a new record; an open one is left as stored.
+        collection
+            .update_one(
+                doc! { "content_hash": &hash, "dedup_until": { "$gt": now } },
+                vec![
+                    doc! { "$replaceWith": { "$cond": [
+                        { "$eq": [{ "$type": "$dedup_until" }, "missing"] },
+                        { "$mergeObjects": [{ "$literal": insert }, "$$ROOT"] },
+                        "$$ROOT",
+                    ] } },
+                    doc! { "$set": {
+                        "repeat_hog_uuids": {
+                            "$setUnion": [{ "$ifNull": ["$repeat_hog_uuids", []] }, { "$literal": hog_uuids }],
+                        },
+                        "last_seen_at": now,
+                    } },
+                    doc! { "$set": { "repeat_count": { "$size": "$repeat_hog_uuids" } } },
+                ],
+            )
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+}
+
+fn hash_of(record: &HogRecord) -> String {
+    record
+        .content_hash
+        .clone()
+        .unwrap_or_else(|| content_hash(record))
+}
@@ -0,0 +1,293 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddedFormat {
+    /// JSON when the text carries an object, logfmt otherwise.
+    #[default]
+    Auto,
+    Json,
+    Logfmt,
+}
+
+/// What to do with a parsed key that's already in the target object.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictRule {
+    #[default]
+    Keep,
+    Overwrite,
+    Prefix,
+}
+
+/// Finds structured data in a message. `None` when there's nothing to merge.
+#[allow(dead_code)]
+pub fn parse_embedded(text: &str, format: EmbeddedFormat) -> Option<Map<String, Value>> {
+    match format {
+        EmbeddedFormat::Json => parse_json(text),
+        EmbeddedFormat::Logfmt => parse_logfmt(text),
+        EmbeddedFormat::Auto => parse_json(text).or_else(|| parse_logfmt(text)),
+    }
+}
+
+/// The whole message as a JSON object, or the span from its first `{` to its
+/// last `}` (`"request done {\"status\":200}"`).
+fn parse_json(text: &str) -> Option<Map<String, Value>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    if end < start {
+        return None;
+    }
+    match serde_json::from_str(&text[start..=end]) {
+        Ok(Value::Object(map)) if !map.is_empty() => Some(map),
+        _ => None,
+    }
+}
+
+/// `key=value` and `key="quoted value"` pairs, values kept as strings. Words
+/// without an `=` are prose around the pairs and are skipped.
+fn parse_logfmt(text: &str) -> Option<Map<String, Value>> {
+    let mut pairs = Map::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut key_end = text.len();
+        let mut has_value = false;
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() {
+                key_end = i;
+                break;
+            }
+            chars.next();
+            if c == '=' {
+                key_end = i;
+                has_value = true;
+                break;
+            }
+        }
+        let key = &text[start..key_end];
+        let valid_key = !key.is_empty() && !key.contains('"');
+
+        if !has_value {
+            continue;
+        }
+        let value = if chars.peek().is_some_and(|&(_, c)| c == '"') {
+            chars.next();
+            let mut value = String::new();
+            while let Some((_, c)) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, escaped)) => value.push(escaped),
+                        None => value.push('\\'),
+                    },
+                    c => value.push(c),
+                }
+            }
+            value
+        } else {
+            let mut value = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            value
+        };
+        if valid_key {
+            pairs.insert(key.to_string(), Value::String(value));
+        }
+    }
+    (!pairs.is_empty()).then_some(pairs)
+}
+
+/// Merges `parsed` into `target` key by key. With `Prefix`, a conflicting key
+/// is written as `<prefix><key>` instead.
+#[allow(dead_code)]
+pub fn merge_embedded(
+    target: &mut Map<String, Value>,
+    parsed: Map<String, Value>,
+    conflict: ConflictRule,
+    prefix: &str,
+) {
+    for (key, value) in parsed {
+        if !target.contains_key(&key) {
+            target.insert(key, value);
+            continue;
+        }
+        match conflict {
+            ConflictRule::Keep => {}
+            ConflictRule::Overwrite => {
+                target.insert(key, value);
+            }
+            ConflictRule::Prefix => {
+                target.insert(format!("{}{}", prefix, key), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            other => panic!("{} is not an object", other),
+        }
+    }
+
+    fn logfmt(text: &str) -> Option<Value> {
+        parse_embedded(text, EmbeddedFormat::Logfmt).map(Value::Object)
+    }
+
+    #[test]
+    fn logfmt_reads_bare_and_quoted_values() {
+        assert_eq!(
+            logfmt(r#"user=alice msg="login failed" attempts=3"#),
+            Some(json!({ "user": "alice", "msg": "login failed", "attempts": "3" }))
+        );
+    }
+
+    #[test]
+    fn logfmt_skips_words_without_a_value() {
+        assert_eq!(
+            logfmt("request done status=200 in 12ms"),
+            Some(json!({ "status": "200" }))
+        );
+        assert_eq!(logfmt("nothing to see here"), None);
+        assert_eq!(logfmt(""), None);
+    }
+
+    #[test]
+    fn logfmt_unescapes_quoted_values() {
+        assert_eq!(
+            logfmt(r#"a="line\none" b="tab\there" c="say \"hi\"" d="back\\slash""#),
+            Some(json!({
+                "a": "line\none",
+                "b": "tab\there",
+                "c": "say \"hi\"",
+                "d": "back\\slash",
+            }))
+        );
+    }
+
+    #[test]
+    fn logfmt_keeps_unterminated_quotes_to_the_end() {
+        assert_eq!(
+            logfmt(r#"a=1 b="no end here"#),
+            Some(json!({ "a": "1", "b": "no end here" }))
+        );
+        assert_eq!(
+            logfmt(r#"a="trailing\"#),
+            Some(json!({ "a": "trailing\\" }))
+        );
+    }
+
+    #[test]
+    fn logfmt_handles_empty_values_and_equals_signs_in_values() {
+        assert_eq!(
+            logfmt(r#"empty= quoted="" query=a=b"#),
+            Some(json!({ "empty": "", "quoted": "", "query": "a=b" }))
+        );
+    }
+
+    #[test]
+    fn logfmt_drops_invalid_keys_but_consumes_their_values() {
+        assert_eq!(
+            logfmt(r#"=orphan "k"=v ok=yes"#),
+            Some(json!({ "ok": "yes" }))
+        );
+        assert_eq!(logfmt(r#"="quoted orphan""#), None);
+    }
+
+    #[test]
+    fn logfmt_keeps_the_last_of_repeated_keys() {
+        assert_eq!(logfmt("a=1 a=2"), Some(json!({ "a": "2" })));
+    }
+
+    #[test]
+    fn logfmt_handles_multibyte_text() {
+        assert_eq!(
+            logfmt("café=crème naïve msg=\"über ✓\""),
+            Some(json!({ "café": "crème", "msg": "über ✓" }))
+        );
+    }
+
+    #[test]
+    fn json_reads_whole_and_embedded_objects() {
+        let parsed = parse_embedded(r#"{"status":200,"ok":true}"#, EmbeddedFormat::Json);
+        assert_eq!(parsed, Some(object(json!({ "status": 200, "ok": true }))));
+
+        let parsed = parse_embedded(
+            r#"request done {"status":200,"user":{"id":7}} (cached)"#,
+            EmbeddedFormat::Json,
+        );
+        assert_eq!(
+            parsed,
+            Some(object(json!({ "status": 200, "user": { "id": 7 } })))
+        );
+    }
+
+    #[test]
+    fn json_rejects_anything_but_a_non_empty_object() {
+        for text in ["{}", "[1, 2]", "{not json}", "} backwards {", "no braces"] {
+            assert_eq!(parse_embedded(text, EmbeddedFormat::Json), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn auto_prefers_json_and_falls_back_to_logfmt() {
+        assert_eq!(
+            parse_embedded(r#"a=1 {"b":2}"#, EmbeddedFormat::Auto),
+            Some(object(json!({ "b": 2 })))
+        );
+        assert_eq!(
+            parse_embedded("a=1 {broken", EmbeddedFormat::Auto),
+            Some(object(json!({ "a": "1" })))
+        );
+        assert_eq!(parse_embedded(r#"{"b":2}"#, EmbeddedFormat::Logfmt), None);
+    }
+
+    #[test]
+    fn merge_follows_the_conflict_rule() {
+        let target = object(json!({ "a": 1, "b": 2 }));
+        let parsed = object(json!({ "b": 20, "c": 30 }));
+
+        let mut kept = target.clone();
+        merge_embedded(&mut kept, parsed.clone(), ConflictRule::Keep, "x_");
+        assert_eq!(Value::Object(kept), json!({ "a": 1, "b": 2, "c": 30 }));
+
+        let mut overwritten = target.clone();
+        merge_embedded(
+            &mut overwritten,
+            parsed.clone(),
+            ConflictRule::Overwrite,
+            "x_",
+        );
+        assert_eq!(
+            Value::Object(overwritten),
+            json!({ "a": 1, "b": 20, "c": 30 })
+        );
+
+        let mut prefixed = target;
+        merge_embedded(&mut prefixed, parsed, ConflictRule::Prefix, "x_");
+        assert_eq!(
+            Value::Object(prefixed),
+            json!({ "a": 1, "b": 2, "x_b": 20, "c": 30 })
+        );
+    }
+}
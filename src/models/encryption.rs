@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::env;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+
+use super::hog_record::HogRecord;
+use super::pipeline::FieldPath;
+
+/// Encrypted values are stored as `enc:v1:<key id>:<base64 nonce + ciphertext>`.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// What callers without the decrypt scope see instead of an encrypted field.
+pub const ENCRYPTED_PLACEHOLDER: &str = "[ENCRYPTED]";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM encryption of the `log_data` paths in `HOG_ENCRYPTED_FIELDS`.
+/// The workers encrypt with the active key; the API decrypts with whichever
+/// key id a value was written under, so retired keys keep working.
+#[allow(dead_code)]
+pub struct FieldCipher {
+    keys: HashMap<String, Aes256Gcm>,
+    active_key_id: Option<String>,
+    fields: Vec<(String, FieldPath)>,
+    decrypt_tokens: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl FieldCipher {
+    /// `HOG_ENCRYPTION_KEYS` is a comma separated list of `<key id>:<base64
+    /// 32-byte key>`. `HOG_ENCRYPTION_KEY_ID` picks the one new values are
+    /// encrypted with and defaults to the last one listed.
+    pub fn from_env() -> Result<Self, String> {
+        let mut keys = HashMap::new();
+        let mut last_key_id = None;
+        for entry in env_list("HOG_ENCRYPTION_KEYS") {
+            let (key_id, key) = entry.split_once(':').ok_or_else(|| {
+                "HOG_ENCRYPTION_KEYS entries must be <key id>:<base64 key>".to_string()
+            })?;
+            let key = BASE64
+                .decode(key)
+                .map_err(|e| format!("HOG_ENCRYPTION_KEYS: key {}: {}", key_id, e))?;
+            if key.len() != 32 {
+                return Err(format!(
+                    "HOG_ENCRYPTION_KEYS: key {} must be 32 bytes, got {}",
+                    key_id,
+                    key.len()
+                ));
+            }
+            keys.insert(
+                key_id.to_string(),
+                Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            );
+            last_key_id = Some(key_id.to_string());
+        }
+
+        let active_key_id = match env::var("HOG_ENCRYPTION_KEY_ID") {
+            Ok(key_id) if !keys.contains_key(&key_id) => {
+                return Err(format!(
+                    "HOG_ENCRYPTION_KEY_ID: {} is not in HOG_ENCRYPTION_KEYS",
+                    key_id
+                ));
+            }
+            Ok(key_id) => Some(key_id),
+            Err(_) => last_key_id,
+        };
+
+        let mut fields = Vec::new();
+        for path in env_list("HOG_ENCRYPTED_FIELDS") {
+            match FieldPath::parse(&path) {
+                Ok(field @ FieldPath::Data(_)) => fields.push((path, field)),
+                _ => {
+                    return Err(format!(
+                        "HOG_ENCRYPTED_FIELDS: {} is not a log_data.<path>",
+                        path
+                    ));
+                }
+            }
+        }
+        if !fields.is_empty() && active_key_id.is_none() {
+            return Err("HOG_ENCRYPTED_FIELDS needs a key in HOG_ENCRYPTION_KEYS".to_string());
+        }
+
+        Ok(FieldCipher {
+            keys,
+            active_key_id,
+            fields,
+            decrypt_tokens: env_list("HOG_DECRYPT_TOKENS"),
+        })
+    }
+
+    /// The `HOG_ENCRYPTED_FIELDS` paths.
+    pub fn encrypted_fields(&self) -> Vec<FieldPath> {
+        self.fields.iter().map(|(_, field)| field.clone()).collect()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.active_key_id.is_some() && !self.fields.is_empty()
+    }
+
+    /// Whether an `Authorization: Bearer <token>` header carries the decrypt
+    /// scope.
+    pub fn can_decrypt(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        self.decrypt_tokens
+            .iter()
+            .any(|allowed| constant_time_eq(allowed.as_bytes(), token.trim().as_bytes()))
+    }
+
+    /// Replaces every configured path of `record` with its ciphertext. The
+    /// path is bound in as associated data, so a value can't be moved to
+    /// another field and still decrypt.
+    pub fn encrypt(&self, record: &mut HogRecord) -> Result<(), String> {
+        let Some(ref key_id) = self.active_key_id else {
+            return Ok(());
+        };
+        let cipher = &self.keys[key_id];
+        for (path, field) in &self.fields {
+            let value = match field.get(record) {
+                None | Some(Value::Null) => continue,
+                Some(Value::String(ref s)) if s.starts_with(ENCRYPTED_PREFIX) => continue,
+                Some(value) => value,
+            };
+            let plaintext = serde_json::to_vec(&value).map_err(|e| e.to_string())?;
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &plaintext,
+                        aad: path.as_bytes(),
+                    },
+                )
+                .map_err(|e| format!("{}: {}", path, e))?;
+            let mut sealed = nonce.to_vec();
+            sealed.extend(ciphertext);
+            field.set(
+                record,
+                Value::String(format!(
+                    "{}{}:{}",
+                    ENCRYPTED_PREFIX,
+                    key_id,
+                    BASE64.encode(sealed)
+                )),
+            );
+        }
+        Ok(())
+    }
+
+    /// Decrypts encrypted values in `log_data` for callers with the decrypt
+    /// scope. Everyone else, and anything that doesn't decrypt, gets the
+    /// placeholder, and so do configured paths that haven't been encrypted yet
+    /// (live tail sees hogs before the workers do).
+    pub fn reveal(&self, log_data: &mut Option<Value>, decrypt: bool) {
+        let Some(log_data) = log_data else {
+            return;
+        };
+        self.reveal_value(log_data, "log_data", decrypt);
+        if decrypt {
+            return;
+        }
+        for (_, field) in &self.fields {
+            let FieldPath::Data(path) = field else {
+                continue;
+            };
+            let (last, parents) = path.split_last().expect("log_data paths are never empty");
+            let parent = parents
+                .iter()
+                .try_fold(&mut *log_data, |value, segment| value.get_mut(segment));
+            if let Some(Value::Object(map)) = parent
+                && let Some(value) = map.get_mut(last)
+                && !value.is_null()
+            {
+                *value = Value::String(ENCRYPTED_PLACEHOLDER.to_string());
+            }
+        }
+    }
+
+    fn reveal_value(&self, value: &mut Value, path: &str, decrypt: bool) {
+        match value {
+            Value::String(text) if text.starts_with(ENCRYPTED_PREFIX) => {
+                *value = decrypt
+                    .then(|| self.decrypt(text, path))
+                    .flatten()
+                    .unwrap_or_else(|| Value::String(ENCRYPTED_PLACEHOLDER.to_string()));
+            }
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    self.reveal_value(value, &format!("{}.{}", path, key), decrypt);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.reveal_value(value, path, decrypt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn decrypt(&self, text: &str, path: &str) -> Option<Value> {
+        let (key_id, sealed) = text.strip_prefix(ENCRYPTED_PREFIX)?.split_once(':')?;
+        let sealed = BASE64.decode(sealed).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .keys
+            .get(key_id)?
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: path.as_bytes(),
+                },
+            )
+            .ok()?;
+        serde_json::from_slice(&plaintext).ok()
+    }
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
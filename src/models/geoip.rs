@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+
+use maxminddb::{geoip2, Reader};
+use serde_json::{json, Map, Value};
+
+/// How often the database files are checked for a newer version.
+const GEOIP_RELOAD_CHECK_SECS: u64 = 30;
+
+/// One MaxMind-format (`.mmdb`) file, swapped for the new version when the
+/// file on disk changes. A file that doesn't open keeps the old version in
+/// use, so a half-written download never breaks enrichment.
+pub struct GeoIpDatabase {
+    path: String,
+    reader: RwLock<Arc<Reader<Vec<u8>>>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl fmt::Debug for GeoIpDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoIpDatabase")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl GeoIpDatabase {
+    /// Opens `path` and starts watching it for changes.
+    pub fn open(path: &str) -> Result<Arc<Self>, String> {
+        let modified = modified_at(path);
+        let reader = Reader::open_readfile(path).map_err(|e| format!("{}: {}", path, e))?;
+        let database = Arc::new(GeoIpDatabase {
+            path: path.to_string(),
+            reader: RwLock::new(Arc::new(reader)),
+            modified: Mutex::new(modified),
+        });
+        spawn_reloader(Arc::downgrade(&database));
+        Ok(database)
+    }
+
+    fn reader(&self) -> Arc<Reader<Vec<u8>>> {
+        Arc::clone(&self.reader.read().unwrap())
+    }
+
+    /// Reopens the file if its modification time changed since it was last
+    /// loaded. Returns whether a new version is now in use.
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let modified = modified_at(&self.path);
+        if modified.is_none() || modified == *self.modified.lock().unwrap() {
+            return Ok(false);
+        }
+        let reader =
+            Reader::open_readfile(&self.path).map_err(|e| format!("{}: {}", self.path, e))?;
+        *self.reader.write().unwrap() = Arc::new(reader);
+        *self.modified.lock().unwrap() = modified;
+        Ok(true)
+    }
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Checks the file until the database is dropped. Reading a new version
+/// happens off the async threads.
+fn spawn_reloader(database: Weak<GeoIpDatabase>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(GEOIP_RELOAD_CHECK_SECS));
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(database) = database.upgrade() else {
+                return;
+            };
+            let path = database.path.clone();
+            match tokio::task::spawn_blocking(move || database.reload_if_changed()).await {
+                Ok(Ok(true)) => println!("Reloaded GeoIP database {}", path),
+                Ok(Ok(false)) => {}
+                Ok(Err(e)) => eprintln!("Failed to reload GeoIP database: {}", e),
+                Err(e) => eprintln!("Failed to reload GeoIP database {}: {:?}", path, e),
+            }
+        }
+    });
+}
+
+/// Looks addresses up in a City (or Country) database and an ASN database,
+/// either of which may be left out.
+#[derive(Debug)]
+pub struct GeoIp {
+    pub city: Option<Arc<GeoIpDatabase>>,
+    pub asn: Option<Arc<GeoIpDatabase>>,
+    pub language: String,
+}
+
+impl GeoIp {
+    /// What the databases know about `ip`: `country_iso_code`,
+    /// `country_name`, `city_name`, `location` (`lat`, `lon`), `asn` and
+    /// `as_org`, leaving out whatever isn't known. `None` when neither
+    /// database has the address.
+    pub fn lookup(&self, ip: IpAddr) -> Option<Value> {
+        let mut geo = Map::new();
+        if let Some(ref city) = self.city {
+            let reader = city.reader();
+            if let Ok(found) = reader.lookup::<geoip2::City>(ip) {
+                let name = |names| localized(names, &self.language);
+                if let Some(ref country) = found.country {
+                    if let Some(code) = country.iso_code {
+                        geo.insert("country_iso_code".to_string(), json!(code));
+                    }
+                    if let Some(country_name) = name(country.names.as_ref()) {
+                        geo.insert("country_name".to_string(), json!(country_name));
+                    }
+                }
+                if let Some(city_name) = found
+                    .city
+                    .as_ref()
+                    .and_then(|city| name(city.names.as_ref()))
+                {
+                    geo.insert("city_name".to_string(), json!(city_name));
+                }
+                if let Some(ref location) = found.location
+                    && let (Some(lat), Some(lon)) = (location.latitude, location.longitude)
+                {
+                    geo.insert("location".to_string(), json!({ "lat": lat, "lon": lon }));
+                }
+            }
+        }
+        if let Some(ref asn) = self.asn {
+            let reader = asn.reader();
+            if let Ok(found) = reader.lookup::<geoip2::Asn>(ip) {
+                if let Some(number) = found.autonomous_system_number {
+                    geo.insert("asn".to_string(), json!(number));
+                }
+                if let Some(org) = found.autonomous_system_organization {
+                    geo.insert("as_org".to_string(), json!(org));
+                }
+            }
+        }
+        (!geo.is_empty()).then_some(Value::Object(geo))
+    }
+}
+
+fn localized<'a>(names: Option<&BTreeMap<&'a str, &'a str>>, language: &str) -> Option<&'a str> {
+    names.and_then(|names| names.get(language).copied())
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::pipeline::FieldPath;
+
+/// Built-in patterns, a regex-crate friendly subset of the Logstash library
+/// (no lookarounds, so the IP and time patterns are a bit more permissive).
+const GROK_PATTERNS: &[(&str, &str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("EMAILLOCALPART", r"[a-zA-Z0-9._%+-]+"),
+    ("EMAILADDRESS", r"%{EMAILLOCALPART}@%{HOSTNAME}"),
+    ("INT", r"[+-]?[0-9]+"),
+    ("BASE10NUM", r"[+-]?(?:[0-9]+(?:\.[0-9]+)?|\.[0-9]+)"),
+    ("NUMBER", r"%{BASE10NUM}"),
+    ("BASE16NUM", r"[+-]?(?:0x)?[0-9A-Fa-f]+"),
+    ("POSINT", r"\b[1-9][0-9]*\b"),
+    ("NONNEGINT", r"\b[0-9]+\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#),
+    ("QS", r"%{QUOTEDSTRING}"),
+    (
+        "UUID",
+        r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}",
+    ),
+    ("MAC", r"(?:[A-Fa-f0-9]{2}[:-]){5}[A-Fa-f0-9]{2}"),
+    (
+        "IPV4",
+        r"(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])",
+    ),
+    ("IPV6", r"(?:[A-Fa-f0-9]{0,4}:){2,7}[A-Fa-f0-9]{0,4}"),
+    ("IP", r"%{IPV4}|%{IPV6}"),
+    (
+        "HOSTNAME",
+        r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?\b",
+    ),
+    ("IPORHOST", r"%{IP}|%{HOSTNAME}"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    ("UNIXPATH", r"(?:/[^/\s]*)+"),
+    ("WINPATH", r"(?:[A-Za-z]+:|\\)(?:\\[^\\?*\s]*)+"),
+    ("PATH", r"%{UNIXPATH}|%{WINPATH}"),
+    ("URIPROTO", r"[A-Za-z][A-Za-z0-9+.-]+"),
+    ("URIHOST", r"%{IPORHOST}(?::%{POSINT})?"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("URIPARAM", r"\?[A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*"),
+    ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+    (
+        "URI",
+        r"%{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?%{URIHOST}(?:%{URIPATHPARAM})?",
+    ),
+    (
+        "MONTH",
+        r"\b(?:[Jj]an(?:uary)?|[Ff]eb(?:ruary)?|[Mm]ar(?:ch)?|[Aa]pr(?:il)?|[Mm]ay|[Jj]un(?:e)?|[Jj]ul(?:y)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo]ct(?:ober)?|[Nn]ov(?:ember)?|[Dd]ec(?:ember)?)\b",
+    ),
+    ("MONTHNUM", r"0?[1-9]|1[0-2]"),
+    ("MONTHDAY", r"0[1-9]|[12][0-9]|3[01]|[1-9]"),
+    (
+        "DAY",
+        r"Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?",
+    ),
+    ("YEAR", r"(?:\d\d){1,2}"),
+    ("HOUR", r"2[0123]|[01]?[0-9]"),
+    ("MINUTE", r"[0-5][0-9]"),
+    ("SECOND", r"(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?"),
+    ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+    ("DATE_US", r"%{MONTHNUM}[/-]%{MONTHDAY}[/-]%{YEAR}"),
+    ("DATE_EU", r"%{MONTHDAY}[./-]%{MONTHNUM}[./-]%{YEAR}"),
+    ("DATE", r"%{DATE_US}|%{DATE_EU}"),
+    ("DATESTAMP", r"%{DATE}[- ]%{TIME}"),
+    ("ISO8601_TIMEZONE", r"Z|[+-]%{HOUR}(?::?%{MINUTE})"),
+    (
+        "TIMESTAMP_ISO8601",
+        r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?",
+    ),
+    ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    ("SYSLOGHOST", r"%{IPORHOST}"),
+    ("PROG", r"[\x21-\x5a\x5c\x5e-\x7e]+"),
+    ("SYSLOGPROG", r"%{PROG:program}(?:\[%{POSINT:pid:int}\])?"),
+    (
+        "SYSLOGBASE",
+        r"%{SYSLOGTIMESTAMP:timestamp} (?:%{SYSLOGHOST:logsource} )?%{SYSLOGPROG}:",
+    ),
+    (
+        "LOGLEVEL",
+        r"[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo(?:rmation)?|INFO(?:RMATION)?|[Ww]arn(?:ing)?|WARN(?:ING)?|[Ee]rr(?:or)?|ERR(?:OR)?|[Cc]rit(?:ical)?|CRIT(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|[Ee]merg(?:ency)?|EMERG(?:ENCY)?",
+    ),
+    ("HTTPDUSER", r"%{EMAILADDRESS}|%{USER}"),
+    (
+        "COMMONAPACHELOG",
+        r#"%{IPORHOST:clientip} %{HTTPDUSER:ident} %{HTTPDUSER:auth} \[%{HTTPDATE:timestamp}\] "(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|%{DATA:rawrequest})" %{NUMBER:response:int} (?:%{NUMBER:bytes:int}|-)"#,
+    ),
+    (
+        "COMBINEDAPACHELOG",
+        r"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}",
+    ),
+];
+
+/// Deepest chain of `%{...}` references we follow before calling it a cycle.
+const MAX_DEPTH: usize = 32;
+
+/// `%{NAME}`, `%{NAME:field}` or `%{NAME:field:type}`.
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"%\{(\w+)(?::([\w.\[\]-]+))?(?::(\w+))?\}").expect("reference regex is valid")
+});
+
+/// Prefix of the capture groups generated for `%{NAME:field}` references.
+const GROUP_PREFIX: &str = "__grok";
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrokType {
+    Int,
+    Float,
+    Bool,
+    String,
+}
+
+impl GrokType {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "int" => Ok(GrokType::Int),
+            "float" => Ok(GrokType::Float),
+            "bool" => Ok(GrokType::Bool),
+            "string" => Ok(GrokType::String),
+            _ => Err(format!(
+                "{} is not a type, use int, float, bool or string",
+                name
+            )),
+        }
+    }
+
+    /// Values that don't convert stay strings rather than being lost.
+    fn coerce(self, text: &str) -> Value {
+        let converted = match self {
+            GrokType::Int => text.parse::<i64>().ok().map(Value::from),
+            GrokType::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            GrokType::Bool => match text.to_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            GrokType::String => None,
+        };
+        converted.unwrap_or_else(|| Value::String(text.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GrokCapture {
+    group: String,
+    field: FieldPath,
+    kind: Option<GrokType>,
+}
+
+#[derive(Debug, Clone)]
+struct GrokExpression {
+    regex: Regex,
+    captures: Vec<GrokCapture>,
+}
+
+/// A list of grok expressions, tried in order until one matches.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Grok {
+    expressions: Vec<GrokExpression>,
+}
+
+#[allow(dead_code)]
+impl Grok {
+    /// `definitions` add to (or override) the built-in patterns. Besides
+    /// `%{...}` references an expression can use plain named groups, and
+    /// `types` converts captures that don't carry a `:type` suffix.
+    pub fn new(
+        patterns: &[String],
+        definitions: &HashMap<String, String>,
+        types: &HashMap<String, GrokType>,
+    ) -> Result<Self, String> {
+        if patterns.is_empty() {
+            return Err("patterns must not be empty".to_string());
+        }
+        let mut expressions = Vec::with_capacity(patterns.len());
+        for (i, pattern) in patterns.iter().enumerate() {
+            let mut named = Vec::new();
+            let source = expand(pattern, definitions, &mut named, 0)
+                .map_err(|e| format!("patterns[{}]: {}", i, e))?;
+            let regex = Regex::new(&source).map_err(|e| format!("patterns[{}]: {}", i, e))?;
+
+            let mut captures = Vec::new();
+            for group in regex.capture_names().flatten() {
+                let (name, kind) = match group
+                    .strip_prefix(GROUP_PREFIX)
+                    .and_then(|index| index.parse::<usize>().ok())
+                {
+                    Some(index) => named[index].clone(),
+                    None => (group.to_string(), None),
+                };
+                captures.push(GrokCapture {
+                    group: group.to_string(),
+                    field: capture_field(&name).map_err(|e| format!("patterns[{}]: {}", i, e))?,
+                    kind: kind.or_else(|| types.get(&name).copied()),
+                });
+            }
+            if captures.is_empty() {
+                return Err(format!("patterns[{}]: captures no fields", i));
+            }
+            expressions.push(GrokExpression { regex, captures });
+        }
+        Ok(Grok { expressions })
+    }
+
+    /// Fields captured by the first expression that matches `text`. Groups
+    /// that didn't take part in the match are left out.
+    pub fn parse(&self, text: &str) -> Option<Vec<(FieldPath, Value)>> {
+        self.expressions.iter().find_map(|expression| {
+            let matched = expression.regex.captures(text)?;
+            Some(
+                expression
+                    .captures
+                    .iter()
+                    .filter_map(|capture| {
+                        let text = matched.name(&capture.group)?.as_str();
+                        let value = match capture.kind {
+                            Some(kind) => kind.coerce(text),
+                            None => Value::String(text.to_string()),
+                        };
+                        Some((capture.field.clone(), value))
+                    })
+                    .collect(),
+            )
+        })
+    }
+}
+
+/// Captures named after a field (`log_level`, `log_data.user.id`) go there,
+/// anything else lands under `log_data`.
+fn capture_field(name: &str) -> Result<FieldPath, String> {
+    FieldPath::parse(name).or_else(|_| FieldPath::parse(&format!("log_data.{}", name)))
+}
+
+fn expand(
+    pattern: &str,
+    definitions: &HashMap<String, String>,
+    named: &mut Vec<(String, Option<GrokType>)>,
+    depth: usize,
+) -> Result<String, String> {
+    if depth > MAX_DEPTH {
+        return Err(
+            "pattern references are nested too deep (is one referencing itself?)".to_string(),
+        );
+    }
+    let mut expanded = String::with_capacity(pattern.len());
+    let mut last = 0;
+    for reference in REFERENCE.captures_iter(pattern) {
+        let whole = reference.get(0).expect("group 0 always matches");
+        expanded.push_str(&pattern[last..whole.start()]);
+        last = whole.end();
+
+        let name = &reference[1];
+        let definition = definitions
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| {
+                GROK_PATTERNS
+                    .iter()
+                    .find(|(pattern_name, _)| *pattern_name == name)
+                    .map(|(_, definition)| *definition)
+            })
+            .ok_or_else(|| format!("%{{{}}} is not a known pattern", name))?;
+        let inner = expand(definition, definitions, named, depth + 1)?;
+
+        match reference.get(2) {
+            Some(field) => {
+                let kind = reference
+                    .get(3)
+                    .map(|kind| GrokType::parse(kind.as_str()))
+                    .transpose()?;
+                expanded.push_str(&format!("(?P<{}{}>{})", GROUP_PREFIX, named.len(), inner));
+                named.push((field.as_str().to_string(), kind));
+            }
+            None => expanded.push_str(&format!("(?:{})", inner)),
+        }
+    }
+    expanded.push_str(&pattern[last..]);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn grok(patterns: &[&str]) -> Result<Grok, String> {
+        grok_with(patterns, &[], &[])
+    }
+
+    fn grok_with(
+        patterns: &[&str],
+        definitions: &[(&str, &str)],
+        types: &[(&str, GrokType)],
+    ) -> Result<Grok, String> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        let definitions = definitions
+            .iter()
+            .map(|(name, definition)| (name.to_string(), definition.to_string()))
+            .collect();
+        let types = types
+            .iter()
+            .map(|(name, kind)| (name.to_string(), *kind))
+            .collect();
+        Grok::new(&patterns, &definitions, &types)
+    }
+
+    fn parse(grok: &Grok, text: &str) -> Option<HashMap<String, Value>> {
+        grok.parse(text).map(|fields| {
+            fields
+                .into_iter()
+                .map(|(field, value)| {
+                    let name = match field {
+                        FieldPath::Level => "log_level".to_string(),
+                        FieldPath::Message => "log_message".to_string(),
+                        FieldPath::Type => "log_type".to_string(),
+                        FieldPath::Source => "log_source".to_string(),
+                        FieldPath::SourceId => "log_source_id".to_string(),
+                        FieldPath::Data(path) => format!("log_data.{}", path.join(".")),
+                    };
+                    (name, value)
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn expands_nested_built_in_patterns() {
+        let grok = grok(&["%{COMMONAPACHELOG}"]).unwrap();
+        let fields = parse(
+            &grok,
+            r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#,
+        )
+        .unwrap();
+        assert_eq!(fields["log_data.clientip"], json!("127.0.0.1"));
+        assert_eq!(fields["log_data.auth"], json!("frank"));
+        assert_eq!(
+            fields["log_data.timestamp"],
+            json!("10/Oct/2000:13:55:36 -0700")
+        );
+        assert_eq!(fields["log_data.verb"], json!("GET"));
+        assert_eq!(fields["log_data.request"], json!("/apache_pb.gif"));
+        assert_eq!(fields["log_data.httpversion"], json!("1.0"));
+        assert_eq!(fields["log_data.response"], json!(200));
+        assert_eq!(fields["log_data.bytes"], json!(2326));
+        assert!(!fields.contains_key("log_data.rawrequest"));
+    }
+
+    #[test]
+    fn leaves_out_groups_that_did_not_match() {
+        let grok = grok(&["%{COMMONAPACHELOG}"]).unwrap();
+        let fields = parse(
+            &grok,
+            r#"10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 304 -"#,
+        )
+        .unwrap();
+        assert_eq!(fields["log_data.response"], json!(304));
+        assert!(!fields.contains_key("log_data.bytes"));
+    }
+
+    #[test]
+    fn keeps_fields_named_inside_referenced_patterns() {
+        let grok = grok(&["%{SYSLOGBASE} %{GREEDYDATA:log_message}"]).unwrap();
+        let fields = parse(&grok, "Mar  7 04:02:16 myhost sshd[1234]: Accepted key").unwrap();
+        assert_eq!(fields["log_data.timestamp"], json!("Mar  7 04:02:16"));
+        assert_eq!(fields["log_data.logsource"], json!("myhost"));
+        assert_eq!(fields["log_data.program"], json!("sshd"));
+        assert_eq!(fields["log_data.pid"], json!(1234));
+        assert_eq!(fields["log_message"], json!("Accepted key"));
+    }
+
+    #[test]
+    fn routes_top_level_and_nested_field_names() {
+        let grok =
+            grok(&["%{LOGLEVEL:log_level} %{WORD:user.name} %{WORD:log_data.action}"]).unwrap();
+        let fields = parse(&grok, "ERROR alice login").unwrap();
+        assert_eq!(fields["log_level"], json!("ERROR"));
+        assert_eq!(fields["log_data.user.name"], json!("alice"));
+        assert_eq!(fields["log_data.action"], json!("login"));
+    }
+
+    #[test]
+    fn tries_expressions_in_order() {
+        let grok = grok(&["^%{INT:code:int}$", "^%{WORD:word}$"]).unwrap();
+        assert_eq!(parse(&grok, "42").unwrap()["log_data.code"], json!(42));
+        assert_eq!(
+            parse(&grok, "hello").unwrap()["log_data.word"],
+            json!("hello")
+        );
+        assert_eq!(parse(&grok, "hello world"), None);
+    }
+
+    #[test]
+    fn custom_definitions_add_to_and_override_built_ins() {
+        let grok = grok_with(
+            &["%{ORDER:order} %{WORD:word}"],
+            &[("ORDER", "ORD-%{INT}"), ("WORD", "[a-z]+")],
+            &[],
+        )
+        .unwrap();
+        let fields = parse(&grok, "ORD-17 abc").unwrap();
+        assert_eq!(fields["log_data.order"], json!("ORD-17"));
+        assert_eq!(fields["log_data.word"], json!("abc"));
+        assert_eq!(parse(&grok, "ORD-17 ABC"), None);
+    }
+
+    #[test]
+    fn coerces_typed_captures() {
+        let grok =
+            grok(&[r"%{INT:count:int} %{NUMBER:ratio:float} %{WORD:ok:bool} %{INT:id:string}"])
+                .unwrap();
+        let fields = parse(&grok, "7 0.25 TRUE 0042").unwrap();
+        assert_eq!(fields["log_data.count"], json!(7));
+        assert_eq!(fields["log_data.ratio"], json!(0.25));
+        assert_eq!(fields["log_data.ok"], json!(true));
+        assert_eq!(fields["log_data.id"], json!("0042"));
+    }
+
+    #[test]
+    fn keeps_values_that_do_not_convert_as_strings() {
+        let grok = grok(&[r"%{NOTSPACE:count:int} %{NOTSPACE:ok:bool}"]).unwrap();
+        let fields = parse(&grok, "many maybe").unwrap();
+        assert_eq!(fields["log_data.count"], json!("many"));
+        assert_eq!(fields["log_data.ok"], json!("maybe"));
+    }
+
+    #[test]
+    fn types_apply_to_plain_groups_and_untyped_references() {
+        let grok = grok_with(
+            &[r"(?P<status>\d+) %{NUMBER:took}"],
+            &[],
+            &[("status", GrokType::Int), ("took", GrokType::Float)],
+        )
+        .unwrap();
+        let fields = parse(&grok, "503 1.5").unwrap();
+        assert_eq!(fields["log_data.status"], json!(503));
+        assert_eq!(fields["log_data.took"], json!(1.5));
+    }
+
+    #[test]
+    fn inline_type_wins_over_types() {
+        let grok = grok_with(&["%{INT:code:string}"], &[], &[("code", GrokType::Int)]).unwrap();
+        assert_eq!(parse(&grok, "12").unwrap()["log_data.code"], json!("12"));
+    }
+
+    #[test]
+    fn rejects_reference_cycles() {
+        let err = grok_with(&["%{A:a}"], &[("A", "%{B}"), ("B", "x%{A}")], &[]).unwrap_err();
+        assert!(err.starts_with("patterns[0]: "), "{}", err);
+        assert!(err.contains("nested too deep"), "{}", err);
+
+        let err = grok_with(&["%{SELF:a}"], &[("SELF", "%{SELF}")], &[]).unwrap_err();
+        assert!(err.contains("nested too deep"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert_eq!(
+            grok(&[]).unwrap_err(),
+            "patterns must not be empty".to_string()
+        );
+        assert_eq!(
+            grok(&["%{WORD:a}", "%{NOPE:b}"]).unwrap_err(),
+            "patterns[1]: %{NOPE} is not a known pattern"
+        );
+        assert!(grok(&["%{INT:a:number}"])
+            .unwrap_err()
+            .contains("number is not a type"));
+        assert_eq!(
+            grok(&["%{INT} %{WORD}"]).unwrap_err(),
+            "patterns[0]: captures no fields"
+        );
+        assert!(grok(&["(?P<a>["]).unwrap_err().starts_with("patterns[0]: "));
+    }
+}
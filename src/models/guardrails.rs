@@ -0,0 +1,45 @@
+use bson::Document;
+use serde::{Deserialize, Serialize};
+
+/// Limits `HogService` enforces on interactive searches. A zero disables the
+/// corresponding check.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchGuardrails {
+    /// Widest `log_timestamp`/`hog_timestamp` range allowed when the search has
+    /// no equality match on an indexed field (`hog_uuid`, `pattern_id`, `log_type`,
+    /// `log_source`).
+    pub max_unindexed_range_secs: i64,
+    /// Passed to Mongo as `maxTimeMS`.
+    pub max_time_ms: u64,
+    /// Upper bound on the documents Mongo would have to examine, estimated from
+    /// the indexed part of the filter. Only checked for searches without a time
+    /// range inside `max_unindexed_range_secs`.
+    pub max_scanned_docs: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardrailReport {
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_scanned_docs: Option<u64>,
+    pub limits: SearchGuardrails,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchExplain {
+    /// `find` or `aggregate`, depending on how the search would run.
+    pub command: String,
+    pub collection_scan: bool,
+    pub indexes_used: Vec<String>,
+    pub guardrails: GuardrailReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_plan: Option<Document>,
+    pub explain: Document,
+}
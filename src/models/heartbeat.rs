@@ -0,0 +1,186 @@
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::date_math;
+use super::notification::{parse_notification_targets, NotificationTarget};
+use super::options::{ApiErrorSchema, ErrorResponse};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceState {
+    /// Not checked yet.
+    Unknown,
+    Ok,
+    Silent,
+}
+
+/// "`log_source` (and optionally `log_source_id`) sends at least one hog every
+/// `max_silence_secs`". The last check's outcome is kept on the rule itself.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatRuleRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub log_source: String,
+    pub log_source_id: Option<String>,
+    pub max_silence_secs: i64,
+    pub notifications: Vec<NotificationTarget>,
+    pub enabled: bool,
+    pub state: SourceState,
+    pub last_seen: Option<BsonDateTime>,
+    pub silent_since: Option<BsonDateTime>,
+    pub checked_at: Option<BsonDateTime>,
+    pub created_at: BsonDateTime,
+    pub updated_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatRule {
+    pub id: String,
+    pub name: String,
+    pub log_source: String,
+    pub log_source_id: Option<String>,
+    pub max_silence_secs: i64,
+    pub notifications: Vec<NotificationTarget>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStatus {
+    pub rule_id: String,
+    pub name: String,
+    pub log_source: String,
+    pub log_source_id: Option<String>,
+    pub state: SourceState,
+    pub max_silence_secs: i64,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub silent_since: Option<DateTime<Utc>>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStatusQuery {
+    pub state: Option<SourceState>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatRuleRequest {
+    pub name: String,
+    pub log_source: String,
+    pub log_source_id: Option<String>,
+    pub max_silence_secs: i64,
+    pub notifications: Vec<NotificationTarget>,
+    pub enabled: bool,
+}
+
+#[allow(dead_code)]
+pub async fn validate_heartbeat_rule(
+    req: serde_json::Value,
+) -> Result<HeartbeatRuleRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+
+    let name = match req.get("name").and_then(|v| v.as_str()) {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "name".to_string(),
+                message: "name is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let log_source = match req.get("log_source").and_then(|v| v.as_str()) {
+        Some(log_source) if !log_source.trim().is_empty() => log_source.to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "log_source".to_string(),
+                message: "log_source is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let log_source_id = req
+        .get("log_source_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    if let Some(ref src_id) = log_source_id
+        && src_id.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_source_id".to_string(),
+            message: "log_source_id cannot be empty".to_string(),
+        });
+    }
+
+    let max_silence_secs = match req.get("max_silence").and_then(|v| v.as_str()) {
+        Some(max_silence) => match date_math::parse_duration(max_silence) {
+            Ok(duration) if duration.num_seconds() > 0 => duration.num_seconds(),
+            Ok(_) => {
+                errors.push(ApiErrorSchema {
+                    field: "max_silence".to_string(),
+                    message: "max_silence must be greater than zero".to_string(),
+                });
+                0
+            }
+            Err(message) => {
+                errors.push(ApiErrorSchema {
+                    field: "max_silence".to_string(),
+                    message,
+                });
+                0
+            }
+        },
+        None => {
+            errors.push(ApiErrorSchema {
+                field: "max_silence".to_string(),
+                message: "max_silence is required, e.g. \"5m\"".to_string(),
+            });
+            0
+        }
+    };
+
+    let notifications = match req.get("notifications") {
+        Some(notifications) => match parse_notification_targets(notifications) {
+            Ok(targets) => targets,
+            Err(message) => {
+                errors.push(ApiErrorSchema {
+                    field: "notifications".to_string(),
+                    message,
+                });
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let enabled = req.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true);
+
+    if !errors.is_empty() {
+        return Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        });
+    }
+
+    Ok(HeartbeatRuleRequest {
+        name,
+        log_source,
+        log_source_id,
+        max_silence_secs,
+        notifications,
+        enabled,
+    })
+}
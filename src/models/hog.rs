@@ -1,7 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::annotations::HogAnnotations;
 use super::client_request::ClientRequest;
+use super::lag::ClockSkew;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hog {
@@ -13,6 +15,24 @@ pub struct Hog {
     pub id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<HogAnnotations>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_id: Option<String>,
+    /// Fraction of matching hogs kept by sampling, when a sampling rule applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    /// How many identical hogs were folded into this one by deduplication.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// Set when `log_timestamp` was far ahead of or behind when the hog was
+    /// received.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<ClockSkew>,
 }
 
 #[allow(dead_code)]
@@ -30,6 +50,13 @@ impl Hog {
             hog_timestamp,
             id,
             created_at,
+            annotations: None,
+            pattern_id: None,
+            sample_rate: None,
+            repeat_count: None,
+            first_seen_at: None,
+            last_seen_at: None,
+            clock_skew: None,
         }
     }
 }
@@ -1,8 +1,11 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::timestamp::TimestampParser;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HogRequest {
-    pub log_timestamp: String,
+    pub log_timestamp: DateTime<Utc>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub log_level: Option<String>,
@@ -36,18 +39,35 @@ pub struct ErrorResponse {
 }
 
 #[allow(dead_code)]
-pub async fn validate(req: serde_json::Value) -> Result<HogRequest, ErrorResponse> {
+pub async fn validate(
+    req: serde_json::Value,
+    timestamps: &TimestampParser,
+) -> Result<HogRequest, ErrorResponse> {
     let mut errors = Vec::new();
 
-    let log_timestamp = match req.get("log_timestamp").and_then(|v| v.as_str()) {
-        Some(ts) => ts.to_string(),
-        None => {
+    let log_source = req
+        .get("log_source")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let log_timestamp = match req.get("log_timestamp") {
+        None | Some(serde_json::Value::Null) => {
             errors.push(ApiErrorSchema {
                 field: "log_timestamp".to_string(),
                 message: "log_timestamp is required".to_string(),
             });
-            String::new()
+            DateTime::UNIX_EPOCH
         }
+        Some(value) => match timestamps.parse(value, log_source.as_deref()) {
+            Ok(ts) => ts,
+            Err(message) => {
+                errors.push(ApiErrorSchema {
+                    field: "log_timestamp".to_string(),
+                    message,
+                });
+                DateTime::UNIX_EPOCH
+            }
+        },
     };
 
     let log_message = match req.get("log_message").and_then(|v| v.as_str()) {
@@ -101,11 +121,6 @@ pub async fn validate(req: serde_json::Value) -> Result<HogRequest, ErrorRespons
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
-    let log_source = req
-        .get("log_source")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
     let log_source_id = req
         .get("log_source_id")
         .and_then(|v| v.as_str())
@@ -2,6 +2,9 @@ use bson::DateTime as BsonDateTime;
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
+use super::annotations::HogAnnotationsRecord;
+use super::lag::ClockSkew;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HogRecord {
     pub log_timestamp: BsonDateTime,
@@ -28,6 +31,31 @@ pub struct HogRecord {
 
     pub created_at: Option<BsonDateTime>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<HogAnnotationsRecord>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_count: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen_at: Option<BsonDateTime>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_at: Option<BsonDateTime>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<ClockSkew>,
+
+    /// Set by the workers on the plaintext, before any field is encrypted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
 }
@@ -0,0 +1,103 @@
+use bson::DateTime as BsonDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::hog_record::HogRecord;
+use crate::config;
+
+pub const CLOCK_SKEW_FUTURE_SECS: i64 = 5 * 60;
+pub const CLOCK_SKEW_PAST_SECS: i64 = 24 * 60 * 60;
+
+/// Which way a hog's `log_timestamp` is off from when the API received it.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSkew {
+    Future,
+    Past,
+}
+
+#[allow(dead_code)]
+impl ClockSkew {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClockSkew::Future => "future",
+            ClockSkew::Past => "past",
+        }
+    }
+}
+
+/// How far `log_timestamp` may be ahead of or behind `hog_timestamp` before a
+/// hog is flagged with `clock_skew`.
+#[allow(dead_code)]
+pub struct ClockSkewLimits {
+    pub future_secs: i64,
+    pub past_secs: i64,
+}
+
+#[allow(dead_code)]
+impl ClockSkewLimits {
+    pub fn from_env() -> Self {
+        ClockSkewLimits {
+            future_secs: config::env_or("HOG_CLOCK_SKEW_FUTURE_SECS", CLOCK_SKEW_FUTURE_SECS),
+            past_secs: config::env_or("HOG_CLOCK_SKEW_PAST_SECS", CLOCK_SKEW_PAST_SECS),
+        }
+    }
+
+    /// Sets `clock_skew` on `record` when its producer's clock looks off.
+    pub fn flag(&self, record: &mut HogRecord) -> Option<ClockSkew> {
+        let received = record.hog_timestamp?.timestamp_millis();
+        let logged = record.log_timestamp.timestamp_millis();
+        let skew = if logged - received > self.future_secs * 1000 {
+            Some(ClockSkew::Future)
+        } else if received - logged > self.past_secs * 1000 {
+            Some(ClockSkew::Past)
+        } else {
+            None
+        };
+        record.clock_skew = skew;
+        skew
+    }
+}
+
+/// Seconds from `log_timestamp` to `hog_timestamp`: how long the producer
+/// took to send the hog. Negative when the producer's clock is ahead.
+#[allow(dead_code)]
+pub fn producer_lag_secs(record: &HogRecord) -> Option<f64> {
+    let received = record.hog_timestamp?;
+    Some(seconds_between(record.log_timestamp, received))
+}
+
+/// Seconds from `hog_timestamp` to `stored_at`: how long the hog spent in the
+/// queue and the worker.
+#[allow(dead_code)]
+pub fn storage_lag_secs(record: &HogRecord, stored_at: BsonDateTime) -> Option<f64> {
+    let received = record.hog_timestamp?;
+    Some(seconds_between(received, stored_at))
+}
+
+fn seconds_between(from: BsonDateTime, to: BsonDateTime) -> f64 {
+    (to.timestamp_millis() - from.timestamp_millis()) as f64 / 1000.0
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceLagQuery {
+    pub log_source: Option<String>,
+    /// RFC 3339 or a `now`-based expression. Defaults to the last hour.
+    pub since: Option<String>,
+}
+
+/// Lag of one `log_source` over the hogs received since the query's `since`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceLag {
+    pub log_source: Option<String>,
+    pub hogs: i64,
+    pub producer_lag_avg_secs: Option<f64>,
+    pub producer_lag_max_secs: Option<f64>,
+    pub storage_lag_avg_secs: Option<f64>,
+    pub storage_lag_max_secs: Option<f64>,
+    pub clock_skew_future: i64,
+    pub clock_skew_past: i64,
+    pub last_received_at: Option<String>,
+}
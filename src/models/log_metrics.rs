@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use prometheus::{
+    register_int_counter_vec, CounterVec, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec,
+    Opts,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::hog_record::HogRecord;
+use super::matcher::HogMatcher;
+use super::options::validate_options;
+use super::pipeline::FieldPath;
+
+/// Label combinations a metric may grow to before new ones are dropped.
+const LOG_METRIC_MAX_SERIES: usize = 1000;
+
+lazy_static::lazy_static! {
+    static ref HOG_LOG_METRIC_SKIPPED_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hog_log_metric_skipped_total", "Total number of hogs a log metric matched but couldn't record", &["metric", "reason"]).unwrap();
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LogMetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogMetricConfig {
+    name: String,
+    #[serde(default)]
+    help: Option<String>,
+    #[serde(rename = "type")]
+    kind: LogMetricType,
+    #[serde(default)]
+    when: Option<Value>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    buckets: Option<Vec<f64>>,
+    #[serde(default)]
+    max_series: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogMetricsConfig {
+    metrics: Vec<LogMetricConfig>,
+}
+
+enum Collector {
+    Counter(CounterVec),
+    Gauge(GaugeVec),
+    Histogram(HistogramVec),
+}
+
+struct LogMetric {
+    name: String,
+    when: Option<HogMatcher>,
+    labels: Vec<FieldPath>,
+    value: Option<FieldPath>,
+    collector: Collector,
+    max_series: usize,
+    series: Mutex<HashSet<Vec<String>>>,
+}
+
+/// What one hog adds to each metric it matched. Taken while the hog is still
+/// plaintext and recorded once it's stored, so hogs that are never stored
+/// don't count.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct LogMetricSamples(Vec<LogMetricSample>);
+
+#[derive(Debug)]
+struct LogMetricSample {
+    metric: usize,
+    label_values: Vec<String>,
+    /// `Err` holds why the hog can't be recorded, for the skipped counter.
+    value: Result<Option<f64>, &'static str>,
+}
+
+/// Prometheus metrics the workers derive from the hogs they consume, so
+/// dashboards and alerts can read Prometheus instead of querying Mongo. See
+/// `HOG_METRICS_FILE` in the README for the format.
+#[allow(dead_code)]
+pub struct LogMetrics {
+    metrics: Vec<LogMetric>,
+}
+
+#[allow(dead_code)]
+impl LogMetrics {
+    pub async fn load(path: &str, encrypted: &[FieldPath]) -> Result<Self, String> {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config, encrypted)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Builds the metrics and registers them with the default registry.
+    /// Labels and values can't read the `encrypted` fields: they're exported
+    /// in plaintext.
+    pub async fn from_config(config: Value, encrypted: &[FieldPath]) -> Result<Self, String> {
+        let config: LogMetricsConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        let mut metrics = Vec::with_capacity(config.metrics.len());
+        for (i, metric) in config.metrics.into_iter().enumerate() {
+            let field =
+                |path: &str| FieldPath::parse(path).map_err(|e| format!("metrics[{}]: {}", i, e));
+            let labels = metric
+                .labels
+                .iter()
+                .map(|path| field(path))
+                .collect::<Result<Vec<_>, _>>()?;
+            for (path, parsed) in metric.labels.iter().zip(&labels) {
+                if reads_encrypted(parsed, encrypted) {
+                    return Err(format!(
+                        "metrics[{}]: label {} is in HOG_ENCRYPTED_FIELDS",
+                        i, path
+                    ));
+                }
+            }
+            let label_names: Vec<String> =
+                metric.labels.iter().map(|path| label_name(path)).collect();
+            for (j, name) in label_names.iter().enumerate() {
+                if label_names[..j].contains(name) {
+                    return Err(format!("metrics[{}]: two labels are named {}", i, name));
+                }
+            }
+            let label_names: Vec<&str> = label_names.iter().map(String::as_str).collect();
+
+            let value = metric.value.as_deref().map(field).transpose()?;
+            if let Some(ref parsed) = value
+                && reads_encrypted(parsed, encrypted)
+            {
+                return Err(format!(
+                    "metrics[{}]: value {} is in HOG_ENCRYPTED_FIELDS",
+                    i,
+                    metric.value.as_deref().unwrap_or_default()
+                ));
+            }
+            if value.is_none() && !matches!(metric.kind, LogMetricType::Counter) {
+                return Err(format!(
+                    "metrics[{}]: gauges and histograms need a value",
+                    i
+                ));
+            }
+            if metric.buckets.is_some() && !matches!(metric.kind, LogMetricType::Histogram) {
+                return Err(format!("metrics[{}]: only histograms take buckets", i));
+            }
+
+            let help = metric
+                .help
+                .unwrap_or_else(|| format!("Derived from hogs by the {} log metric", metric.name));
+            let opts = Opts::new(metric.name.clone(), help);
+            let invalid = |e: prometheus::Error| format!("metrics[{}]: {}", i, e);
+            let collector = match metric.kind {
+                LogMetricType::Counter => {
+                    let counter = CounterVec::new(opts, &label_names).map_err(invalid)?;
+                    prometheus::register(Box::new(counter.clone())).map_err(invalid)?;
+                    Collector::Counter(counter)
+                }
+                LogMetricType::Gauge => {
+                    let gauge = GaugeVec::new(opts, &label_names).map_err(invalid)?;
+                    prometheus::register(Box::new(gauge.clone())).map_err(invalid)?;
+                    Collector::Gauge(gauge)
+                }
+                LogMetricType::Histogram => {
+                    let mut opts = HistogramOpts::from(opts);
+                    if let Some(buckets) = metric.buckets {
+                        opts = opts.buckets(buckets);
+                    }
+                    let histogram = HistogramVec::new(opts, &label_names).map_err(invalid)?;
+                    prometheus::register(Box::new(histogram.clone())).map_err(invalid)?;
+                    Collector::Histogram(histogram)
+                }
+            };
+
+            let when = match metric.when {
+                Some(when) => Some(
+                    HogMatcher::new(validate_options(when).await.map_err(|validation_error| {
+                        let errors: Vec<String> = validation_error
+                            .errors
+                            .into_iter()
+                            .map(|e| format!("{}: {}", e.field, e.message))
+                            .collect();
+                        format!("metrics[{}].when: {}", i, errors.join(", "))
+                    })?)
+                    .map_err(|e| format!("metrics[{}].when: {}", i, e))?,
+                ),
+                None => None,
+            };
+            metrics.push(LogMetric {
+                name: metric.name,
+                when,
+                labels,
+                value,
+                collector,
+                max_series: metric.max_series.unwrap_or(LOG_METRIC_MAX_SERIES),
+                series: Mutex::new(HashSet::new()),
+            });
+        }
+        Ok(LogMetrics { metrics })
+    }
+
+    /// What `record` adds to every metric whose `when` it matches.
+    pub fn sample(&self, record: &HogRecord) -> LogMetricSamples {
+        let mut samples = Vec::new();
+        for (index, metric) in self.metrics.iter().enumerate() {
+            if let Some(ref when) = metric.when
+                && !when.matches(record)
+            {
+                continue;
+            }
+            let value = match metric.value {
+                // Hogs without the field just aren't part of the metric.
+                Some(ref path) => match path.get(record) {
+                    None | Some(Value::Null) => continue,
+                    Some(value) => as_number(&value).map(Some).ok_or("not_a_number"),
+                },
+                None => Ok(None),
+            };
+            samples.push(LogMetricSample {
+                metric: index,
+                label_values: metric
+                    .labels
+                    .iter()
+                    .map(|path| label_value(path.get(record)))
+                    .collect(),
+                value,
+            });
+        }
+        LogMetricSamples(samples)
+    }
+
+    /// Records samples once their hog is stored.
+    pub fn record(&self, samples: LogMetricSamples) {
+        for sample in samples.0 {
+            let metric = &self.metrics[sample.metric];
+            let value = match sample.value {
+                Ok(value) => value,
+                Err(reason) => {
+                    metric.skip(reason);
+                    continue;
+                }
+            };
+            {
+                let mut series = metric.series.lock().unwrap();
+                if !series.contains(&sample.label_values) {
+                    if series.len() >= metric.max_series {
+                        metric.skip("max_series");
+                        continue;
+                    }
+                    series.insert(sample.label_values.clone());
+                }
+            }
+            let label_values: Vec<&str> = sample.label_values.iter().map(String::as_str).collect();
+            match metric.collector {
+                Collector::Counter(ref counter) => {
+                    let by = value.unwrap_or(1.0);
+                    // Counters only go up.
+                    if by < 0.0 {
+                        metric.skip("negative_value");
+                        continue;
+                    }
+                    counter.with_label_values(&label_values).inc_by(by);
+                }
+                Collector::Gauge(ref gauge) => {
+                    gauge
+                        .with_label_values(&label_values)
+                        .set(value.unwrap_or_default());
+                }
+                Collector::Histogram(ref histogram) => {
+                    histogram
+                        .with_label_values(&label_values)
+                        .observe(value.unwrap_or_default());
+                }
+            }
+        }
+    }
+}
+
+impl LogMetric {
+    fn skip(&self, reason: &str) {
+        HOG_LOG_METRIC_SKIPPED_TOTAL
+            .with_label_values(&[self.name.as_str(), reason])
+            .inc();
+    }
+}
+
+/// `log_data.http.status` becomes the `status` label; anything Prometheus
+/// doesn't allow in a label name becomes `_`.
+fn label_name(path: &str) -> String {
+    let name = path.rsplit('.').next().unwrap_or(path);
+    let mut label: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if label.starts_with(|c: char| c.is_ascii_digit()) {
+        label.insert(0, '_');
+    }
+    label
+}
+
+/// Whether `path` is an encrypted field, inside one, or an object holding one.
+fn reads_encrypted(path: &FieldPath, encrypted: &[FieldPath]) -> bool {
+    let FieldPath::Data(path) = path else {
+        return false;
+    };
+    encrypted.iter().any(|field| match field {
+        FieldPath::Data(field) => path.starts_with(field) || field.starts_with(path),
+        _ => false,
+    })
+}
+
+fn label_value(value: Option<Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text,
+        Some(other) => other.to_string(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|number: &f64| number.is_finite())
+}
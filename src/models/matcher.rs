@@ -0,0 +1,177 @@
+use bson::DateTime as BsonDateTime;
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+
+use super::hog_record::HogRecord;
+use super::options::OptionsRequest;
+
+/// In-memory counterpart of `build_filter`: evaluates an `OptionsRequest`
+/// against a single record without going through Mongo. Used wherever records
+/// have to be matched as they flow past instead of being queried.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct HogMatcher {
+    options: OptionsRequest,
+    log_message_regex: Option<Regex>,
+}
+
+#[allow(dead_code)]
+impl HogMatcher {
+    /// Fails when `log_message` is a partial match that isn't a valid regex,
+    /// so a rule that could never match as written is rejected up front.
+    pub fn new(options: OptionsRequest) -> Result<Self, String> {
+        let log_message_regex = match (&options.log_message, options.hog_partial) {
+            (Some(log_message), Some(true)) => Some(log_message_regex(log_message)?),
+            _ => None,
+        };
+
+        Ok(HogMatcher {
+            options,
+            log_message_regex,
+        })
+    }
+
+    pub fn options(&self) -> &OptionsRequest {
+        &self.options
+    }
+
+    pub fn matches(&self, record: &HogRecord) -> bool {
+        let options = &self.options;
+
+        if !eq_opt(&options.log_level, &record.log_level)
+            || !eq_opt(&options.log_source, &record.log_source)
+            || !eq_opt(&options.log_source_id, &record.log_source_id)
+            || !eq_opt(&options.log_type, &record.log_type)
+            || !eq_opt(&options.hog_uuid, &record.hog_uuid)
+            || !eq_opt(&options.pattern_id, &record.pattern_id)
+        {
+            return false;
+        }
+
+        if let Some(ref log_message) = options.log_message {
+            let matched = match self.log_message_regex {
+                Some(ref regex) => regex.is_match(&record.log_message),
+                None => &record.log_message == log_message,
+            };
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(ref log_data) = options.log_data
+            && record.log_data.as_ref() != Some(log_data)
+        {
+            return false;
+        }
+
+        if options.log_timestamp_start.is_some() || options.log_timestamp_end.is_some() {
+            if !in_range(
+                Some(record.log_timestamp),
+                options.log_timestamp_start,
+                options.log_timestamp_end,
+            ) {
+                return false;
+            }
+        } else if let Some(timestamp) = options.log_timestamp
+            && record.log_timestamp != BsonDateTime::from_chrono(timestamp)
+        {
+            return false;
+        }
+
+        if let Some(hog_timestamp) = options.hog_timestamp {
+            if record.hog_timestamp != Some(BsonDateTime::from_chrono(hog_timestamp)) {
+                return false;
+            }
+        } else if (options.hog_timestamp_start.is_some() || options.hog_timestamp_end.is_some())
+            && !in_range(
+                record.hog_timestamp,
+                options.hog_timestamp_start,
+                options.hog_timestamp_end,
+            )
+        {
+            return false;
+        }
+
+        let log_data = record.log_data.as_ref();
+
+        match (&options.log_data_value, &options.log_data_field) {
+            (Some(value), Some(field)) => {
+                if lookup(log_data, field) != value.as_ref() {
+                    return false;
+                }
+            }
+            (Some(Some(value)), None) => {
+                let found = log_data
+                    .and_then(|data| data.as_object())
+                    .is_some_and(|map| map.values().any(|v| v == value));
+                if !found {
+                    return false;
+                }
+            }
+            (Some(None), None) => {}
+            (None, Some(field)) => {
+                if lookup(log_data, field).is_none() {
+                    return false;
+                }
+            }
+            (None, None) => {}
+        }
+
+        if let Some(Some(log_data_values)) = &options.log_data_values
+            && let Some(map) = log_data_values.as_object()
+        {
+            for (key, expected) in map {
+                if lookup(log_data, key) != Some(expected) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// The regex a partial `log_message` search runs, case-insensitive like the
+/// Mongo `$regex` that `build_filter` builds.
+pub fn log_message_regex(log_message: &str) -> Result<Regex, String> {
+    RegexBuilder::new(log_message)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("log_message is not a valid regex: {}", e))
+}
+
+fn eq_opt(expected: &Option<String>, actual: &Option<String>) -> bool {
+    match expected {
+        Some(expected) => actual.as_deref() == Some(expected.as_str()),
+        None => true,
+    }
+}
+
+fn in_range(
+    value: Option<BsonDateTime>,
+    start: Option<chrono::DateTime<chrono::Utc>>,
+    end: Option<chrono::DateTime<chrono::Utc>>,
+) -> bool {
+    let Some(value) = value else {
+        return false;
+    };
+    if let Some(start) = start
+        && value < BsonDateTime::from_chrono(start)
+    {
+        return false;
+    }
+    if let Some(end) = end
+        && value > BsonDateTime::from_chrono(end)
+    {
+        return false;
+    }
+    true
+}
+
+/// Resolves a dotted `log_data` path (`"a.b.c"`) the same way Mongo does for
+/// `log_data.a.b.c`.
+#[allow(dead_code)]
+pub fn lookup<'a>(log_data: Option<&'a Value>, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(log_data?, |value, segment| value.get(segment))
+}
@@ -1,6 +1,33 @@
+pub mod alert;
+pub mod annotations;
+pub mod anomaly;
 pub mod client_request;
+pub mod context;
+pub mod date_math;
+pub mod dedup;
+pub mod embedded;
+pub mod encryption;
+pub mod export;
+pub mod geoip;
+pub mod grok;
+pub mod guardrails;
+pub mod heartbeat;
 pub mod hog;
 pub mod hog_client_schema;
 pub mod hog_record;
+pub mod lag;
+pub mod log_metrics;
+pub mod matcher;
+pub mod multiline;
+pub mod notification;
 pub mod options;
+pub mod pattern;
+pub mod pipeline;
+pub mod redaction;
+pub mod sampling;
+pub mod saved_search;
+pub mod search_job;
 pub mod statistics;
+pub mod stream_alert;
+pub mod tail;
+pub mod timestamp;
@@ -0,0 +1,231 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::hog_record::HogRecord;
+
+const MULTILINE_MAX_LINES: usize = 500;
+const MULTILINE_FLUSH_AFTER_MS: u64 = 2_000;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultilineRuleConfig {
+    log_source_id: String,
+    start: String,
+    continuation: Option<String>,
+    #[serde(default = "default_max_lines")]
+    max_lines: usize,
+    #[serde(default = "default_flush_after_ms")]
+    flush_after_ms: u64,
+}
+
+fn default_max_lines() -> usize {
+    MULTILINE_MAX_LINES
+}
+
+fn default_flush_after_ms() -> u64 {
+    MULTILINE_FLUSH_AFTER_MS
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultilineConfig {
+    rules: Vec<MultilineRuleConfig>,
+}
+
+struct MultilineRule {
+    start: Regex,
+    continuation: Option<Regex>,
+    max_lines: usize,
+    flush_after_ms: u64,
+}
+
+impl MultilineRule {
+    /// Without a continuation pattern, every line that doesn't start a new
+    /// event continues the open one.
+    fn continues(&self, message: &str) -> bool {
+        match self.continuation {
+            Some(ref continuation) => continuation.is_match(message),
+            None => !self.start.is_match(message),
+        }
+    }
+}
+
+/// An event that is still collecting lines.
+struct PendingEvent {
+    record: HogRecord,
+    lines: usize,
+    flush_at_ms: i64,
+}
+
+/// What pushing one line gave back.
+#[allow(dead_code)]
+pub struct MultilineOutcome {
+    /// Events the line completed, ready to publish.
+    pub flushed: Vec<HogRecord>,
+    /// The line itself when no rule held on to it.
+    pub current: Option<HogRecord>,
+}
+
+/// Merges lines of the same `log_source_id` into one hog: a line matching
+/// `start` opens an event and the lines after it that match `continuation`
+/// are appended to its `log_message`. The event is complete on the next line
+/// that doesn't continue it, after `max_lines`, or once nothing was appended
+/// for `flush_after_ms`. It only sees lines, not how they arrived, so any
+/// ingest path can feed it. See `HOG_MULTILINE_FILE` in the README.
+#[allow(dead_code)]
+pub struct MultilineAggregator {
+    rules: HashMap<String, MultilineRule>,
+    pending: Mutex<HashMap<String, PendingEvent>>,
+    /// Completed events that failed to publish, handed out again by the next
+    /// `flush_expired`.
+    retry: Mutex<Vec<HogRecord>>,
+}
+
+#[allow(dead_code)]
+impl MultilineAggregator {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_config(config: Value) -> Result<Self, String> {
+        let config: MultilineConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        let mut rules = HashMap::new();
+        for (i, rule) in config.rules.into_iter().enumerate() {
+            let start =
+                Regex::new(&rule.start).map_err(|e| format!("rules[{}]: start: {}", i, e))?;
+            let continuation = rule
+                .continuation
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()
+                .map_err(|e| format!("rules[{}]: continuation: {}", i, e))?;
+            if rule.max_lines < 2 {
+                return Err(format!("rules[{}]: max_lines must be at least 2", i));
+            }
+            if rule.flush_after_ms == 0 {
+                return Err(format!("rules[{}]: flush_after_ms must be above 0", i));
+            }
+            let compiled = MultilineRule {
+                start,
+                continuation,
+                max_lines: rule.max_lines,
+                flush_after_ms: rule.flush_after_ms,
+            };
+            if rules.insert(rule.log_source_id.clone(), compiled).is_some() {
+                return Err(format!(
+                    "rules[{}]: log_source_id {} has more than one rule",
+                    i, rule.log_source_id
+                ));
+            }
+        }
+        Ok(MultilineAggregator {
+            rules,
+            pending: Mutex::new(HashMap::new()),
+            retry: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Feeds one line in. `now_ms` is the current time in milliseconds.
+    pub fn push(&self, record: HogRecord, now_ms: i64) -> MultilineOutcome {
+        let Some((source_id, rule)) = record
+            .log_source_id
+            .as_ref()
+            .and_then(|id| self.rules.get_key_value(id))
+        else {
+            return MultilineOutcome {
+                flushed: Vec::new(),
+                current: Some(record),
+            };
+        };
+        let message = record.log_message.as_str();
+
+        let mut pending = self.pending.lock().unwrap();
+        let mut flushed = Vec::new();
+        if let Some(event) = pending.get_mut(source_id)
+            && rule.continues(message)
+        {
+            event.record.log_message.push('\n');
+            event.record.log_message.push_str(message);
+            event.lines += 1;
+            event.flush_at_ms = now_ms + rule.flush_after_ms as i64;
+            if event.lines >= rule.max_lines {
+                flushed.extend(pending.remove(source_id).map(|event| event.record));
+            }
+            return MultilineOutcome {
+                flushed,
+                current: None,
+            };
+        }
+
+        flushed.extend(pending.remove(source_id).map(|event| event.record));
+        if !rule.start.is_match(message) {
+            return MultilineOutcome {
+                flushed,
+                current: Some(record),
+            };
+        }
+        pending.insert(
+            source_id.clone(),
+            PendingEvent {
+                record,
+                lines: 1,
+                flush_at_ms: now_ms + rule.flush_after_ms as i64,
+            },
+        );
+        MultilineOutcome {
+            flushed,
+            current: None,
+        }
+    }
+
+    /// Takes out the events nothing was appended to for their rule's
+    /// `flush_after_ms`, and the ones that are waiting to be published again.
+    pub fn flush_expired(&self, now_ms: i64) -> Vec<HogRecord> {
+        let mut pending = self.pending.lock().unwrap();
+        let expired: Vec<String> = pending
+            .iter()
+            .filter(|(_, event)| event.flush_at_ms <= now_ms)
+            .map(|(source_id, _)| source_id.clone())
+            .collect();
+        let mut events = std::mem::take(&mut *self.retry.lock().unwrap());
+        events.extend(
+            expired
+                .into_iter()
+                .filter_map(|source_id| pending.remove(&source_id))
+                .map(|event| event.record),
+        );
+        events
+    }
+
+    /// Takes out every event, complete or not, for when the API stops.
+    pub fn flush_all(&self) -> Vec<HogRecord> {
+        let mut events = std::mem::take(&mut *self.retry.lock().unwrap());
+        events.extend(
+            self.pending
+                .lock()
+                .unwrap()
+                .drain()
+                .map(|(_, event)| event.record),
+        );
+        events
+    }
+
+    /// Holds on to a completed event that failed to publish, so it isn't
+    /// lost with the request that completed it.
+    pub fn requeue(&self, record: HogRecord) {
+        self.retry.lock().unwrap().push(record);
+    }
+
+    /// The shortest `flush_after_ms` of any rule, for how often to check.
+    pub fn check_interval_ms(&self) -> u64 {
+        self.rules
+            .values()
+            .map(|rule| rule.flush_after_ms)
+            .min()
+            .unwrap_or(MULTILINE_FLUSH_AFTER_MS)
+    }
+}
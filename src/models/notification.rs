@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::options::{ApiErrorSchema, ErrorResponse};
+
+/// Where a notification goes.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationTarget {
+    /// JSON `Notification` POSTed as is.
+    Webhook { url: String },
+    /// Slack-compatible incoming webhook, gets `{"text": ...}`.
+    Slack { url: String },
+    /// Plain text mail through the `SMTP_*` server.
+    Smtp { to: Vec<String> },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationStatus {
+    Firing,
+    Resolved,
+}
+
+#[allow(dead_code)]
+impl NotificationStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationStatus::Firing => "FIRING",
+            NotificationStatus::Resolved => "RESOLVED",
+        }
+    }
+}
+
+/// One of the alerts a grouped notification is about.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationAlert {
+    pub labels: BTreeMap<String, String>,
+    pub value: i64,
+    pub since: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    /// What raised it, e.g. `heartbeat`.
+    pub source: String,
+    pub status: NotificationStatus,
+    pub title: String,
+    pub message: String,
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<NotificationAlert>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+impl Notification {
+    /// Text version for chat and mail.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("[{}] {}\n{}", self.status.label(), self.title, self.message);
+        for alert in &self.alerts {
+            let labels: Vec<String> = alert
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            text.push_str(&format!("\n- {} ({})", labels.join(", "), alert.value));
+        }
+        text
+    }
+}
+
+/// A named, reusable notification target.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannelRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub target: NotificationTarget,
+    pub created_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannel {
+    pub id: String,
+    pub name: String,
+    pub target: NotificationTarget,
+    pub created_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannelRequest {
+    pub name: String,
+    pub target: NotificationTarget,
+}
+
+#[allow(dead_code)]
+pub async fn validate_notification_channel(
+    req: serde_json::Value,
+) -> Result<NotificationChannelRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+
+    let name = match req.get("name").and_then(|v| v.as_str()) {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "name".to_string(),
+                message: "name is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let target = match req.get("target").map(parse_notification_target) {
+        Some(Ok(target)) => Some(target),
+        Some(Err(message)) => {
+            errors.push(ApiErrorSchema {
+                field: "target".to_string(),
+                message,
+            });
+            None
+        }
+        None => {
+            errors.push(ApiErrorSchema {
+                field: "target".to_string(),
+                message: "target is required".to_string(),
+            });
+            None
+        }
+    };
+
+    match target {
+        Some(target) if errors.is_empty() => Ok(NotificationChannelRequest { name, target }),
+        _ => Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        }),
+    }
+}
+
+/// Parses a `notifications` array from a request body.
+#[allow(dead_code)]
+pub fn parse_notification_targets(
+    value: &serde_json::Value,
+) -> Result<Vec<NotificationTarget>, String> {
+    let Some(targets) = value.as_array() else {
+        return Err("notifications must be an array".to_string());
+    };
+    targets.iter().map(parse_notification_target).collect()
+}
+
+#[allow(dead_code)]
+pub fn parse_notification_target(value: &serde_json::Value) -> Result<NotificationTarget, String> {
+    let target: NotificationTarget = serde_json::from_value(value.clone()).map_err(|e| {
+        format!(
+            "expected {{\"type\": \"webhook\" | \"slack\", \"url\": ...}} or {{\"type\": \"smtp\", \"to\": [...]}}: {}",
+            e
+        )
+    })?;
+    match target {
+        NotificationTarget::Webhook { ref url } | NotificationTarget::Slack { ref url } => {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!("url {} must be http(s)", url));
+            }
+        }
+        NotificationTarget::Smtp { ref to } => {
+            if to.is_empty() || to.iter().any(|address| !address.contains('@')) {
+                return Err("to must be a non-empty list of mail addresses".to_string());
+            }
+        }
+    }
+    Ok(target)
+}
@@ -1,15 +1,19 @@
 use std::collections::HashMap;
 
-use bson::{Bson, doc};
+use bson::doc;
 use mongodb::bson::{self, Document};
 use serde::{Deserialize, Serialize};
 
+use super::date_math;
+use super::matcher;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SortType {
     Ascending,
     Descending,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
     None,
@@ -37,6 +41,7 @@ pub struct OptionsRequest {
     pub log_source: Option<String>,
     pub log_source_id: Option<String>,
     pub hog_uuid: Option<String>,
+    pub pattern_id: Option<String>,
     pub hog_limit: Option<i64>,
     pub hog_sort: Option<SortType>,       // TODO: Implement this
     pub hog_partial: Option<bool>,        // TODO: Implement this, currently only partial on mensage
@@ -79,14 +84,14 @@ pub fn build_filter(options: &OptionsRequest) -> Document {
     if let Some(ref hog_uuid) = options.hog_uuid {
         filter.insert("hog_uuid", hog_uuid);
     }
+    if let Some(ref pattern_id) = options.pattern_id {
+        filter.insert("pattern_id", pattern_id);
+    }
 
-    if let Some(ref log_data) = options.log_data {
-        match bson::to_bson(log_data) {
-            Ok(bson_data) => {
-                filter.insert("log_data", bson_data);
-            }
-            Err(_) => {}
-        }
+    if let Some(ref log_data) = options.log_data
+        && let Ok(bson_data) = bson::to_bson(log_data)
+    {
+        filter.insert("log_data", bson_data);
     }
     if options.log_timestamp_start.is_some() || options.log_timestamp_end.is_some() {
         let mut ts_filter: Document = Document::new();
@@ -148,7 +153,7 @@ pub fn build_filter(options: &OptionsRequest) -> Document {
         if let Some(map) = log_data_values.as_object() {
             for (k, v) in map {
                 let prefixed = format!("log_data.{}", k);
-                flat_doc.insert(prefixed.clone(), Bson::from(bson::to_bson(v).unwrap()));
+                flat_doc.insert(prefixed.clone(), bson::to_bson(v).unwrap());
             }
         }
         filter.extend(flat_doc);
@@ -232,161 +237,206 @@ pub struct ErrorResponse {
 #[allow(dead_code)]
 pub async fn validate_options(req: serde_json::Value) -> Result<OptionsRequest, ErrorResponse> {
     let mut errors = Vec::new();
+    // Every relative timestamp in a request resolves against the same `now`.
+    let now = chrono::Utc::now();
 
     // Example: extract fields as Option<T>
     let log_level = req
         .get("log_level")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref lvl) = log_level {
-        if lvl.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_level".to_string(),
-                message: "log_level cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref lvl) = log_level
+        && lvl.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_level".to_string(),
+            message: "log_level cannot be empty".to_string(),
+        });
     }
 
     let log_message = req
         .get("log_message")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref msg) = log_message {
-        if msg.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_message".to_string(),
-                message: "log_message cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref msg) = log_message
+        && msg.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_message".to_string(),
+            message: "log_message cannot be empty".to_string(),
+        });
     }
 
     let log_data_values = req.get("log_data_values").cloned();
-    if let Some(ref log_data) = log_data_values {
-        if !log_data.is_object() {
-            errors.push(ApiErrorSchema {
-                field: "log_data".to_string(),
-                message: "log_data must be a valid JSON object".to_string(),
-            });
-        }
+    if let Some(ref log_data) = log_data_values
+        && !log_data.is_object()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_data".to_string(),
+            message: "log_data must be a valid JSON object".to_string(),
+        });
     }
 
     let log_type = req
         .get("log_type")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref t) = log_type {
-        if t.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_type".to_string(),
-                message: "log_type cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref t) = log_type
+        && t.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_type".to_string(),
+            message: "log_type cannot be empty".to_string(),
+        });
     }
 
     let log_source = req
         .get("log_source")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref src) = log_source {
-        if src.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_source".to_string(),
-                message: "log_source cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref src) = log_source
+        && src.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_source".to_string(),
+            message: "log_source cannot be empty".to_string(),
+        });
     }
 
     let log_source_id = req
         .get("log_source_id")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref src_id) = log_source_id {
-        if src_id.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_source_id".to_string(),
-                message: "log_source_id cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref src_id) = log_source_id
+        && src_id.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_source_id".to_string(),
+            message: "log_source_id cannot be empty".to_string(),
+        });
     }
 
-    let log_timestamp = req
-        .get("log_timestamp")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = log_timestamp {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "log_timestamp".to_string(),
-                message: "log_timestamp cannot be before epoch".to_string(),
-            });
-        }
+    let log_timestamp = resolve_timestamp(&req, "log_timestamp", now, false, &mut errors);
+    if let Some(ts) = log_timestamp
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_timestamp".to_string(),
+            message: "log_timestamp cannot be before epoch".to_string(),
+        });
     }
-    let log_timestamp_start = req
-        .get("log_timestamp_start")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = log_timestamp_start {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "log_timestamp_start".to_string(),
-                message: "log_timestamp_start cannot be before epoch".to_string(),
-            });
-        }
+    let log_timestamp_start =
+        resolve_timestamp(&req, "log_timestamp_start", now, false, &mut errors);
+    if let Some(ts) = log_timestamp_start
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_timestamp_start".to_string(),
+            message: "log_timestamp_start cannot be before epoch".to_string(),
+        });
     }
-    let log_timestamp_end = req
-        .get("log_timestamp_end")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = log_timestamp_end {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "log_timestamp_end".to_string(),
-                message: "log_timestamp_end cannot be before epoch".to_string(),
-            });
-        }
+    let log_timestamp_end = resolve_timestamp(&req, "log_timestamp_end", now, true, &mut errors);
+    if let Some(ts) = log_timestamp_end
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_timestamp_end".to_string(),
+            message: "log_timestamp_end cannot be before epoch".to_string(),
+        });
+    }
+
+    let hog_last = req.get("hog_last").and_then(|v| v.as_str()).and_then(|s| {
+        date_math::parse_duration(s)
+            .map_err(|message| {
+                errors.push(ApiErrorSchema {
+                    field: "hog_last".to_string(),
+                    message,
+                })
+            })
+            .ok()
+    });
+    if hog_last.is_some()
+        && (req.get("log_timestamp_start").is_some() || req.get("log_timestamp_end").is_some())
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_last".to_string(),
+            message: "hog_last cannot be combined with log_timestamp_start or log_timestamp_end"
+                .to_string(),
+        });
     }
+    let (log_timestamp_start, log_timestamp_end) = match hog_last {
+        Some(last) => match now.checked_sub_signed(last) {
+            Some(start) => (Some(start), Some(now)),
+            None => {
+                errors.push(ApiErrorSchema {
+                    field: "hog_last".to_string(),
+                    message: "hog_last is out of range".to_string(),
+                });
+                (None, None)
+            }
+        },
+        None => (log_timestamp_start, log_timestamp_end),
+    };
 
     let hog_uuid = req
         .get("hog_uuid")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref uuid) = hog_uuid {
-        if uuid.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "hog_uuid".to_string(),
-                message: "hog_uuid cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref uuid) = hog_uuid
+        && uuid.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_uuid".to_string(),
+            message: "hog_uuid cannot be empty".to_string(),
+        });
+    }
+    let pattern_id = req
+        .get("pattern_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    if let Some(ref id) = pattern_id
+        && id.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "pattern_id".to_string(),
+            message: "pattern_id cannot be empty".to_string(),
+        });
     }
     let hog_limit = req.get("hog_limit").and_then(|v| v.as_i64());
-    if let Some(limit) = hog_limit {
-        if limit <= 0 {
-            errors.push(ApiErrorSchema {
-                field: "hog_limit".to_string(),
-                message: "hog_limit must be greater than zero".to_string(),
-            });
-        }
+    if let Some(limit) = hog_limit
+        && limit <= 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_limit".to_string(),
+            message: "hog_limit must be greater than zero".to_string(),
+        });
     }
     let hog_partial = req.get("hog_partial").and_then(|v| v.as_bool());
-    if let Some(partial) = hog_partial {
-        if !partial {
-            errors.push(ApiErrorSchema {
-                field: "hog_partial".to_string(),
-                message: "hog_partial must be true for this operation".to_string(),
-            });
-        }
+    if let Some(partial) = hog_partial
+        && !partial
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_partial".to_string(),
+            message: "hog_partial must be true for this operation".to_string(),
+        });
+    }
+    if hog_partial == Some(true)
+        && let Some(ref msg) = log_message
+        && let Err(message) = matcher::log_message_regex(msg)
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_message".to_string(),
+            message,
+        });
     }
     let hog_case_sensitive = req.get("hog_case_sensitive").and_then(|v| v.as_bool());
-    if let Some(case_sensitive) = hog_case_sensitive {
-        if !case_sensitive {
-            errors.push(ApiErrorSchema {
-                field: "hog_case_sensitive".to_string(),
-                message: "hog_case_sensitive must be true for this operation".to_string(),
-            });
-        }
+    if let Some(case_sensitive) = hog_case_sensitive
+        && !case_sensitive
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_case_sensitive".to_string(),
+            message: "hog_case_sensitive must be true for this operation".to_string(),
+        });
     }
     let hog_sort = req.get("hog_sort").and_then(|v| v.as_str()).map(|s| {
         Some(match s {
@@ -406,100 +456,89 @@ pub async fn validate_options(req: serde_json::Value) -> Result<OptionsRequest,
         .get("hog_sort_field")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref sort_field) = hog_sort_field {
-        if sort_field.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "hog_sort_field".to_string(),
-                message: "hog_sort_field cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref sort_field) = hog_sort_field
+        && sort_field.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_sort_field".to_string(),
+            message: "hog_sort_field cannot be empty".to_string(),
+        });
     }
     let hog_fields = req.get("hog_fields").and_then(|v| v.as_array()).map(|arr| {
         arr.iter()
             .filter_map(|v| v.as_str().map(|s| s.to_string()))
             .collect::<Vec<String>>()
     });
-    if let Some(ref fields) = hog_fields {
-        if fields.is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "hog_fields".to_string(),
-                message: "hog_fields cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref fields) = hog_fields
+        && fields.is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_fields".to_string(),
+            message: "hog_fields cannot be empty".to_string(),
+        });
     }
 
-    let hog_timestamp = req
-        .get("hog_timestamp")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = hog_timestamp {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "hog_timestamp".to_string(),
-                message: "hog_timestamp cannot be before epoch".to_string(),
-            });
-        }
+    let hog_timestamp = resolve_timestamp(&req, "hog_timestamp", now, false, &mut errors);
+    if let Some(ts) = hog_timestamp
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_timestamp".to_string(),
+            message: "hog_timestamp cannot be before epoch".to_string(),
+        });
     }
-    let hog_timestamp_start = req
-        .get("hog_timestamp_start")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = hog_timestamp_start {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "hog_timestamp_start".to_string(),
-                message: "hog_timestamp_start cannot be before epoch".to_string(),
-            });
-        }
+    let hog_timestamp_start =
+        resolve_timestamp(&req, "hog_timestamp_start", now, false, &mut errors);
+    if let Some(ts) = hog_timestamp_start
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_timestamp_start".to_string(),
+            message: "hog_timestamp_start cannot be before epoch".to_string(),
+        });
     }
-    let hog_timestamp_end = req
-        .get("hog_timestamp_end")
-        .and_then(|v| v.as_str())
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&chrono::Utc));
-    if let Some(ts) = hog_timestamp_end {
-        if ts.timestamp() < 0 {
-            errors.push(ApiErrorSchema {
-                field: "hog_timestamp_end".to_string(),
-                message: "hog_timestamp_end cannot be before epoch".to_string(),
-            });
-        }
+    let hog_timestamp_end = resolve_timestamp(&req, "hog_timestamp_end", now, true, &mut errors);
+    if let Some(ts) = hog_timestamp_end
+        && ts.timestamp() < 0
+    {
+        errors.push(ApiErrorSchema {
+            field: "hog_timestamp_end".to_string(),
+            message: "hog_timestamp_end cannot be before epoch".to_string(),
+        });
     }
 
     let log_data = req.get("log_data").and_then(|v| v.as_object()).cloned();
-    if let Some(ref data) = log_data {
-        if data.is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_data".to_string(),
-                message: "log_data cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref data) = log_data
+        && data.is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_data".to_string(),
+            message: "log_data cannot be empty".to_string(),
+        });
     }
     let log_data_field = req
         .get("log_data_field")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    if let Some(ref field) = log_data_field {
-        if field.trim().is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_data_field".to_string(),
-                message: "log_data_field cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref field) = log_data_field
+        && field.trim().is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_data_field".to_string(),
+            message: "log_data_field cannot be empty".to_string(),
+        });
     }
     let log_data_value = req
         .get("log_data_value")
         .and_then(|v| v.as_object())
         .cloned();
-    if let Some(ref value) = log_data_value {
-        if value.is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_data_value".to_string(),
-                message: "log_data_value cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref value) = log_data_value
+        && value.is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_data_value".to_string(),
+            message: "log_data_value cannot be empty".to_string(),
+        });
     }
     let log_data_fields = req
         .get("log_data_fields")
@@ -509,13 +548,13 @@ pub async fn validate_options(req: serde_json::Value) -> Result<OptionsRequest,
                 .filter_map(|v| v.as_str().map(|s| s.to_string()))
                 .collect::<Vec<String>>()
         });
-    if let Some(ref fields) = log_data_fields {
-        if fields.is_empty() {
-            errors.push(ApiErrorSchema {
-                field: "log_data_fields".to_string(),
-                message: "log_data_fields cannot be empty".to_string(),
-            });
-        }
+    if let Some(ref fields) = log_data_fields
+        && fields.is_empty()
+    {
+        errors.push(ApiErrorSchema {
+            field: "log_data_fields".to_string(),
+            message: "log_data_fields cannot be empty".to_string(),
+        });
     }
 
     if !errors.is_empty() {
@@ -542,6 +581,7 @@ pub async fn validate_options(req: serde_json::Value) -> Result<OptionsRequest,
         log_data_value: log_data_value.map(|v| Some(serde_json::Value::Object(v))),
         log_data_fields,
         hog_uuid,
+        pattern_id,
         hog_limit,
         hog_partial,
         hog_sort: hog_sort.flatten(),
@@ -553,3 +593,41 @@ pub async fn validate_options(req: serde_json::Value) -> Result<OptionsRequest,
 
     Ok(options)
 }
+
+/// Reads a timestamp field that may be RFC 3339 or a `now`-based expression.
+/// `_end` fields round up so `now/d` covers the rest of the day.
+fn resolve_timestamp(
+    req: &serde_json::Value,
+    field: &str,
+    now: chrono::DateTime<chrono::Utc>,
+    round_up: bool,
+    errors: &mut Vec<ApiErrorSchema>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    let expr = req.get(field).and_then(|v| v.as_str())?;
+    match date_math::resolve(expr, now, round_up) {
+        Ok(timestamp) => Some(timestamp),
+        Err(message) => {
+            errors.push(ApiErrorSchema {
+                field: field.to_string(),
+                message,
+            });
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hog_last_past_the_calendar_is_a_field_error() {
+        let error = validate_options(serde_json::json!({ "hog_last": "100000000d" }))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.errors.len(), 1);
+        assert_eq!(error.errors[0].field, "hog_last");
+        assert_eq!(error.errors[0].message, "hog_last is out of range");
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::doc;
+use mongodb::options::UpdateOptions;
+use mongodb::Collection;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::hog_record::HogRecord;
+
+/// Placeholder for the variable parts of a template.
+pub const PATTERN_WILDCARD: &str = "<*>";
+#[allow(dead_code)]
+pub const PATTERN_SIMILARITY: f64 = 0.5;
+const PATTERN_MAX_CLUSTERS_PER_GROUP: usize = 100;
+/// Groups kept before the least recently used ones are forgotten.
+#[allow(dead_code)]
+pub const PATTERN_MAX_GROUPS: usize = 10_000;
+const PATTERN_MAX_SAMPLES: usize = 5;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogPatternRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub template: String,
+    pub count: i64,
+    pub sample_hog_uuids: Vec<String>,
+    pub first_seen: BsonDateTime,
+    pub last_seen: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogPattern {
+    pub pattern_id: String,
+    pub template: String,
+    pub count: i64,
+    pub sample_hog_uuids: Vec<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HogPatterns {
+    pub scanned: u64,
+    pub patterns: Vec<HogPattern>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternsQuery {
+    pub limit: Option<i64>,
+}
+
+/// Which template a message landed in. `previous_pattern_id` is set when the
+/// message made its cluster's template more general, which changes its id.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PatternMatch {
+    pub pattern_id: String,
+    pub template: String,
+    pub previous_pattern_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct PatternGroup {
+    clusters: Vec<PatternCluster>,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone)]
+struct PatternCluster {
+    tokens: Vec<String>,
+    count: i64,
+    sample_hog_uuids: Vec<String>,
+    first_seen: BsonDateTime,
+    last_seen: BsonDateTime,
+}
+
+impl PatternCluster {
+    fn template(&self) -> String {
+        self.tokens.join(" ")
+    }
+
+    fn absorb(&mut self, count: i64, samples: &[String], first: BsonDateTime, last: BsonDateTime) {
+        self.count += count;
+        for sample in samples {
+            if self.sample_hog_uuids.len() >= PATTERN_MAX_SAMPLES {
+                break;
+            }
+            self.sample_hog_uuids.push(sample.clone());
+        }
+        self.first_seen = self.first_seen.min(first);
+        self.last_seen = self.last_seen.max(last);
+    }
+}
+
+/// Drain-style template miner. Messages are split on whitespace, tokens with
+/// digits are masked up front, and messages are grouped by token count and
+/// first token. Within a group a message joins the most similar template if
+/// enough tokens line up; positions that differ turn into `<*>`.
+///
+/// Only `max_groups` groups are kept; past that the least recently used
+/// tenth is forgotten, so a stream of unique first tokens can't grow it
+/// forever. A forgotten template starts over if its messages come back.
+///
+/// Also keeps the per-pattern deltas since the last `flush`, so the workers
+/// can fold them into the `hog_pattern` collection.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct PatternMiner {
+    similarity: f64,
+    max_groups: usize,
+    tick: u64,
+    groups: HashMap<(usize, String), PatternGroup>,
+    pending: HashMap<String, PatternCluster>,
+    renamed: HashMap<String, String>,
+}
+
+#[allow(dead_code)]
+impl PatternMiner {
+    pub fn new(similarity: f64, max_groups: usize) -> Self {
+        PatternMiner {
+            similarity,
+            max_groups: max_groups.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Makes room for one more group.
+    fn evict_groups(&mut self) {
+        if self.groups.len() < self.max_groups {
+            return;
+        }
+        let keep = self.max_groups - (self.max_groups / 10).max(1);
+        let mut last_used: Vec<u64> = self.groups.values().map(|group| group.last_used).collect();
+        last_used.sort_unstable_by(|a, b| b.cmp(a));
+        match keep.checked_sub(1).map(|index| last_used[index]) {
+            Some(cutoff) => self.groups.retain(|_, group| group.last_used >= cutoff),
+            None => self.groups.clear(),
+        }
+    }
+
+    pub fn add(&mut self, record: &HogRecord) -> PatternMatch {
+        let tokens = tokenize(&record.log_message);
+        let key = (tokens.len(), tokens.first().cloned().unwrap_or_default());
+        let seen = record.log_timestamp;
+        let samples: Vec<String> = record.hog_uuid.iter().cloned().collect();
+
+        self.tick += 1;
+        if !self.groups.contains_key(&key) {
+            self.evict_groups();
+        }
+        let group = self.groups.entry(key).or_default();
+        group.last_used = self.tick;
+        let clusters = &mut group.clusters;
+        let best = clusters
+            .iter()
+            .enumerate()
+            .map(|(index, cluster)| (index, similarity(&cluster.tokens, &tokens)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let join = match best {
+            Some((index, score)) if score >= self.similarity => Some(index),
+            // A full group takes the closest template rather than growing.
+            Some((index, _)) if clusters.len() >= PATTERN_MAX_CLUSTERS_PER_GROUP => Some(index),
+            _ => None,
+        };
+
+        let (cluster, previous_pattern_id) = match join {
+            Some(index) => {
+                let cluster = &mut clusters[index];
+                let before = cluster.template();
+                for (current, token) in cluster.tokens.iter_mut().zip(&tokens) {
+                    if current != token {
+                        *current = PATTERN_WILDCARD.to_string();
+                    }
+                }
+                cluster.absorb(1, &samples, seen, seen);
+                let previous = (cluster.template() != before).then(|| pattern_id(&before));
+                (cluster.clone(), previous)
+            }
+            None => {
+                let cluster = PatternCluster {
+                    tokens,
+                    count: 1,
+                    sample_hog_uuids: samples.clone(),
+                    first_seen: seen,
+                    last_seen: seen,
+                };
+                clusters.push(cluster.clone());
+                (cluster, None)
+            }
+        };
+
+        let template = cluster.template();
+        let id = pattern_id(&template);
+        let mut delta = PatternCluster {
+            count: 0,
+            sample_hog_uuids: Vec::new(),
+            first_seen: seen,
+            last_seen: seen,
+            ..cluster
+        };
+        if let Some(ref previous) = previous_pattern_id {
+            if let Some(old) = self.pending.remove(previous) {
+                delta.absorb(
+                    old.count,
+                    &old.sample_hog_uuids,
+                    old.first_seen,
+                    old.last_seen,
+                );
+            }
+            for target in self.renamed.values_mut() {
+                if target == previous {
+                    *target = id.clone();
+                }
+            }
+            self.renamed.insert(previous.clone(), id.clone());
+        }
+        let pending = match self.pending.remove(&id) {
+            Some(mut pending) => {
+                pending.absorb(
+                    delta.count,
+                    &delta.sample_hog_uuids,
+                    delta.first_seen,
+                    delta.last_seen,
+                );
+                pending
+            }
+            None => delta,
+        };
+        self.pending
+            .entry(id.clone())
+            .or_insert(pending)
+            .absorb(1, &samples, seen, seen);
+
+        PatternMatch {
+            pattern_id: id,
+            template,
+            previous_pattern_id,
+        }
+    }
+
+    /// Every template seen so far, most frequent first.
+    pub fn patterns(&self) -> Vec<HogPattern> {
+        // Separate clusters can generalise into the same template.
+        let mut merged: HashMap<String, PatternCluster> = HashMap::new();
+        for cluster in self.groups.values().flat_map(|group| &group.clusters) {
+            match merged.get_mut(&cluster.template()) {
+                Some(existing) => existing.absorb(
+                    cluster.count,
+                    &cluster.sample_hog_uuids,
+                    cluster.first_seen,
+                    cluster.last_seen,
+                ),
+                None => {
+                    merged.insert(cluster.template(), cluster.clone());
+                }
+            }
+        }
+
+        let mut patterns: Vec<HogPattern> = merged
+            .into_iter()
+            .map(|(template, cluster)| HogPattern {
+                pattern_id: pattern_id(&template),
+                template,
+                count: cluster.count,
+                sample_hog_uuids: cluster.sample_hog_uuids,
+                first_seen: cluster.first_seen.to_chrono(),
+                last_seen: cluster.last_seen.to_chrono(),
+            })
+            .collect();
+        patterns.sort_by(|a, b| b.count.cmp(&a.count).then(a.template.cmp(&b.template)));
+        patterns
+    }
+
+    /// Folds everything added since the last flush into `collection`.
+    /// Documents of templates that have since become more general are merged
+    /// into their successor, and hogs stored under the old id move to the new
+    /// one, so no `pattern_id` is left pointing at a deleted pattern.
+    pub async fn flush(
+        &mut self,
+        collection: &Collection<HogPatternRecord>,
+        hogs: &Collection<HogRecord>,
+    ) -> Result<(), mongodb::error::Error> {
+        for (old, new) in std::mem::take(&mut self.renamed) {
+            hogs.update_many(
+                doc! { "pattern_id": &old },
+                doc! { "$set": { "pattern_id": &new } },
+            )
+            .await?;
+            if let Some(stored) = collection.find_one_and_delete(doc! { "_id": &old }).await?
+                && let Some(pending) = self.pending.get_mut(&new)
+            {
+                pending.absorb(
+                    stored.count,
+                    &stored.sample_hog_uuids,
+                    stored.first_seen,
+                    stored.last_seen,
+                );
+            }
+        }
+
+        let upsert = UpdateOptions::builder().upsert(true).build();
+        for (id, delta) in std::mem::take(&mut self.pending) {
+            collection
+                .update_one(
+                    doc! { "_id": &id },
+                    doc! {
+                        "$set": { "template": delta.template() },
+                        "$inc": { "count": delta.count },
+                        "$min": { "first_seen": delta.first_seen },
+                        "$max": { "last_seen": delta.last_seen },
+                        "$push": { "sample_hog_uuids": {
+                            "$each": &delta.sample_hog_uuids,
+                            "$slice": PATTERN_MAX_SAMPLES as i64,
+                        } },
+                    },
+                )
+                .with_options(upsert.clone())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Templates are identified by a name-based uuid, so every worker arrives at
+/// the same id for the same template.
+#[allow(dead_code)]
+pub fn pattern_id(template: &str) -> String {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, template.as_bytes()).to_string()
+}
+
+fn tokenize(message: &str) -> Vec<String> {
+    message
+        .split_whitespace()
+        .map(|token| {
+            if token.chars().any(|c| c.is_ascii_digit()) {
+                PATTERN_WILDCARD.to_string()
+            } else {
+                token.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Share of positions where the template and the message agree. A `<*>` only
+/// counts when the message has a masked token in the same place.
+fn similarity(template: &[String], tokens: &[String]) -> f64 {
+    if tokens.is_empty() {
+        return 1.0;
+    }
+    let same = template
+        .iter()
+        .zip(tokens)
+        .filter(|(current, token)| current == token)
+        .count();
+    same as f64 / tokens.len() as f64
+}
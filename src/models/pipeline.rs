@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::annotations::HogAnnotationsRecord;
+use super::embedded::{merge_embedded, parse_embedded, ConflictRule, EmbeddedFormat};
+use super::geoip::{GeoIp, GeoIpDatabase};
+use super::grok::{Grok, GrokType};
+use super::hog_record::HogRecord;
+use super::matcher::HogMatcher;
+use super::options::validate_options;
+
+/// One entry of the pipeline file. `when` takes the same fields as
+/// `/hogs/search`; without it the processor runs on every hog.
+#[derive(Debug, Clone, Deserialize)]
+struct ProcessorEntry {
+    #[serde(flatten)]
+    config: ProcessorConfig,
+    #[serde(default)]
+    when: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ProcessorConfig {
+    Rename {
+        from: String,
+        to: String,
+    },
+    Set {
+        field: String,
+        value: Value,
+        #[serde(default = "default_overwrite")]
+        overwrite: bool,
+    },
+    Remove {
+        field: String,
+    },
+    Copy {
+        from: String,
+        to: String,
+    },
+    Drop,
+    NormalizeLevel {
+        #[serde(default)]
+        aliases: HashMap<String, String>,
+    },
+    Grok {
+        #[serde(default = "default_grok_field")]
+        field: String,
+        patterns: Vec<String>,
+        #[serde(default)]
+        pattern_definitions: HashMap<String, String>,
+        #[serde(default)]
+        types: HashMap<String, GrokType>,
+        #[serde(default = "default_grok_failure_tag")]
+        tag_on_failure: Option<String>,
+    },
+    ParseEmbedded {
+        #[serde(default = "default_grok_field")]
+        field: String,
+        #[serde(default)]
+        format: EmbeddedFormat,
+        #[serde(default)]
+        target: Option<String>,
+        #[serde(default)]
+        conflict: ConflictRule,
+        #[serde(default = "default_conflict_prefix")]
+        prefix: String,
+        #[serde(default)]
+        tag_on_failure: Option<String>,
+    },
+    Geoip {
+        fields: Vec<String>,
+        #[serde(default)]
+        database: Option<String>,
+        #[serde(default)]
+        asn_database: Option<String>,
+        #[serde(default = "default_geoip_language")]
+        language: String,
+        #[serde(default = "default_geoip_failure_tag")]
+        tag_on_failure: Option<String>,
+    },
+}
+
+fn default_overwrite() -> bool {
+    true
+}
+
+fn default_grok_field() -> String {
+    "log_message".to_string()
+}
+
+fn default_grok_failure_tag() -> Option<String> {
+    Some("_grokparsefailure".to_string())
+}
+
+fn default_conflict_prefix() -> String {
+    "parsed_".to_string()
+}
+
+fn default_geoip_language() -> String {
+    "en".to_string()
+}
+
+fn default_geoip_failure_tag() -> Option<String> {
+    Some("_geoip_lookup_failure".to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct PipelineConfig {
+    processors: Vec<ProcessorEntry>,
+}
+
+/// A field a processor can read or write: one of the top-level string fields
+/// or a dotted path into `log_data`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldPath {
+    Level,
+    Message,
+    Type,
+    Source,
+    SourceId,
+    Data(Vec<String>),
+}
+
+#[allow(dead_code)]
+impl FieldPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        match path {
+            "log_level" => Ok(FieldPath::Level),
+            "log_message" => Ok(FieldPath::Message),
+            "log_type" => Ok(FieldPath::Type),
+            "log_source" => Ok(FieldPath::Source),
+            "log_source_id" => Ok(FieldPath::SourceId),
+            _ => match path.strip_prefix("log_data.") {
+                Some(rest) if !rest.split('.').any(str::is_empty) => Ok(FieldPath::Data(
+                    rest.split('.').map(String::from).collect(),
+                )),
+                _ => Err(format!(
+                    "{} is not a field, use log_level, log_message, log_type, log_source, log_source_id or log_data.<path>",
+                    path
+                )),
+            },
+        }
+    }
+
+    fn top_level<'a>(&self, record: &'a mut HogRecord) -> Option<&'a mut Option<String>> {
+        match self {
+            FieldPath::Level => Some(&mut record.log_level),
+            FieldPath::Type => Some(&mut record.log_type),
+            FieldPath::Source => Some(&mut record.log_source),
+            FieldPath::SourceId => Some(&mut record.log_source_id),
+            FieldPath::Message | FieldPath::Data(_) => None,
+        }
+    }
+
+    pub fn get(&self, record: &HogRecord) -> Option<Value> {
+        let string = |value: &Option<String>| value.clone().map(Value::String);
+        match self {
+            FieldPath::Level => string(&record.log_level),
+            FieldPath::Message => Some(Value::String(record.log_message.clone())),
+            FieldPath::Type => string(&record.log_type),
+            FieldPath::Source => string(&record.log_source),
+            FieldPath::SourceId => string(&record.log_source_id),
+            FieldPath::Data(path) => path
+                .iter()
+                .try_fold(record.log_data.as_ref()?, |value, segment| {
+                    value.get(segment)
+                })
+                .cloned(),
+        }
+    }
+
+    /// Top-level fields are strings, anything else is stored as its JSON
+    /// text. `log_data` paths create the objects they need, but never replace
+    /// a value that isn't an object.
+    pub fn set(&self, record: &mut HogRecord, value: Value) -> bool {
+        let text = |value: Value| match value {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        if let FieldPath::Message = self {
+            record.log_message = text(value);
+            return true;
+        }
+        if let Some(field) = self.top_level(record) {
+            *field = match value {
+                Value::Null => None,
+                value => Some(text(value)),
+            };
+            return true;
+        }
+        let FieldPath::Data(path) = self else {
+            return false;
+        };
+        let mut current = record
+            .log_data
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        let (last, parents) = path.split_last().expect("log_data paths are never empty");
+        for segment in parents {
+            let Some(map) = current.as_object_mut() else {
+                return false;
+            };
+            current = map
+                .entry(segment.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        match current.as_object_mut() {
+            Some(map) => {
+                map.insert(last.clone(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&self, record: &mut HogRecord) -> Option<Value> {
+        if let Some(field) = self.top_level(record) {
+            return field.take().map(Value::String);
+        }
+        let FieldPath::Data(path) = self else {
+            return None;
+        };
+        let (last, parents) = path.split_last()?;
+        let parent = parents
+            .iter()
+            .try_fold(record.log_data.as_mut()?, |value, segment| {
+                value.get_mut(segment)
+            })?;
+        parent.as_object_mut()?.remove(last)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    Rename {
+        from: FieldPath,
+        to: FieldPath,
+    },
+    Set {
+        field: FieldPath,
+        value: Value,
+        overwrite: bool,
+    },
+    Remove {
+        field: FieldPath,
+    },
+    Copy {
+        from: FieldPath,
+        to: FieldPath,
+    },
+    Drop,
+    NormalizeLevel {
+        aliases: HashMap<String, String>,
+    },
+    Grok {
+        field: FieldPath,
+        grok: Grok,
+        tag_on_failure: Option<String>,
+    },
+    ParseEmbedded {
+        field: FieldPath,
+        format: EmbeddedFormat,
+        target: Option<FieldPath>,
+        conflict: ConflictRule,
+        prefix: String,
+        tag_on_failure: Option<String>,
+    },
+    Geoip {
+        /// Each address field with the `<field>_geo` field next to it.
+        fields: Vec<(FieldPath, FieldPath)>,
+        geoip: Arc<GeoIp>,
+        tag_on_failure: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Processor {
+    action: Action,
+    when: Option<HogMatcher>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineOutcome {
+    Keep,
+    Drop,
+}
+
+/// Processors the workers run on every hog before it's stored, in file
+/// order. See `HOG_PIPELINE_FILE` in the README for the format.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    processors: Vec<Processor>,
+}
+
+#[allow(dead_code)]
+impl Pipeline {
+    pub async fn load(path: &str) -> Result<Self, String> {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub async fn from_config(config: Value) -> Result<Self, String> {
+        let config: PipelineConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        let mut processors = Vec::with_capacity(config.processors.len());
+        for (i, entry) in config.processors.into_iter().enumerate() {
+            let field = |path: &str| {
+                FieldPath::parse(path).map_err(|e| format!("processors[{}]: {}", i, e))
+            };
+            let action = match entry.config {
+                ProcessorConfig::Rename { from, to } => Action::Rename {
+                    from: field(&from)?,
+                    to: field(&to)?,
+                },
+                ProcessorConfig::Set {
+                    field: path,
+                    value,
+                    overwrite,
+                } => Action::Set {
+                    field: field(&path)?,
+                    value,
+                    overwrite,
+                },
+                ProcessorConfig::Remove { field: path } => match field(&path)? {
+                    FieldPath::Message => {
+                        return Err(format!("processors[{}]: log_message can't be removed", i));
+                    }
+                    path => Action::Remove { field: path },
+                },
+                ProcessorConfig::Copy { from, to } => Action::Copy {
+                    from: field(&from)?,
+                    to: field(&to)?,
+                },
+                ProcessorConfig::Drop => Action::Drop,
+                ProcessorConfig::NormalizeLevel { aliases } => Action::NormalizeLevel {
+                    aliases: aliases
+                        .into_iter()
+                        .map(|(alias, level)| (alias.to_lowercase(), level))
+                        .collect(),
+                },
+                ProcessorConfig::Grok {
+                    field: path,
+                    patterns,
+                    pattern_definitions,
+                    types,
+                    tag_on_failure,
+                } => Action::Grok {
+                    field: field(&path)?,
+                    grok: Grok::new(&patterns, &pattern_definitions, &types)
+                        .map_err(|e| format!("processors[{}]: {}", i, e))?,
+                    tag_on_failure,
+                },
+                ProcessorConfig::ParseEmbedded {
+                    field: path,
+                    format,
+                    target,
+                    conflict,
+                    prefix,
+                    tag_on_failure,
+                } => Action::ParseEmbedded {
+                    field: field(&path)?,
+                    format,
+                    // Without a target the keys go straight into `log_data`.
+                    target: match target {
+                        None => None,
+                        Some(target) => match field(&target)? {
+                            path @ FieldPath::Data(_) => Some(path),
+                            _ => {
+                                return Err(format!(
+                                    "processors[{}]: target must be a log_data.<path>",
+                                    i
+                                ));
+                            }
+                        },
+                    },
+                    conflict,
+                    prefix,
+                    tag_on_failure,
+                },
+                ProcessorConfig::Geoip {
+                    fields,
+                    database,
+                    asn_database,
+                    language,
+                    tag_on_failure,
+                } => {
+                    if database.is_none() && asn_database.is_none() {
+                        return Err(format!(
+                            "processors[{}]: set database, asn_database or both",
+                            i
+                        ));
+                    }
+                    let open = |path: Option<String>| {
+                        path.map(|path| GeoIpDatabase::open(&path))
+                            .transpose()
+                            .map_err(|e| format!("processors[{}]: {}", i, e))
+                    };
+                    let mut targets = Vec::with_capacity(fields.len());
+                    for path in fields {
+                        let FieldPath::Data(segments) = field(&path)? else {
+                            return Err(format!(
+                                "processors[{}]: {} is not a log_data.<path>",
+                                i, path
+                            ));
+                        };
+                        let mut geo = segments.clone();
+                        if let Some(last) = geo.last_mut() {
+                            last.push_str("_geo");
+                        }
+                        targets.push((FieldPath::Data(segments), FieldPath::Data(geo)));
+                    }
+                    Action::Geoip {
+                        fields: targets,
+                        geoip: Arc::new(GeoIp {
+                            city: open(database)?,
+                            asn: open(asn_database)?,
+                            language,
+                        }),
+                        tag_on_failure,
+                    }
+                }
+            };
+            let when = match entry.when {
+                Some(when) => Some(
+                    HogMatcher::new(validate_options(when).await.map_err(|validation_error| {
+                        let errors: Vec<String> = validation_error
+                            .errors
+                            .into_iter()
+                            .map(|e| format!("{}: {}", e.field, e.message))
+                            .collect();
+                        format!("processors[{}].when: {}", i, errors.join(", "))
+                    })?)
+                    .map_err(|e| format!("processors[{}].when: {}", i, e))?,
+                ),
+                None => None,
+            };
+            processors.push(Processor { action, when });
+        }
+        Ok(Pipeline { processors })
+    }
+
+    pub fn process(&self, record: &mut HogRecord) -> PipelineOutcome {
+        for processor in &self.processors {
+            if let Some(ref when) = processor.when
+                && !when.matches(record)
+            {
+                continue;
+            }
+            match processor.action {
+                Action::Rename { ref from, ref to } => {
+                    if let Some(value) = from.get(record) {
+                        from.remove(record);
+                        to.set(record, value);
+                    }
+                }
+                Action::Set {
+                    ref field,
+                    ref value,
+                    overwrite,
+                } => {
+                    if overwrite || field.get(record).is_none() {
+                        field.set(record, value.clone());
+                    }
+                }
+                Action::Remove { ref field } => {
+                    field.remove(record);
+                }
+                Action::Copy { ref from, ref to } => {
+                    if let Some(value) = from.get(record) {
+                        to.set(record, value);
+                    }
+                }
+                Action::Drop => return PipelineOutcome::Drop,
+                Action::NormalizeLevel { ref aliases } => {
+                    if let Some(ref level) = record.log_level {
+                        record.log_level = Some(normalize_level(level, aliases));
+                    }
+                }
+                Action::Grok {
+                    ref field,
+                    ref grok,
+                    ref tag_on_failure,
+                } => {
+                    let parsed = match field.get(record) {
+                        Some(Value::String(text)) => grok.parse(&text),
+                        _ => None,
+                    };
+                    match parsed {
+                        Some(fields) => {
+                            for (path, value) in fields {
+                                path.set(record, value);
+                            }
+                        }
+                        None => {
+                            if let Some(tag) = tag_on_failure {
+                                add_tag(record, tag);
+                            }
+                        }
+                    }
+                }
+                Action::ParseEmbedded {
+                    ref field,
+                    format,
+                    ref target,
+                    conflict,
+                    ref prefix,
+                    ref tag_on_failure,
+                } => {
+                    let parsed = match field.get(record) {
+                        Some(Value::String(text)) => parse_embedded(&text, format),
+                        _ => None,
+                    };
+                    let merged = parsed.and_then(|parsed| {
+                        let existing = match target {
+                            Some(target) => target.get(record),
+                            None => record.log_data.clone(),
+                        };
+                        let mut object = match existing {
+                            None | Some(Value::Null) => Map::new(),
+                            Some(Value::Object(object)) => object,
+                            // Nowhere to put the keys without replacing data.
+                            Some(_) => return None,
+                        };
+                        merge_embedded(&mut object, parsed, conflict, prefix);
+                        Some(Value::Object(object))
+                    });
+                    let stored = match (merged, target) {
+                        (Some(object), Some(target)) => target.set(record, object),
+                        (Some(object), None) => {
+                            record.log_data = Some(object);
+                            true
+                        }
+                        (None, _) => false,
+                    };
+                    if !stored && let Some(tag) = tag_on_failure {
+                        add_tag(record, tag);
+                    }
+                }
+                Action::Geoip {
+                    ref fields,
+                    ref geoip,
+                    ref tag_on_failure,
+                } => {
+                    for (field, target) in fields {
+                        // Hogs without the field aren't a failed lookup.
+                        let Some(value) = field.get(record) else {
+                            continue;
+                        };
+                        let geo = value
+                            .as_str()
+                            .and_then(|text| text.trim().parse::<IpAddr>().ok())
+                            .and_then(|ip| geoip.lookup(ip));
+                        match geo {
+                            Some(geo) => {
+                                target.set(record, geo);
+                            }
+                            None => {
+                                if let Some(tag) = tag_on_failure {
+                                    add_tag(record, tag);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        PipelineOutcome::Keep
+    }
+}
+
+fn add_tag(record: &mut HogRecord, tag: &str) {
+    let tags = &mut record
+        .annotations
+        .get_or_insert_with(HogAnnotationsRecord::default)
+        .tags;
+    if !tags.iter().any(|existing| existing == tag) {
+        tags.push(tag.to_string());
+    }
+}
+
+/// Maps the usual spellings onto `TRACE`, `DEBUG`, `INFO`, `WARN`, `ERROR`
+/// and `FATAL`. Configured aliases win; anything unknown is just uppercased.
+fn normalize_level(level: &str, aliases: &HashMap<String, String>) -> String {
+    let key = level.trim().to_lowercase();
+    if let Some(level) = aliases.get(&key) {
+        return level.clone();
+    }
+    match key.as_str() {
+        "trace" | "verbose" | "finest" | "finer" => "TRACE",
+        "debug" | "dbg" | "fine" => "DEBUG",
+        "info" | "information" | "informational" | "notice" => "INFO",
+        "warn" | "warning" => "WARN",
+        "error" | "err" | "severe" => "ERROR",
+        "fatal" | "critical" | "crit" | "alert" | "emerg" | "emergency" | "panic" => "FATAL",
+        _ => return key.to_uppercase(),
+    }
+    .to_string()
+}
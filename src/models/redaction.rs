@@ -0,0 +1,352 @@
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::hog_record::HogRecord;
+use super::pipeline::FieldPath;
+use crate::config;
+
+/// `log_data` keys whose values are always redacted, wherever they appear.
+const REDACTION_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "client_secret",
+    "authorization",
+    "api_key",
+    "apikey",
+    "access_token",
+    "refresh_token",
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    /// `[REDACTED:<rule>]`
+    #[default]
+    Mask,
+    /// `[<rule>:<sha256 prefix>]`, so equal values still line up in searches.
+    Hash,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Detector {
+    Bearer,
+    Jwt,
+    Email,
+    CreditCard,
+    Ip,
+    AwsKey,
+}
+
+#[allow(dead_code)]
+impl Detector {
+    fn name(self) -> &'static str {
+        match self {
+            Detector::Bearer => "bearer",
+            Detector::Jwt => "jwt",
+            Detector::Email => "email",
+            Detector::CreditCard => "credit_card",
+            Detector::Ip => "ip",
+            Detector::AwsKey => "aws_key",
+        }
+    }
+
+    /// A `secret` group narrows what gets replaced to part of the match.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Detector::Bearer => &[r"(?i)\bbearer\s+(?P<secret>[A-Za-z0-9\-._~+/]+=*)"],
+            Detector::Jwt => &[r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*"],
+            Detector::Email => &[r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b"],
+            Detector::CreditCard => &[r"\b(?:[0-9][ -]?){12,18}[0-9]\b"],
+            Detector::Ip => &[
+                r"\b(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\b",
+                r"\b(?:[A-Fa-f0-9]{1,4}:){7}[A-Fa-f0-9]{1,4}\b",
+                r"\b(?:[A-Fa-f0-9]{1,4}:){1,6}(?::[A-Fa-f0-9]{1,4}){1,6}\b",
+            ],
+            Detector::AwsKey => &[
+                r"\b(?:AKIA|ASIA|AGPA|AIDA|AROA|ANPA|ANVA|AIPA)[A-Z0-9]{16}\b",
+                r#"(?i)aws_?secret_?access_?key["']?\s*[=:]\s*["']?(?P<secret>[A-Za-z0-9/+=]{40})"#,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PatternConfig {
+    name: String,
+    regex: String,
+    #[serde(default)]
+    mode: Option<RedactionMode>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RedactionConfig {
+    #[serde(default)]
+    mode: RedactionMode,
+    #[serde(default = "default_detectors")]
+    detectors: Vec<Detector>,
+    #[serde(default)]
+    patterns: Vec<PatternConfig>,
+    #[serde(default)]
+    fields: Vec<String>,
+    #[serde(default = "default_keys")]
+    keys: Vec<String>,
+}
+
+/// Emails and IPs are often what you search by, so they're opt-in.
+fn default_detectors() -> Vec<Detector> {
+    vec![
+        Detector::Bearer,
+        Detector::Jwt,
+        Detector::CreditCard,
+        Detector::AwsKey,
+    ]
+}
+
+fn default_keys() -> Vec<String> {
+    REDACTION_KEYS.iter().map(|key| key.to_string()).collect()
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    regex: Regex,
+    mode: RedactionMode,
+    luhn: bool,
+}
+
+/// Masks or hashes secrets in `log_message` and `log_data` before a hog is
+/// published. See `HOG_REDACTION_FILE` in the README for the format.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+    fields: Vec<FieldPath>,
+    keys: Vec<String>,
+    mode: RedactionMode,
+    salt: String,
+}
+
+#[allow(dead_code)]
+impl Redactor {
+    pub fn load(path: &str, salt: String) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config, salt).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// The redactor the API and the workers share: `None` with
+    /// `REDACTION_ENABLED=false`, the defaults without `HOG_REDACTION_FILE`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        if !config::env_or("REDACTION_ENABLED", true) {
+            return Ok(None);
+        }
+        let salt = std::env::var("REDACTION_HASH_SALT").unwrap_or_default();
+        match std::env::var("HOG_REDACTION_FILE") {
+            Ok(path) => Self::load(&path, salt).map(Some),
+            Err(_) => Self::from_config(serde_json::json!({}), salt).map(Some),
+        }
+    }
+
+    pub fn from_config(config: Value, salt: String) -> Result<Self, String> {
+        let config: RedactionConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        let mut rules = Vec::new();
+        for detector in config.detectors {
+            for pattern in detector.patterns() {
+                rules.push(Rule {
+                    name: detector.name().to_string(),
+                    regex: Regex::new(pattern).expect("built-in redaction patterns are valid"),
+                    mode: config.mode,
+                    luhn: detector == Detector::CreditCard,
+                });
+            }
+        }
+        for (i, pattern) in config.patterns.into_iter().enumerate() {
+            rules.push(Rule {
+                regex: Regex::new(&pattern.regex).map_err(|e| format!("patterns[{}]: {}", i, e))?,
+                name: pattern.name,
+                mode: pattern.mode.unwrap_or(config.mode),
+                luhn: false,
+            });
+        }
+        let keys: Vec<String> = config.keys.iter().map(|key| key.to_lowercase()).collect();
+        // `password=hunter2`, `password='hunter2'` or `"password": "hunter2"`
+        // inside a message, which the embedded parser would otherwise lift
+        // into `log_data` unmasked.
+        if !keys.is_empty() {
+            let names: Vec<String> = keys.iter().map(|key| regex::escape(key)).collect();
+            let pattern = format!(
+                r#"(?i)\b(?:{})["']?\s*[=:]\s*(?:"(?P<quoted>(?:[^"\\]|\\.)*)"|'(?P<single>[^']*)'|(?P<secret>[^\s"',;&}}]+))"#,
+                names.join("|")
+            );
+            rules.push(Rule {
+                name: "key".to_string(),
+                regex: Regex::new(&pattern).map_err(|e| format!("keys: {}", e))?,
+                mode: config.mode,
+                luhn: false,
+            });
+        }
+        let fields = config
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, path)| FieldPath::parse(path).map_err(|e| format!("fields[{}]: {}", i, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Redactor {
+            rules,
+            fields,
+            keys,
+            mode: config.mode,
+            salt,
+        })
+    }
+
+    /// Redacts `record` in place and returns the name of the rule behind
+    /// every replacement, for the metrics.
+    pub fn redact(&self, record: &mut HogRecord) -> Vec<String> {
+        let mut hits = Vec::new();
+
+        for field in &self.fields {
+            if let Some(value) = field.get(record)
+                && !value.is_null()
+            {
+                let text = match value {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                if is_replacement(&text) {
+                    continue;
+                }
+                field.set(
+                    record,
+                    Value::String(self.replace("field", &text, self.mode)),
+                );
+                hits.push("field".to_string());
+            }
+        }
+
+        record.log_message = self.redact_text(&record.log_message, &mut hits);
+        if let Some(ref mut log_data) = record.log_data {
+            self.redact_value(log_data, &mut hits);
+        }
+        hits
+    }
+
+    fn redact_value(&self, value: &mut Value, hits: &mut Vec<String>) {
+        match value {
+            Value::String(text) => *text = self.redact_text(text, hits),
+            Value::Array(values) => {
+                for value in values {
+                    self.redact_value(value, hits);
+                }
+            }
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if value.is_null() || !self.keys.contains(&key.to_lowercase()) {
+                        self.redact_value(value, hits);
+                        continue;
+                    }
+                    let text = match &*value {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    if is_replacement(&text) {
+                        continue;
+                    }
+                    *value = Value::String(self.replace("key", &text, self.mode));
+                    hits.push("key".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_text(&self, text: &str, hits: &mut Vec<String>) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            if !rule.regex.is_match(&text) {
+                continue;
+            }
+            text = rule
+                .regex
+                .replace_all(&text, |captures: &Captures| {
+                    let whole = captures.get(0).expect("group 0 always matches");
+                    let secret = captures
+                        .name("secret")
+                        .or_else(|| captures.name("quoted"))
+                        .or_else(|| captures.name("single"))
+                        .unwrap_or(whole);
+                    if is_replacement(secret.as_str()) || rule.luhn && !luhn_valid(secret.as_str())
+                    {
+                        return whole.as_str().to_string();
+                    }
+                    hits.push(rule.name.clone());
+                    let start = secret.start() - whole.start();
+                    let end = secret.end() - whole.start();
+                    format!(
+                        "{}{}{}",
+                        &whole.as_str()[..start],
+                        self.replace(&rule.name, secret.as_str(), rule.mode),
+                        &whole.as_str()[end..]
+                    )
+                })
+                .into_owned();
+        }
+        text
+    }
+
+    fn replace(&self, rule: &str, secret: &str, mode: RedactionMode) -> String {
+        match mode {
+            RedactionMode::Mask => format!("[REDACTED:{}]", rule),
+            RedactionMode::Hash => {
+                let digest = Sha256::new()
+                    .chain_update(self.salt.as_bytes())
+                    .chain_update(secret.as_bytes())
+                    .finalize();
+                let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+                format!("[{}:{}]", rule, hex)
+            }
+        }
+    }
+}
+
+/// What `replace` wrote on an earlier pass. The API redacts and the workers
+/// redact again after their pipeline; hashing a hash would break searches.
+fn is_replacement(text: &str) -> bool {
+    let Some(inner) = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    else {
+        return false;
+    };
+    match inner.split_once(':') {
+        Some(("REDACTED", _)) => true,
+        Some((_, hex)) => hex.len() == 16 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+/// Card numbers pass the Luhn check; order ids and timestamps mostly don't.
+fn luhn_valid(text: &str) -> bool {
+    let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
@@ -0,0 +1,218 @@
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use serde::Deserialize;
+use serde_json::Value;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::hog_record::HogRecord;
+
+/// How many decisions go by between dropping idle rate limit keys.
+const SAMPLING_SHRINK_EVERY: u64 = 65_536;
+
+/// Fields a sampling rule can select on. Every field that is set must match
+/// exactly; `log_level` ignores case.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingSelector {
+    log_source: Option<String>,
+    log_level: Option<String>,
+    log_type: Option<String>,
+    log_source_id: Option<String>,
+}
+
+impl SamplingSelector {
+    fn matches(&self, record: &HogRecord) -> bool {
+        let exact = |expected: &Option<String>, actual: &Option<String>| {
+            expected.is_none() || expected == actual
+        };
+        exact(&self.log_source, &record.log_source)
+            && exact(&self.log_type, &record.log_type)
+            && exact(&self.log_source_id, &record.log_source_id)
+            && self.log_level.as_ref().is_none_or(|expected| {
+                record
+                    .log_level
+                    .as_ref()
+                    .is_some_and(|level| level.eq_ignore_ascii_case(expected))
+            })
+    }
+}
+
+/// The field a rate limit is counted per, so that one noisy instance doesn't
+/// use up the budget of the others.
+#[derive(Debug, Clone, Copy, Deserialize)]
+enum RateKey {
+    #[serde(rename = "log_source")]
+    Source,
+    #[serde(rename = "log_source_id")]
+    SourceId,
+    #[serde(rename = "log_type")]
+    Type,
+    #[serde(rename = "log_level")]
+    Level,
+}
+
+impl RateKey {
+    fn value(self, record: &HogRecord) -> String {
+        let value = match self {
+            RateKey::Source => &record.log_source,
+            RateKey::SourceId => &record.log_source_id,
+            RateKey::Type => &record.log_type,
+            RateKey::Level => &record.log_level,
+        };
+        value.clone().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingRuleConfig {
+    name: String,
+    #[serde(default)]
+    when: SamplingSelector,
+    sample_rate: Option<f64>,
+    max_per_second: Option<u32>,
+    per: Option<RateKey>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplingConfig {
+    rules: Vec<SamplingRuleConfig>,
+    #[serde(default)]
+    attach_sample_rate: bool,
+}
+
+struct SamplingRule {
+    name: String,
+    when: SamplingSelector,
+    sample_rate: Option<f64>,
+    limiter: Option<(DefaultKeyedRateLimiter<String>, Option<RateKey>)>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    Sampled,
+    RateLimited,
+}
+
+#[allow(dead_code)]
+impl DropReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DropReason::Sampled => "sampled",
+            DropReason::RateLimited => "rate_limited",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SamplingDecision {
+    Keep,
+    Drop { rule: String, reason: DropReason },
+}
+
+/// Sampling and rate rules applied by the API before a hog is published.
+/// The first rule a hog matches decides; hogs no rule matches are kept. See
+/// `HOG_SAMPLING_FILE` in the README for the format.
+#[allow(dead_code)]
+pub struct Sampler {
+    rules: Vec<SamplingRule>,
+    attach_sample_rate: bool,
+    decisions: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl Sampler {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_config(config: Value) -> Result<Self, String> {
+        let config: SamplingConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        let mut rules = Vec::with_capacity(config.rules.len());
+        for (i, rule) in config.rules.into_iter().enumerate() {
+            if let Some(rate) = rule.sample_rate
+                && !(0.0..=1.0).contains(&rate)
+            {
+                return Err(format!("rules[{}]: sample_rate must be between 0 and 1", i));
+            }
+            if rule.sample_rate.is_none() && rule.max_per_second.is_none() {
+                return Err(format!(
+                    "rules[{}]: set sample_rate, max_per_second or both",
+                    i
+                ));
+            }
+            if rule.per.is_some() && rule.max_per_second.is_none() {
+                return Err(format!("rules[{}]: per needs max_per_second", i));
+            }
+            let limiter = match rule.max_per_second {
+                Some(max) => {
+                    let max = NonZeroU32::new(max)
+                        .ok_or_else(|| format!("rules[{}]: max_per_second must be above 0", i))?;
+                    Some((RateLimiter::keyed(Quota::per_second(max)), rule.per))
+                }
+                None => None,
+            };
+            rules.push(SamplingRule {
+                name: rule.name,
+                when: rule.when,
+                sample_rate: rule.sample_rate,
+                limiter,
+            });
+        }
+        Ok(Sampler {
+            rules,
+            attach_sample_rate: config.attach_sample_rate,
+            decisions: AtomicU64::new(0),
+        })
+    }
+
+    /// `roll` is a uniformly distributed number in `[0, 1)`. Kept hogs get
+    /// `sample_rate` set when `attach_sample_rate` is on.
+    pub fn decide(&self, record: &mut HogRecord, roll: f64) -> SamplingDecision {
+        if self.decisions.fetch_add(1, Ordering::Relaxed) % SAMPLING_SHRINK_EVERY
+            == SAMPLING_SHRINK_EVERY - 1
+        {
+            self.shrink();
+        }
+        let Some(rule) = self.rules.iter().find(|rule| rule.when.matches(record)) else {
+            return SamplingDecision::Keep;
+        };
+        if let Some(rate) = rule.sample_rate
+            && roll >= rate
+        {
+            return SamplingDecision::Drop {
+                rule: rule.name.clone(),
+                reason: DropReason::Sampled,
+            };
+        }
+        if let Some((ref limiter, per)) = rule.limiter {
+            let key = per.map(|per| per.value(record)).unwrap_or_default();
+            if limiter.check_key(&key).is_err() {
+                return SamplingDecision::Drop {
+                    rule: rule.name.clone(),
+                    reason: DropReason::RateLimited,
+                };
+            }
+        }
+        if self.attach_sample_rate {
+            record.sample_rate = rule.sample_rate;
+        }
+        SamplingDecision::Keep
+    }
+
+    /// Forgets rate limit keys that haven't been seen for a while, so
+    /// short-lived source ids don't pile up.
+    fn shrink(&self) {
+        for rule in &self.rules {
+            if let Some((ref limiter, _)) = rule.limiter {
+                limiter.retain_recent();
+                limiter.shrink_to_fit();
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::options::{validate_options, ApiErrorSchema, ErrorResponse};
+
+/// Search fields a saved search can be run with on top of its stored options.
+#[allow(dead_code)]
+pub const SAVED_SEARCH_OVERRIDES: [&str; 8] = [
+    "hog_last",
+    "log_timestamp",
+    "log_timestamp_start",
+    "log_timestamp_end",
+    "hog_timestamp",
+    "hog_timestamp_start",
+    "hog_timestamp_end",
+    "hog_limit",
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearchRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub owner: String,
+    /// The search payload exactly as accepted by `/hogs/search`.
+    pub options: serde_json::Value,
+    pub created_at: BsonDateTime,
+    pub updated_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub owner: String,
+    pub options: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearchRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub owner: String,
+    pub options: serde_json::Value,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearchListQuery {
+    pub owner: Option<String>,
+}
+
+#[allow(dead_code)]
+pub async fn validate_saved_search(
+    req: serde_json::Value,
+) -> Result<SavedSearchRequest, ErrorResponse> {
+    let mut errors = Vec::new();
+
+    let name = match req.get("name").and_then(|v| v.as_str()) {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "name".to_string(),
+                message: "name is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let owner = match req.get("owner").and_then(|v| v.as_str()) {
+        Some(owner) if !owner.trim().is_empty() => owner.trim().to_string(),
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "owner".to_string(),
+                message: "owner is required".to_string(),
+            });
+            String::new()
+        }
+    };
+
+    let description = req
+        .get("description")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let options = match req.get("options") {
+        Some(options) if options.is_object() => {
+            if options.get("saved_search_id").is_some() {
+                errors.push(ApiErrorSchema {
+                    field: "options.saved_search_id".to_string(),
+                    message: "saved searches cannot be based on other saved searches".to_string(),
+                });
+            }
+            if let Err(validation_error) = validate_options(options.clone()).await {
+                errors.extend(validation_error.errors.into_iter().map(|e| ApiErrorSchema {
+                    field: format!("options.{}", e.field),
+                    message: e.message,
+                }));
+            }
+            options.clone()
+        }
+        _ => {
+            errors.push(ApiErrorSchema {
+                field: "options".to_string(),
+                message: "options must be a valid JSON object".to_string(),
+            });
+            serde_json::Value::Null
+        }
+    };
+
+    if !errors.is_empty() {
+        return Err(ErrorResponse {
+            status_code: 400,
+            message: "Validation errors occurred".to_string(),
+            errors,
+        });
+    }
+
+    Ok(SavedSearchRequest {
+        name,
+        description,
+        owner,
+        options,
+    })
+}
+
+/// Lays `overrides` over a saved search's stored options. When `allowed` is
+/// given, only those fields may be overridden.
+#[allow(dead_code)]
+pub fn merge_options(
+    base: &serde_json::Value,
+    overrides: &serde_json::Value,
+    allowed: Option<&[&str]>,
+) -> Result<serde_json::Value, Vec<ApiErrorSchema>> {
+    let mut merged = base.as_object().cloned().unwrap_or_default();
+    let mut errors = Vec::new();
+
+    if let Some(overrides) = overrides.as_object() {
+        for (key, value) in overrides {
+            if key == "saved_search_id" {
+                continue;
+            }
+            if let Some(allowed) = allowed
+                && !allowed.contains(&key.as_str())
+            {
+                errors.push(ApiErrorSchema {
+                    field: key.clone(),
+                    message: format!(
+                        "{} cannot be overridden, allowed: {}",
+                        key,
+                        allowed.join(", ")
+                    ),
+                });
+                continue;
+            }
+            // `hog_last` and an explicit log range replace each other rather
+            // than clashing.
+            if key == "hog_last" {
+                merged.remove("log_timestamp_start");
+                merged.remove("log_timestamp_end");
+            } else if key == "log_timestamp_start" || key == "log_timestamp_end" {
+                merged.remove("hog_last");
+            }
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(serde_json::Value::Object(merged))
+    } else {
+        Err(errors)
+    }
+}
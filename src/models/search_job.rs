@@ -0,0 +1,82 @@
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::hog::Hog;
+use super::hog_record::HogRecord;
+use super::options::OptionsRequest;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchJobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchJobRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub status: SearchJobStatus,
+    pub options: OptionsRequest,
+    pub result_count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: BsonDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<BsonDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<BsonDateTime>,
+    pub expires_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchJob {
+    pub id: String,
+    pub status: SearchJobStatus,
+    pub options: OptionsRequest,
+    pub result_count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// One hog found by a search job, stored under the job's id in insertion order.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchJobResultRecord {
+    pub job_id: String,
+    pub seq: i64,
+    pub hog: HogRecord,
+    pub expires_at: BsonDateTime,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchJobResultsQuery {
+    /// Only return results after this `seq` (exclusive).
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchJobResults {
+    pub job: SearchJob,
+    pub hogs: Vec<Hog>,
+    /// Pass as `after` to fetch the next page. Always there while the job is
+    /// pending or running, absent once a finished job has nothing left.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_after: Option<i64>,
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bson::DateTime as BsonDateTime;
+use futures::{stream, StreamExt, TryStreamExt};
+use lapin::options::{BasicPublishOptions, ExchangeDeclareOptions};
+use lapin::types::FieldTable;
+use lapin::{BasicProperties, Channel, ExchangeKind};
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use tokio::sync::mpsc;
+
+use super::alert::{AlertEventRecord, AlertRuleKind, AlertRuleRecord};
+use super::hog_record::HogRecord;
+use super::matcher::HogMatcher;
+use super::options::validate_options;
+
+/// Topic exchange match events are published to, routed as `alert.<rule_id>`.
+pub const ALERT_EXCHANGE: &str = "alerts";
+#[allow(dead_code)]
+pub const STREAM_ALERT_REFRESH_SECS: u64 = 10;
+#[allow(dead_code)]
+pub const ALERT_EVENT_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+/// Matches waiting to be stored and published before `evaluate` has to wait.
+const STREAM_ALERT_QUEUE_SIZE: usize = 10_000;
+/// Matches stored and published at the same time.
+const STREAM_ALERT_EMIT_CONCURRENCY: usize = 16;
+
+/// Runs `match` alert rules against every hog a worker consumes. Rules are
+/// reloaded from `alert_rule` every `refresh_interval`, so new rules reach the
+/// workers without a restart. Matches are stored and published by a
+/// background task, so a burst of them doesn't hold up consuming.
+#[allow(dead_code)]
+pub struct StreamAlerter {
+    rules: Collection<AlertRuleRecord>,
+    matchers: Vec<(Arc<AlertRuleRecord>, HogMatcher)>,
+    refresh_interval: Duration,
+    refreshed_at: Option<Instant>,
+    matches: mpsc::Sender<(Arc<AlertRuleRecord>, HogRecord)>,
+}
+
+/// Stores and publishes match events.
+struct AlertEmitter {
+    events: Collection<AlertEventRecord>,
+    channel: Channel,
+    retention_secs: i64,
+}
+
+#[allow(dead_code)]
+impl StreamAlerter {
+    pub async fn new(
+        db: &Database,
+        channel: Channel,
+        refresh_interval: Duration,
+        retention_secs: i64,
+    ) -> lapin::Result<Self> {
+        channel
+            .exchange_declare(
+                ALERT_EXCHANGE,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+        let emitter = Arc::new(AlertEmitter {
+            events: db.collection::<AlertEventRecord>("alert_event"),
+            channel,
+            retention_secs,
+        });
+        let (matches, receiver) = mpsc::channel(STREAM_ALERT_QUEUE_SIZE);
+        tokio::spawn(async move {
+            stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|matched| (matched, receiver))
+            })
+            .for_each_concurrent(
+                STREAM_ALERT_EMIT_CONCURRENCY,
+                |(rule, record): (Arc<AlertRuleRecord>, HogRecord)| {
+                    let emitter = Arc::clone(&emitter);
+                    async move {
+                        if let Err(e) = emitter.emit(&rule, &record).await {
+                            eprintln!("Failed to emit alert event for \"{}\": {:?}", rule.name, e);
+                        }
+                    }
+                },
+            )
+            .await;
+        });
+        Ok(StreamAlerter {
+            rules: db.collection::<AlertRuleRecord>("alert_rule"),
+            matchers: Vec::new(),
+            refresh_interval,
+            refreshed_at: None,
+            matches,
+        })
+    }
+
+    /// Queues an event for every rule `record` matches. Storing and
+    /// publishing it happens in the background; failures are logged and never
+    /// hold up the insert.
+    pub async fn evaluate(&mut self, record: &HogRecord) {
+        if self
+            .refreshed_at
+            .is_none_or(|refreshed_at| refreshed_at.elapsed() >= self.refresh_interval)
+        {
+            if let Err(e) = self.refresh().await {
+                eprintln!("Failed to load match alert rules: {:?}", e);
+            }
+            self.refreshed_at = Some(Instant::now());
+        }
+
+        for (rule, matcher) in &self.matchers {
+            if !matcher.matches(record) {
+                continue;
+            }
+            // Only waits when the background task is far behind.
+            if self
+                .matches
+                .send((Arc::clone(rule), record.clone()))
+                .await
+                .is_err()
+            {
+                eprintln!("Failed to queue alert event for \"{}\"", rule.name);
+            }
+        }
+    }
+
+    async fn refresh(&mut self) -> Result<(), mongodb::error::Error> {
+        let rules: Vec<AlertRuleRecord> = self
+            .rules
+            .find(doc! { "enabled": true, "kind": bson::to_bson(&AlertRuleKind::Match)? })
+            .await?
+            .try_collect()
+            .await?;
+        let mut matchers = Vec::with_capacity(rules.len());
+        for rule in rules {
+            match validate_options(rule.options.clone()).await {
+                Ok(options) => match HogMatcher::new(options) {
+                    Ok(matcher) => matchers.push((Arc::new(rule), matcher)),
+                    Err(e) => eprintln!("Alert rule \"{}\" has invalid options: {}", rule.name, e),
+                },
+                Err(validation_error) => eprintln!(
+                    "Alert rule \"{}\" has invalid options: {:?}",
+                    rule.name, validation_error.errors
+                ),
+            }
+        }
+        self.matchers = matchers;
+        Ok(())
+    }
+}
+
+impl AlertEmitter {
+    async fn emit(&self, rule: &AlertRuleRecord, record: &HogRecord) -> anyhow::Result<()> {
+        let rule_id = rule.id.map(|oid| oid.to_hex()).unwrap_or_default();
+        let now = BsonDateTime::now();
+        let mut labels = rule.labels.clone();
+        labels.insert("alertname".to_string(), rule.name.clone());
+        labels.insert("rule_id".to_string(), rule_id.clone());
+        let mut event = AlertEventRecord {
+            id: None,
+            rule_id: rule_id.clone(),
+            rule_name: rule.name.clone(),
+            labels,
+            hog_uuid: record.hog_uuid.clone(),
+            log_timestamp: record.log_timestamp,
+            log_level: record.log_level.clone(),
+            log_type: record.log_type.clone(),
+            log_source: record.log_source.clone(),
+            log_source_id: record.log_source_id.clone(),
+            log_message: record.log_message.clone(),
+            matched_at: now,
+            expires_at: BsonDateTime::from_millis(
+                now.timestamp_millis() + self.retention_secs * 1000,
+            ),
+        };
+
+        // A redelivered hog matches again; the upsert keeps one event per
+        // rule and hog, and only the first one gets published.
+        let id = match record.hog_uuid {
+            Some(ref hog_uuid) => {
+                let result = self
+                    .events
+                    .update_one(
+                        doc! { "rule_id": &rule_id, "hog_uuid": hog_uuid },
+                        doc! { "$setOnInsert": bson::to_document(&event)? },
+                    )
+                    .upsert(true)
+                    .await?;
+                match result.upserted_id {
+                    Some(id) => id,
+                    None => return Ok(()),
+                }
+            }
+            None => self.events.insert_one(&event).await?.inserted_id,
+        };
+        event.id = id.as_object_id();
+
+        self.channel
+            .basic_publish(
+                ALERT_EXCHANGE,
+                &format!("alert.{}", rule_id),
+                BasicPublishOptions::default(),
+                &serde_json::to_vec(&event.to_event())?,
+                BasicProperties::default().with_content_type("application/json".into()),
+            )
+            .await?
+            .await?;
+        Ok(())
+    }
+}
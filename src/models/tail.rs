@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use super::hog::Hog;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TailEvent {
+    Hog { hog: Box<Hog> },
+    Dropped { dropped: u64 },
+}
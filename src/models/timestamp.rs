@@ -0,0 +1,387 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Formats without an offset tried after the configured ones, read in the
+/// default timezone.
+const NAIVE_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Epochs below these magnitudes are read as seconds, milliseconds and
+/// microseconds; anything larger is nanoseconds. Seconds reach the year 5138.
+const EPOCH_SECONDS_BELOW: f64 = 1e11;
+const EPOCH_MILLIS_BELOW: f64 = 1e14;
+const EPOCH_MICROS_BELOW: f64 = 1e17;
+
+const TIMESTAMP_FORMAT_HELP: &str = "log_timestamp must be RFC 3339, RFC 2822, epoch seconds, \
+     milliseconds, microseconds or nanoseconds, or match one of the HOG_TIMESTAMP_FILE formats";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimestampConfig {
+    #[serde(default)]
+    formats: Vec<String>,
+    timezone: Option<String>,
+    #[serde(default)]
+    source_timezones: HashMap<String, String>,
+}
+
+/// Reads `log_timestamp` in whatever shape a source sends it and normalizes
+/// it to UTC. Values that carry an offset keep it; the rest are read in the
+/// timezone of their `log_source`, or the default one. See
+/// `HOG_TIMESTAMP_FILE` in the README.
+#[allow(dead_code)]
+pub struct TimestampParser {
+    formats: Vec<String>,
+    timezone: Tz,
+    source_timezones: HashMap<String, Tz>,
+}
+
+#[allow(dead_code)]
+impl TimestampParser {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_config(config).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_config(config: Value) -> Result<Self, String> {
+        let config: TimestampConfig = serde_json::from_value(config).map_err(|e| e.to_string())?;
+        for (i, format) in config.formats.iter().enumerate() {
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("formats[{}]: {} is not a valid format", i, format));
+            }
+        }
+        let timezone = match config.timezone {
+            Some(ref name) => parse_timezone(name).map_err(|e| format!("timezone: {}", e))?,
+            None => Tz::UTC,
+        };
+        let mut source_timezones = HashMap::new();
+        for (source, name) in config.source_timezones {
+            let tz =
+                parse_timezone(&name).map_err(|e| format!("source_timezones.{}: {}", source, e))?;
+            source_timezones.insert(source, tz);
+        }
+        Ok(TimestampParser {
+            formats: config.formats,
+            timezone,
+            source_timezones,
+        })
+    }
+
+    /// Parses a `log_timestamp` value, returning the message for a
+    /// field-level validation error when it can't.
+    pub fn parse(&self, value: &Value, log_source: Option<&str>) -> Result<DateTime<Utc>, String> {
+        match value {
+            Value::Number(number) => match number.as_i64() {
+                Some(epoch) => from_epoch_int(epoch),
+                None => number.as_f64().and_then(from_epoch_float),
+            }
+            .ok_or_else(|| "log_timestamp is out of range".to_string()),
+            Value::String(text) => self
+                .parse_str(text.trim(), log_source)
+                .ok_or_else(|| TIMESTAMP_FORMAT_HELP.to_string()),
+            _ => Err("log_timestamp must be a string or a number".to_string()),
+        }
+    }
+
+    fn parse_str(&self, text: &str, log_source: Option<&str>) -> Option<DateTime<Utc>> {
+        if is_epoch(text) {
+            return match text.parse::<i64>() {
+                Ok(epoch) => from_epoch_int(epoch),
+                Err(_) => text.parse::<f64>().ok().and_then(from_epoch_float),
+            };
+        }
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+            return Some(datetime.with_timezone(&Utc));
+        }
+        if let Ok(datetime) = DateTime::parse_from_rfc2822(text) {
+            return Some(datetime.with_timezone(&Utc));
+        }
+
+        let timezone = log_source
+            .and_then(|source| self.source_timezones.get(source))
+            .unwrap_or(&self.timezone);
+        let formats = self
+            .formats
+            .iter()
+            .map(String::as_str)
+            .chain(NAIVE_FORMATS.iter().copied());
+        for format in formats {
+            if let Ok(datetime) = DateTime::parse_from_str(text, format) {
+                return Some(datetime.with_timezone(&Utc));
+            }
+            let naive = NaiveDateTime::parse_from_str(text, format).or_else(|_| {
+                NaiveDate::parse_from_str(text, format)
+                    .map(|date| date.and_time(Default::default()))
+            });
+            // Inside a DST gap there's no such local time; in an overlap the
+            // earlier of the two is taken.
+            if let Ok(naive) = naive
+                && let Some(datetime) = timezone.from_local_datetime(&naive).earliest()
+            {
+                return Some(datetime.with_timezone(&Utc));
+            }
+        }
+        None
+    }
+}
+
+fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>()
+        .map_err(|_| format!("{} is not an IANA timezone", name))
+}
+
+fn is_epoch(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    !whole.is_empty()
+        && whole.bytes().all(|b| b.is_ascii_digit())
+        && fraction.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Units per second of an epoch of this magnitude.
+fn epoch_units_per_second(magnitude: f64) -> i64 {
+    if magnitude < EPOCH_SECONDS_BELOW {
+        1
+    } else if magnitude < EPOCH_MILLIS_BELOW {
+        1_000
+    } else if magnitude < EPOCH_MICROS_BELOW {
+        1_000_000
+    } else {
+        1_000_000_000
+    }
+}
+
+fn from_epoch_int(epoch: i64) -> Option<DateTime<Utc>> {
+    let per_second = epoch_units_per_second(epoch.unsigned_abs() as f64);
+    let nanos = epoch.rem_euclid(per_second) * (1_000_000_000 / per_second);
+    DateTime::from_timestamp(epoch.div_euclid(per_second), nanos as u32)
+}
+
+fn from_epoch_float(epoch: f64) -> Option<DateTime<Utc>> {
+    let seconds = epoch / epoch_units_per_second(epoch.abs()) as f64;
+    let whole = seconds.floor();
+    if !whole.is_finite() || whole.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    let nanos = ((seconds - whole) * 1e9).round().min(999_999_999.0);
+    DateTime::from_timestamp(whole as i64, nanos as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parser(config: Value) -> TimestampParser {
+        TimestampParser::from_config(config).unwrap()
+    }
+
+    fn parse(parser: &TimestampParser, value: Value) -> Result<String, String> {
+        parser
+            .parse(&value, None)
+            .map(|datetime| datetime.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+
+    #[test]
+    fn epochs_are_read_by_magnitude() {
+        let parser = parser(json!({}));
+        for epoch in [
+            json!(1_700_000_000),
+            json!(1_700_000_000_000i64),
+            json!(1_700_000_000_000_000i64),
+            json!(1_700_000_000_000_000_000i64),
+            json!("1700000000000"),
+            json!(" 1700000000 "),
+        ] {
+            assert_eq!(
+                parse(&parser, epoch.clone()),
+                Ok("2023-11-14T22:13:20Z".to_string()),
+                "{}",
+                epoch
+            );
+        }
+    }
+
+    #[test]
+    fn millisecond_epochs_before_1973_read_as_seconds() {
+        let parser = parser(json!({}));
+        // 1e11 ms is 1973-03-03; below that an epoch is taken as seconds.
+        assert_eq!(
+            parse(&parser, json!(99_999_999_999i64)),
+            Ok("5138-11-16T09:46:39Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!(100_000_000_000i64)),
+            Ok("1973-03-03T09:46:40Z".to_string())
+        );
+    }
+
+    #[test]
+    fn negative_epochs_count_back_from_1970() {
+        let parser = parser(json!({}));
+        assert_eq!(
+            parse(&parser, json!(-1)),
+            Ok("1969-12-31T23:59:59Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!(-1_500)),
+            Ok("1969-12-31T23:35:00Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!(-1_000_000_000_000i64)),
+            Ok("1938-04-24T22:13:20Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!("-1")),
+            Ok("1969-12-31T23:59:59Z".to_string())
+        );
+    }
+
+    #[test]
+    fn fractional_epochs_keep_their_fraction() {
+        let parser = parser(json!({}));
+        assert_eq!(
+            parse(&parser, json!(1_700_000_000.5)),
+            Ok("2023-11-14T22:13:20.500Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!("1700000000.25")),
+            Ok("2023-11-14T22:13:20.250Z".to_string())
+        );
+        // The fraction goes through an f64, so it's only as exact as that.
+        assert_eq!(
+            parse(&parser, json!("1700000000500.0")),
+            Ok("2023-11-14T22:13:20.500Z".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!("-1.5")),
+            Ok("1969-12-31T23:59:58.500Z".to_string())
+        );
+    }
+
+    #[test]
+    fn offsets_in_the_value_win_over_the_timezone() {
+        let parser = parser(json!({
+            "formats": ["%d/%b/%Y:%H:%M:%S %z"],
+            "timezone": "Europe/Berlin",
+        }));
+        for text in [
+            "2024-05-18T15:28:34+02:00",
+            "Sat, 18 May 2024 15:28:34 +0200",
+            "18/May/2024:15:28:34 +0200",
+        ] {
+            assert_eq!(
+                parse(&parser, json!(text)),
+                Ok("2024-05-18T13:28:34Z".to_string()),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn naive_values_are_read_in_the_source_timezone() {
+        let parser = parser(json!({
+            "formats": ["%d.%m.%Y"],
+            "timezone": "Europe/Berlin",
+            "source_timezones": { "ledger": "America/New_York" },
+        }));
+        let naive = json!("2024-05-18 15:28:34.250");
+        assert_eq!(
+            parse(&parser, naive.clone()),
+            Ok("2024-05-18T13:28:34.250Z".to_string())
+        );
+        assert_eq!(
+            parser
+                .parse(&naive, Some("ledger"))
+                .map(|datetime| datetime.to_rfc3339()),
+            Ok("2024-05-18T19:28:34.250+00:00".to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!("18.05.2024")),
+            Ok("2024-05-17T22:00:00Z".to_string())
+        );
+        assert_eq!(
+            parse(&TimestampParser::from_config(json!({})).unwrap(), naive),
+            Ok("2024-05-18T15:28:34.250Z".to_string())
+        );
+    }
+
+    #[test]
+    fn digits_only_values_are_epochs_before_formats() {
+        let parser = parser(json!({ "formats": ["%Y%m%d"] }));
+        assert_eq!(
+            parse(&parser, json!("20240518")),
+            Ok("1970-08-23T06:21:58Z".to_string())
+        );
+    }
+
+    #[test]
+    fn dst_overlaps_take_the_earlier_time() {
+        let parser = parser(json!({ "timezone": "America/New_York" }));
+        // 01:30 happens twice on 2024-11-03, first in EDT (-4) then in EST.
+        assert_eq!(
+            parse(&parser, json!("2024-11-03T01:30:00")),
+            Ok("2024-11-03T05:30:00Z".to_string())
+        );
+    }
+
+    #[test]
+    fn dst_gaps_are_rejected() {
+        let parser = parser(json!({ "timezone": "America/New_York" }));
+        // Clocks jump from 02:00 to 03:00 on 2024-03-10.
+        assert_eq!(
+            parse(&parser, json!("2024-03-10T02:30:00")),
+            Err(TIMESTAMP_FORMAT_HELP.to_string())
+        );
+        assert_eq!(
+            parse(&parser, json!("2024-03-10T03:30:00")),
+            Ok("2024-03-10T07:30:00Z".to_string())
+        );
+    }
+
+    #[test]
+    fn unreadable_values_are_rejected() {
+        let parser = parser(json!({}));
+        for text in ["yesterday", "", "-", "1e9", "1.2.3", "2024-13-01T00:00:00Z"] {
+            assert_eq!(
+                parse(&parser, json!(text)),
+                Err(TIMESTAMP_FORMAT_HELP.to_string()),
+                "{}",
+                text
+            );
+        }
+        for value in [json!(true), json!(null), json!([1]), json!({ "at": 1 })] {
+            assert_eq!(
+                parse(&parser, value),
+                Err("log_timestamp must be a string or a number".to_string())
+            );
+        }
+        assert_eq!(
+            parse(&parser, json!(1e300)),
+            Err("log_timestamp is out of range".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let error = |config: Value| TimestampParser::from_config(config).err().unwrap();
+        assert_eq!(
+            error(json!({ "formats": ["%Y-%m-%d", "%Y %!"] })),
+            "formats[1]: %Y %! is not a valid format"
+        );
+        assert_eq!(
+            error(json!({ "timezone": "Mars/Olympus" })),
+            "timezone: Mars/Olympus is not an IANA timezone"
+        );
+        assert_eq!(
+            error(json!({ "source_timezones": { "ledger": "EST5" } })),
+            "source_timezones.ledger: EST5 is not an IANA timezone"
+        );
+        assert!(error(json!({ "zone": "UTC" })).contains("unknown field"));
+    }
+}
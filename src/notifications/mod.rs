@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::config;
+use crate::models::notification::{Notification, NotificationTarget};
+
+const NOTIFICATION_TIMEOUT_SECS: u64 = 10;
+const SMTP_PORT: u16 = 587;
+
+/// Delivers notifications to their targets. Failures are logged, never
+/// retried: whatever raised the notification will raise it again if it still
+/// matters.
+pub struct Notifier {
+    client: reqwest::Client,
+    smtp: Option<Smtp>,
+}
+
+struct Smtp {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let timeout = config::env_or("NOTIFICATION_TIMEOUT_SECS", NOTIFICATION_TIMEOUT_SECS);
+        Notifier {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(timeout))
+                .build()
+                .expect("Failed to build HTTP client"),
+            smtp: Smtp::from_env(timeout),
+        }
+    }
+
+    pub async fn notify(&self, targets: &[NotificationTarget], notification: &Notification) {
+        for target in targets {
+            if let Err(err) = self.send(target, notification).await {
+                eprintln!(
+                    "Failed to send notification \"{}\" to {:?}: {}",
+                    notification.title, target, err
+                );
+            }
+        }
+    }
+
+    /// Sends to a single target and hands the error back instead of logging
+    /// it.
+    pub async fn send(
+        &self,
+        target: &NotificationTarget,
+        notification: &Notification,
+    ) -> Result<(), String> {
+        match target {
+            NotificationTarget::Webhook { url } => {
+                self.client
+                    .post(url)
+                    .json(notification)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| e.to_string())?;
+            }
+            NotificationTarget::Slack { url } => {
+                self.client
+                    .post(url)
+                    .json(&serde_json::json!({ "text": notification.to_text() }))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| e.to_string())?;
+            }
+            NotificationTarget::Smtp { to } => {
+                let Some(ref smtp) = self.smtp else {
+                    return Err("SMTP_HOST is not set".to_string());
+                };
+                smtp.send(to, notification).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Smtp {
+    /// Reads `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD`,
+    /// `SMTP_FROM` and `SMTP_TLS` (`starttls`, `tls` or `none`). Mail targets
+    /// fail when `SMTP_HOST` is not set.
+    fn from_env(timeout: u64) -> Option<Self> {
+        let host = std::env::var("SMTP_HOST").ok()?;
+        let tls = std::env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+        let builder = match tls.as_str() {
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &host,
+            )),
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host),
+        };
+        let mut builder = match builder {
+            Ok(builder) => builder
+                .port(config::env_or("SMTP_PORT", SMTP_PORT))
+                .timeout(Some(Duration::from_secs(timeout))),
+            Err(err) => {
+                eprintln!("Failed to set up SMTP transport for {}: {}", host, err);
+                return None;
+            }
+        };
+        if let (Ok(username), Ok(password)) = (
+            std::env::var("SMTP_USERNAME"),
+            std::env::var("SMTP_PASSWORD"),
+        ) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Some(Smtp {
+            transport: builder.build(),
+            from: std::env::var("SMTP_FROM").unwrap_or_else(|_| format!("hogger@{}", host)),
+        })
+    }
+
+    async fn send(&self, to: &[String], notification: &Notification) -> Result<(), String> {
+        let mut message = Message::builder()
+            .from(self.from.parse().map_err(|e| format!("SMTP_FROM: {}", e))?)
+            .subject(format!(
+                "[{}] {}",
+                notification.status.label(),
+                notification.title
+            ))
+            .header(ContentType::TEXT_PLAIN);
+        for address in to {
+            message = message.to(address.parse().map_err(|e| format!("{}: {}", address, e))?);
+        }
+        let message = message
+            .body(notification.to_text())
+            .map_err(|e| e.to_string())?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
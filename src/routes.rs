@@ -1,21 +1,149 @@
-use axum::{Extension, Router, routing::get, routing::post};
+use axum::{
+    Extension, Router,
+    routing::{delete, get, patch, post},
+};
 use std::sync::Arc;
 
-use crate::{controllers::hog_controller, services::hog_service::HogService};
-use lapin::Channel;
+use crate::{
+    controllers::{
+        alert_controller, anomaly_controller, heartbeat_controller, hog_controller,
+        pattern_controller, saved_search_controller, search_job_controller,
+    },
+    notifications::Notifier,
+    services::{
+        alert_service::AlertService, anomaly_service::AnomalyService,
+        heartbeat_service::HeartbeatService, hog_service::HogService,
+        pattern_service::PatternService, saved_search_service::SavedSearchService,
+        search_job_service::SearchJobService,
+    },
+};
 use mongodb::Database;
 
-type RabbitChannel = Channel;
-
-pub fn create_router(db: Database, rabbit_channel: RabbitChannel) -> Router {
-    let hog_service = Arc::new(HogService::new(&db, rabbit_channel));
+pub fn create_router(db: Database, hog_service: Arc<HogService>) -> Router {
+    let search_job_service = Arc::new(SearchJobService::new(&db, Arc::clone(&hog_service)));
+    let saved_search_service = Arc::new(SavedSearchService::new(&db));
+    let pattern_service = Arc::new(PatternService::new(&db, Arc::clone(&hog_service)));
+    let anomaly_service = Arc::new(AnomalyService::new(&db));
+    anomaly_service.spawn();
+    let notifier = Arc::new(Notifier::new());
+    let heartbeat_service = Arc::new(HeartbeatService::new(&db, Arc::clone(&notifier)));
+    heartbeat_service.spawn();
+    let alert_service = Arc::new(AlertService::new(&db, Arc::clone(&notifier)));
+    alert_service.spawn();
 
     Router::new()
         .route("/metrics", get(hog_controller::metrics))
         .route("/hogs", get(hog_controller::get_hogs))
         .route("/hogs", post(hog_controller::create_hog))
         .route("/hogs/search", post(hog_controller::handle_search))
+        .route("/hogs/search/explain", post(hog_controller::explain_search))
+        .route("/hogs/tail", get(hog_controller::tail_hogs))
+        .route("/hogs/export", post(hog_controller::export_hogs))
+        .route(
+            "/hogs/patterns",
+            get(pattern_controller::list_patterns).post(pattern_controller::mine_patterns),
+        )
+        .route(
+            "/hogs/{id}",
+            get(hog_controller::get_hog).delete(hog_controller::delete_hog),
+        )
+        .route(
+            "/hogs/{id}/annotations",
+            patch(hog_controller::annotate_hog),
+        )
+        .route("/hogs/{id}/context", get(hog_controller::hog_context))
         .route("/hogs/statistics", get(hog_controller::hog_statistics))
         .route("/hogs/stats", get(hog_controller::hog_stats))
+        .route(
+            "/search-jobs",
+            post(search_job_controller::create_search_job),
+        )
+        .route(
+            "/search-jobs/{id}",
+            get(search_job_controller::get_search_job)
+                .delete(search_job_controller::delete_search_job),
+        )
+        .route(
+            "/search-jobs/{id}/results",
+            get(search_job_controller::get_search_job_results),
+        )
+        .route(
+            "/search-jobs/{id}/cancel",
+            post(search_job_controller::cancel_search_job),
+        )
+        .route(
+            "/saved-searches",
+            get(saved_search_controller::list_saved_searches)
+                .post(saved_search_controller::create_saved_search),
+        )
+        .route(
+            "/saved-searches/{id}",
+            get(saved_search_controller::get_saved_search)
+                .put(saved_search_controller::update_saved_search)
+                .delete(saved_search_controller::delete_saved_search),
+        )
+        .route(
+            "/saved-searches/{id}/run",
+            post(saved_search_controller::run_saved_search),
+        )
+        .route("/anomalies", get(anomaly_controller::list_anomalies))
+        .route(
+            "/anomalies/baselines",
+            get(anomaly_controller::list_baselines),
+        )
+        .route(
+            "/sources/rules",
+            get(heartbeat_controller::list_heartbeat_rules)
+                .post(heartbeat_controller::create_heartbeat_rule),
+        )
+        .route(
+            "/sources/rules/{id}",
+            get(heartbeat_controller::get_heartbeat_rule)
+                .put(heartbeat_controller::update_heartbeat_rule)
+                .delete(heartbeat_controller::delete_heartbeat_rule),
+        )
+        .route("/sources/status", get(heartbeat_controller::source_status))
+        .route("/sources/lag", get(hog_controller::source_lag))
+        .route("/alerts", get(alert_controller::list_alerts))
+        .route("/alerts/events", get(alert_controller::list_alert_events))
+        .route(
+            "/alerts/rules",
+            get(alert_controller::list_alert_rules).post(alert_controller::create_alert_rule),
+        )
+        .route(
+            "/alerts/rules/{id}",
+            get(alert_controller::get_alert_rule)
+                .put(alert_controller::update_alert_rule)
+                .delete(alert_controller::delete_alert_rule),
+        )
+        .route(
+            "/alerts/channels",
+            get(alert_controller::list_notification_channels)
+                .post(alert_controller::create_notification_channel),
+        )
+        .route(
+            "/alerts/channels/{id}",
+            get(alert_controller::get_notification_channel)
+                .put(alert_controller::update_notification_channel)
+                .delete(alert_controller::delete_notification_channel),
+        )
+        .route(
+            "/alerts/channels/{id}/test",
+            post(alert_controller::test_notification_channel),
+        )
+        .route(
+            "/alerts/silences",
+            get(alert_controller::list_silences).post(alert_controller::create_silence),
+        )
+        .route(
+            "/alerts/silences/{id}",
+            delete(alert_controller::delete_silence),
+        )
         .layer(Extension(hog_service))
+        .layer(Extension(search_job_service))
+        .layer(Extension(saved_search_service))
+        .layer(Extension(pattern_service))
+        .layer(Extension(anomaly_service))
+        .layer(Extension(heartbeat_service))
+        .layer(Extension(alert_service))
 }
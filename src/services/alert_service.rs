@@ -0,0 +1,836 @@
+use crate::config;
+use crate::models::alert::{
+    Alert, AlertEvent, AlertEventRecord, AlertQuery, AlertRecord, AlertRule, AlertRuleKind,
+    AlertRuleRecord, AlertRuleRequest, AlertState, Silence, SilenceRecord, SilenceRequest,
+};
+use crate::models::notification::{
+    Notification, NotificationAlert, NotificationChannel, NotificationChannelRecord,
+    NotificationChannelRequest, NotificationStatus,
+};
+use crate::models::options::{self, build_log_data_value_aggregation_pipeline, validate_options};
+use crate::notifications::Notifier;
+use crate::services::saved_search_service::is_duplicate_key_error;
+use crate::utils::utils;
+use bson::DateTime as BsonDateTime;
+use futures::TryStreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::options::{AggregateOptions, FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    Collection, Database,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+const ALERT_EVAL_INTERVAL_SECS: u64 = 30;
+const ALERT_RESOLVED_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+const ALERT_MAX_TIME_MS: u64 = 30_000;
+
+/// Evaluates alert rules on a schedule, keeps one alert per rule × group in
+/// the `alert` collection and notifies the rule's channels when alerts fire,
+/// keep firing past their repeat interval or resolve.
+pub struct AlertService {
+    hogs: Collection<Document>,
+    rules: Collection<AlertRuleRecord>,
+    alerts: Collection<AlertRecord>,
+    channels: Collection<NotificationChannelRecord>,
+    silences: Collection<SilenceRecord>,
+    events: Collection<AlertEventRecord>,
+    notifier: Arc<Notifier>,
+    max_time: Duration,
+    resolved_retention_secs: i64,
+}
+
+/// What a rule's evaluation wants to tell its channels, grouped so one cycle
+/// sends at most one firing and one resolved notification per rule.
+#[derive(Default)]
+struct PendingNotifications {
+    firing: Vec<NotificationAlert>,
+    resolved: Vec<NotificationAlert>,
+}
+
+impl AlertService {
+    pub fn new(db: &Database, notifier: Arc<Notifier>) -> Self {
+        AlertService {
+            hogs: db.collection::<Document>("hog"),
+            rules: db.collection::<AlertRuleRecord>("alert_rule"),
+            alerts: db.collection::<AlertRecord>("alert"),
+            channels: db.collection::<NotificationChannelRecord>("notification_channel"),
+            silences: db.collection::<SilenceRecord>("alert_silence"),
+            events: db.collection::<AlertEventRecord>("alert_event"),
+            notifier,
+            max_time: Duration::from_millis(config::env_or("ALERT_MAX_TIME_MS", ALERT_MAX_TIME_MS)),
+            resolved_retention_secs: config::env_or(
+                "ALERT_RESOLVED_RETENTION_SECS",
+                ALERT_RESOLVED_RETENTION_SECS,
+            ),
+        }
+    }
+
+    /// Starts the scheduler that evaluates every enabled rule each
+    /// `ALERT_EVAL_INTERVAL_SECS`.
+    pub fn spawn(self: &Arc<Self>) {
+        let interval = config::env_or("ALERT_EVAL_INTERVAL_SECS", ALERT_EVAL_INTERVAL_SECS);
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+            loop {
+                ticker.tick().await;
+                if let Err(err) = service.evaluate_rules().await {
+                    eprintln!("Failed to evaluate alert rules: {:?}", err);
+                }
+            }
+        });
+    }
+
+    async fn evaluate_rules(&self) -> Result<(), mongodb::error::Error> {
+        let rules: Vec<AlertRuleRecord> = self
+            .rules
+            .find(doc! {
+                "enabled": true,
+                "kind": { "$ne": bson::to_bson(&AlertRuleKind::Match)? },
+            })
+            .await?
+            .try_collect()
+            .await?;
+        let silences: Vec<SilenceRecord> = self
+            .silences
+            .find(doc! { "ends_at": { "$gt": BsonDateTime::now() } })
+            .await?
+            .try_collect()
+            .await?;
+        for rule in rules {
+            // One broken rule shouldn't keep the others from running.
+            if let Err(err) = self.evaluate_rule(&rule, &silences).await {
+                eprintln!("Failed to evaluate alert rule \"{}\": {:?}", rule.name, err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn evaluate_rule(
+        &self,
+        rule: &AlertRuleRecord,
+        silences: &[SilenceRecord],
+    ) -> Result<(), mongodb::error::Error> {
+        let Some(id) = rule.id else {
+            return Ok(());
+        };
+        let rule_id = id.to_hex();
+        let now = utils::get_timestamp();
+        let counts = self.count_groups(rule, now).await?;
+
+        let existing: Vec<AlertRecord> = self
+            .alerts
+            .find(doc! { "rule_id": &rule_id })
+            .await?
+            .try_collect()
+            .await?;
+        let mut existing: HashMap<String, AlertRecord> = existing
+            .into_iter()
+            .map(|alert| (alert.group_key.clone(), alert))
+            .collect();
+
+        // Groups that matched nothing this window, but have an alert, count
+        // as zero. Without `group_by` the single group always gets evaluated.
+        let mut groups: BTreeMap<String, (BTreeMap<String, String>, i64)> = counts;
+        if rule.group_by.is_empty() {
+            groups
+                .entry(String::new())
+                .or_insert_with(|| (BTreeMap::new(), 0));
+        }
+        for (group_key, alert) in &existing {
+            groups.entry(group_key.clone()).or_insert_with(|| {
+                let group_labels = rule
+                    .group_by
+                    .iter()
+                    .filter_map(|field| {
+                        alert
+                            .labels
+                            .get(field)
+                            .map(|value| (field.clone(), value.clone()))
+                    })
+                    .collect();
+                (group_labels, 0)
+            });
+        }
+
+        let mut pending = PendingNotifications::default();
+        for (group_key, (group_labels, value)) in groups {
+            let labels = alert_labels(rule, &rule_id, group_labels);
+            let silenced = silences
+                .iter()
+                .any(|silence| silence.silences(&labels, BsonDateTime::from_chrono(now)));
+            self.evaluate_group(
+                rule,
+                &rule_id,
+                existing.remove(&group_key),
+                group_key,
+                labels,
+                value,
+                silenced,
+                now,
+                &mut pending,
+            )
+            .await?;
+        }
+
+        self.send_notifications(rule, pending, now).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn evaluate_group(
+        &self,
+        rule: &AlertRuleRecord,
+        rule_id: &str,
+        existing: Option<AlertRecord>,
+        group_key: String,
+        labels: BTreeMap<String, String>,
+        value: i64,
+        silenced: bool,
+        now: chrono::DateTime<chrono::Utc>,
+        pending: &mut PendingNotifications,
+    ) -> Result<(), mongodb::error::Error> {
+        let now_bson = BsonDateTime::from_chrono(now);
+        let active = rule
+            .condition
+            .is_some_and(|condition| condition.matches(value));
+        let previous = existing.filter(|alert| alert.state != AlertState::Resolved);
+
+        let Some(previous) = previous else {
+            if !active {
+                return Ok(());
+            }
+            let state = if rule.for_secs == 0 {
+                AlertState::Firing
+            } else {
+                AlertState::Pending
+            };
+            let notify = state == AlertState::Firing && !silenced;
+            let alert = AlertRecord {
+                id: None,
+                rule_id: rule_id.to_string(),
+                rule_name: rule.name.clone(),
+                group_key: group_key.clone(),
+                labels,
+                state,
+                value,
+                active_since: now_bson,
+                fired_at: (state == AlertState::Firing).then_some(now_bson),
+                resolved_at: None,
+                last_evaluated_at: now_bson,
+                last_notified_at: notify.then_some(now_bson),
+                silenced,
+                expires_at: None,
+            };
+            // A resolved alert for the group is reused, so the unique
+            // rule_id/group_key index decides which API instance starts it.
+            let result = self
+                .alerts
+                .replace_one(
+                    doc! {
+                        "rule_id": rule_id,
+                        "group_key": &group_key,
+                        "state": bson::to_bson(&AlertState::Resolved)?,
+                    },
+                    &alert,
+                )
+                .upsert(true)
+                .await;
+            match result {
+                Ok(_) if notify => pending.firing.push(notification_alert(&alert)),
+                Ok(_) => {}
+                Err(err) if is_duplicate_key_error(&err) => {}
+                Err(err) => return Err(err),
+            }
+            return Ok(());
+        };
+
+        // Matching on the previous evaluation means only one API instance
+        // wins each transition, so only one of them notifies.
+        let filter = doc! {
+            "_id": previous.id,
+            "state": bson::to_bson(&previous.state)?,
+            "last_evaluated_at": previous.last_evaluated_at,
+        };
+
+        if !active && previous.state == AlertState::Pending {
+            self.alerts.delete_one(filter).await?;
+            return Ok(());
+        }
+
+        let mut alert = AlertRecord {
+            labels,
+            value,
+            silenced,
+            last_evaluated_at: now_bson,
+            ..previous.clone()
+        };
+        let mut status = None;
+        if !active {
+            alert.state = AlertState::Resolved;
+            alert.resolved_at = Some(now_bson);
+            alert.expires_at = Some(BsonDateTime::from_chrono(
+                now + chrono::Duration::seconds(self.resolved_retention_secs),
+            ));
+            // Nobody heard it fire, nobody needs to hear it resolve.
+            if previous.last_notified_at.is_some() && !silenced {
+                status = Some(NotificationStatus::Resolved);
+            }
+        } else {
+            if previous.state == AlertState::Pending
+                && (now - previous.active_since.to_chrono()).num_seconds() >= rule.for_secs
+            {
+                alert.state = AlertState::Firing;
+                alert.fired_at = Some(now_bson);
+            }
+            let due = match previous.last_notified_at {
+                Some(last_notified_at) => {
+                    (now - last_notified_at.to_chrono()).num_seconds() >= rule.repeat_interval_secs
+                }
+                None => true,
+            };
+            if alert.state == AlertState::Firing && !silenced && due {
+                alert.last_notified_at = Some(now_bson);
+                status = Some(NotificationStatus::Firing);
+            }
+        }
+
+        let updated = self.alerts.replace_one(filter, &alert).await?;
+        if updated.modified_count > 0 {
+            match status {
+                Some(NotificationStatus::Firing) => pending.firing.push(notification_alert(&alert)),
+                Some(NotificationStatus::Resolved) => {
+                    pending.resolved.push(notification_alert(&alert))
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_notifications(
+        &self,
+        rule: &AlertRuleRecord,
+        pending: PendingNotifications,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), mongodb::error::Error> {
+        if pending.firing.is_empty() && pending.resolved.is_empty() {
+            return Ok(());
+        }
+        let channel_ids: Vec<ObjectId> = rule
+            .channels
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let targets: Vec<_> = self
+            .channels
+            .find(doc! { "_id": { "$in": channel_ids } })
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .map(|channel| channel.target)
+            .collect();
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        for (status, alerts) in [
+            (NotificationStatus::Firing, pending.firing),
+            (NotificationStatus::Resolved, pending.resolved),
+        ] {
+            if alerts.is_empty() {
+                continue;
+            }
+            let notification = alert_notification(rule, status, alerts, now);
+            self.notifier.notify(&targets, &notification).await;
+        }
+        Ok(())
+    }
+
+    pub async fn list_alerts(
+        &self,
+        query: &AlertQuery,
+    ) -> Result<Vec<Alert>, mongodb::error::Error> {
+        let mut filter = Document::new();
+        if let Some(state) = query.state {
+            filter.insert("state", bson::to_bson(&state)?);
+        }
+        if let Some(ref rule_id) = query.rule_id {
+            filter.insert("rule_id", rule_id);
+        }
+        let find_options = FindOptions::builder()
+            .sort(doc! { "active_since": -1 })
+            .build();
+        let records: Vec<AlertRecord> = self
+            .alerts
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_alert_record_to_alert)
+            .collect())
+    }
+
+    /// Hogs that matched `match` rules, newest first.
+    pub async fn list_events(
+        &self,
+        rule_id: Option<&str>,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        limit: i64,
+    ) -> Result<Vec<AlertEvent>, mongodb::error::Error> {
+        let mut filter = Document::new();
+        if let Some(rule_id) = rule_id {
+            filter.insert("rule_id", rule_id);
+        }
+        if let Some(since) = since {
+            filter.insert(
+                "matched_at",
+                doc! { "$gte": BsonDateTime::from_chrono(since) },
+            );
+        }
+        let find_options = FindOptions::builder()
+            .sort(doc! { "matched_at": -1 })
+            .limit(limit)
+            .build();
+        let records: Vec<AlertEventRecord> = self
+            .events
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records.iter().map(AlertEventRecord::to_event).collect())
+    }
+
+    pub async fn create_rule(
+        &self,
+        req: AlertRuleRequest,
+    ) -> Result<AlertRule, mongodb::error::Error> {
+        let now = utils::convert_timestamp_chrono_to_bson(utils::get_timestamp());
+        let mut record = AlertRuleRecord {
+            id: None,
+            name: req.name,
+            description: req.description,
+            options: req.options,
+            kind: req.kind,
+            window_secs: req.window_secs,
+            condition: req.condition,
+            for_secs: req.for_secs,
+            repeat_interval_secs: req.repeat_interval_secs,
+            group_by: req.group_by,
+            channels: req.channels,
+            labels: req.labels,
+            enabled: req.enabled,
+            created_at: now,
+            updated_at: now,
+        };
+        let result = self.rules.insert_one(&record).await?;
+        record.id = result.inserted_id.as_object_id();
+        Ok(utils::convert_alert_rule_record_to_alert_rule(record))
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<AlertRule>, mongodb::error::Error> {
+        let find_options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        let records: Vec<AlertRuleRecord> = self
+            .rules
+            .find(doc! {})
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_alert_rule_record_to_alert_rule)
+            .collect())
+    }
+
+    pub async fn get_rule(&self, id: &str) -> Result<Option<AlertRule>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let record = self.rules.find_one(doc! { "_id": oid }).await?;
+        Ok(record.map(utils::convert_alert_rule_record_to_alert_rule))
+    }
+
+    /// Replaces the rule's settings. Its alerts are dropped and start over on
+    /// the next evaluation, since their groups may not exist anymore.
+    pub async fn update_rule(
+        &self,
+        id: &str,
+        req: AlertRuleRequest,
+    ) -> Result<Option<AlertRule>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let update = doc! { "$set": {
+            "name": req.name,
+            "description": req.description,
+            "options": bson::to_bson(&req.options)?,
+            "kind": bson::to_bson(&req.kind)?,
+            "window_secs": req.window_secs,
+            "condition": bson::to_bson(&req.condition)?,
+            "for_secs": req.for_secs,
+            "repeat_interval_secs": req.repeat_interval_secs,
+            "group_by": req.group_by,
+            "channels": req.channels,
+            "labels": bson::to_bson(&req.labels)?,
+            "enabled": req.enabled,
+            "updated_at": utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let record = self
+            .rules
+            .find_one_and_update(doc! { "_id": oid }, update)
+            .with_options(options)
+            .await?;
+        if record.is_some() {
+            self.alerts.delete_many(doc! { "rule_id": id }).await?;
+        }
+        Ok(record.map(utils::convert_alert_rule_record_to_alert_rule))
+    }
+
+    pub async fn delete_rule(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = self.rules.delete_one(doc! { "_id": oid }).await?;
+        if result.deleted_count > 0 {
+            self.alerts.delete_many(doc! { "rule_id": id }).await?;
+        }
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Channel ids out of `ids` that don't exist.
+    pub async fn missing_channels(
+        &self,
+        ids: &[String],
+    ) -> Result<Vec<String>, mongodb::error::Error> {
+        let oids: Vec<ObjectId> = ids
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let found: Vec<String> = self
+            .channels
+            .find(doc! { "_id": { "$in": oids } })
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .filter_map(|channel| channel.id.map(|oid| oid.to_hex()))
+            .collect();
+        Ok(ids
+            .iter()
+            .filter(|id| !found.contains(id))
+            .cloned()
+            .collect())
+    }
+
+    pub async fn create_channel(
+        &self,
+        req: NotificationChannelRequest,
+    ) -> Result<NotificationChannel, mongodb::error::Error> {
+        let mut record = NotificationChannelRecord {
+            id: None,
+            name: req.name,
+            target: req.target,
+            created_at: utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        };
+        let result = self.channels.insert_one(&record).await?;
+        record.id = result.inserted_id.as_object_id();
+        Ok(utils::convert_notification_channel_record_to_notification_channel(record))
+    }
+
+    pub async fn list_channels(&self) -> Result<Vec<NotificationChannel>, mongodb::error::Error> {
+        let find_options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        let records: Vec<NotificationChannelRecord> = self
+            .channels
+            .find(doc! {})
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_notification_channel_record_to_notification_channel)
+            .collect())
+    }
+
+    pub async fn get_channel(
+        &self,
+        id: &str,
+    ) -> Result<Option<NotificationChannel>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let record = self.channels.find_one(doc! { "_id": oid }).await?;
+        Ok(record.map(utils::convert_notification_channel_record_to_notification_channel))
+    }
+
+    pub async fn update_channel(
+        &self,
+        id: &str,
+        req: NotificationChannelRequest,
+    ) -> Result<Option<NotificationChannel>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let update = doc! { "$set": {
+            "name": req.name,
+            "target": bson::to_bson(&req.target)?,
+        } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let record = self
+            .channels
+            .find_one_and_update(doc! { "_id": oid }, update)
+            .with_options(options)
+            .await?;
+        Ok(record.map(utils::convert_notification_channel_record_to_notification_channel))
+    }
+
+    /// Rules that still point at a deleted channel just skip it.
+    pub async fn delete_channel(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = self.channels.delete_one(doc! { "_id": oid }).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Sends a test notification to the channel. `Ok(None)` when it doesn't
+    /// exist, `Ok(Some(Err(..)))` when delivery failed.
+    pub async fn test_channel(
+        &self,
+        id: &str,
+    ) -> Result<Option<Result<(), String>>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let Some(channel) = self.channels.find_one(doc! { "_id": oid }).await? else {
+            return Ok(None);
+        };
+        let notification = Notification {
+            source: "alert".to_string(),
+            status: NotificationStatus::Firing,
+            title: format!("Test notification for {}", channel.name),
+            message: "If you can read this, the channel works.".to_string(),
+            labels: BTreeMap::new(),
+            alerts: Vec::new(),
+            timestamp: utils::get_timestamp(),
+        };
+        Ok(Some(
+            self.notifier.send(&channel.target, &notification).await,
+        ))
+    }
+
+    pub async fn create_silence(
+        &self,
+        req: SilenceRequest,
+    ) -> Result<Silence, mongodb::error::Error> {
+        let mut record = SilenceRecord {
+            id: None,
+            matchers: req.matchers,
+            starts_at: utils::convert_timestamp_chrono_to_bson(req.starts_at),
+            ends_at: utils::convert_timestamp_chrono_to_bson(req.ends_at),
+            comment: req.comment,
+            created_by: req.created_by,
+            created_at: utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        };
+        let result = self.silences.insert_one(&record).await?;
+        record.id = result.inserted_id.as_object_id();
+        Ok(utils::convert_silence_record_to_silence(record))
+    }
+
+    pub async fn list_silences(&self, active: bool) -> Result<Vec<Silence>, mongodb::error::Error> {
+        let filter = if active {
+            doc! { "ends_at": { "$gt": BsonDateTime::now() } }
+        } else {
+            doc! {}
+        };
+        let find_options = FindOptions::builder().sort(doc! { "ends_at": -1 }).build();
+        let records: Vec<SilenceRecord> = self
+            .silences
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_silence_record_to_silence)
+            .collect())
+    }
+
+    /// Deleting a silence ends it; alerts it muted notify on the next
+    /// evaluation if they are still firing.
+    pub async fn delete_silence(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = self.silences.delete_one(doc! { "_id": oid }).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Counts the rule's hogs in `[now - window, now]` of `hog_timestamp`, per
+    /// `group_by` values. Keys are the rendered group labels.
+    async fn count_groups(
+        &self,
+        rule: &AlertRuleRecord,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<BTreeMap<String, (BTreeMap<String, String>, i64)>, mongodb::error::Error> {
+        let mut options = match validate_options(rule.options.clone()).await {
+            Ok(options) => options,
+            Err(validation_error) => {
+                eprintln!(
+                    "Alert rule \"{}\" has invalid options: {:?}",
+                    rule.name, validation_error.errors
+                );
+                return Ok(BTreeMap::new());
+            }
+        };
+        // Rules stored before `window` was capped can still reach past it.
+        let Some(start) = chrono::Duration::try_seconds(rule.window_secs)
+            .and_then(|window| now.checked_sub_signed(window))
+        else {
+            eprintln!(
+                "Alert rule \"{}\" has a window that is out of range: {}s",
+                rule.name, rule.window_secs
+            );
+            return Ok(BTreeMap::new());
+        };
+        options.hog_timestamp_start = Some(start);
+        options.hog_timestamp_end = Some(now);
+
+        let mut pipeline = match options.log_data_value {
+            Some(Some(ref log_data_value)) if options.log_data_field.is_none() => {
+                build_log_data_value_aggregation_pipeline(log_data_value, &options)
+            }
+            _ => vec![doc! { "$match": options::build_filter(&options) }],
+        };
+        let mut group_id = Document::new();
+        for (i, field) in rule.group_by.iter().enumerate() {
+            group_id.insert(format!("g{}", i), format!("${}", field));
+        }
+        pipeline.push(doc! { "$group": { "_id": group_id, "count": { "$sum": 1 } } });
+
+        let aggregate_options = AggregateOptions::builder().max_time(self.max_time).build();
+        let mut cursor = self
+            .hogs
+            .aggregate(pipeline)
+            .with_options(aggregate_options)
+            .await?;
+        let mut counts = BTreeMap::new();
+        while let Some(doc) = cursor.try_next().await? {
+            let id = doc.get_document("_id").cloned().unwrap_or_default();
+            let labels: BTreeMap<String, String> = rule
+                .group_by
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    (
+                        field.clone(),
+                        id.get(format!("g{}", i))
+                            .map(label_value)
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect();
+            let count = doc
+                .get_i32("count")
+                .map(i64::from)
+                .or_else(|_| doc.get_i64("count"))
+                .unwrap_or(0);
+            counts.insert(group_key(&labels), (labels, count));
+        }
+        Ok(counts)
+    }
+}
+
+fn notification_alert(alert: &AlertRecord) -> NotificationAlert {
+    NotificationAlert {
+        labels: alert.labels.clone(),
+        value: alert.value,
+        since: alert.active_since.to_chrono(),
+    }
+}
+
+fn alert_notification(
+    rule: &AlertRuleRecord,
+    status: NotificationStatus,
+    alerts: Vec<NotificationAlert>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Notification {
+    let condition = match rule.condition {
+        Some(condition) => format!(
+            "{} {} in the last {}s",
+            condition.op.as_str(),
+            condition.threshold,
+            rule.window_secs
+        ),
+        None => String::new(),
+    };
+    let (title, message) = match status {
+        NotificationStatus::Firing => (
+            format!("{} is firing ({} alerts)", rule.name, alerts.len()),
+            format!("Hog count is {}", condition),
+        ),
+        NotificationStatus::Resolved => (
+            format!("{} resolved ({} alerts)", rule.name, alerts.len()),
+            format!("Hog count is no longer {}", condition),
+        ),
+    };
+    let mut labels = rule.labels.clone();
+    labels.insert("alertname".to_string(), rule.name.clone());
+
+    Notification {
+        source: "alert".to_string(),
+        status,
+        title,
+        message: match rule.description {
+            Some(ref description) => format!("{}\n{}", message, description),
+            None => message,
+        },
+        labels,
+        alerts,
+        timestamp: now,
+    }
+}
+
+fn label_value(value: &Bson) -> String {
+    match value {
+        Bson::String(s) => s.clone(),
+        Bson::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn group_key(labels: &BTreeMap<String, String>) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Labels silences match against: the rule's own labels, `alertname`,
+/// `rule_id` and the group's values.
+fn alert_labels(
+    rule: &AlertRuleRecord,
+    rule_id: &str,
+    group_labels: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut labels = rule.labels.clone();
+    labels.insert("alertname".to_string(), rule.name.clone());
+    labels.insert("rule_id".to_string(), rule_id.to_string());
+    labels.extend(group_labels);
+    labels
+}
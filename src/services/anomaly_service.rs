@@ -0,0 +1,375 @@
+use crate::config;
+use crate::metrics::{
+    HOG_ANOMALIES_TOTAL, HOG_VOLUME_ANOMALOUS, HOG_VOLUME_EXPECTED, HOG_VOLUME_OBSERVED,
+};
+use crate::models::anomaly::{
+    Anomaly, AnomalyKind, AnomalyQuery, AnomalyRecord, AnomalySettings, VolumeBaseline,
+    VolumeBaselineRecord,
+};
+use crate::utils::utils;
+use bson::DateTime as BsonDateTime;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::options::{FindOptions, ReplaceOptions};
+use mongodb::{
+    bson::{doc, Document},
+    Collection, Database,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const ANOMALY_INTERVAL_SECS: i64 = 60;
+const ANOMALY_SETTLE_SECS: i64 = 30;
+const ANOMALY_EWMA_ALPHA: f64 = 0.1;
+const ANOMALY_WARMUP_WINDOWS: i64 = 30;
+const ANOMALY_MIN_COUNT: f64 = 10.0;
+const ANOMALY_SPIKE_RATIO: f64 = 3.0;
+const ANOMALY_SPIKE_Z_SCORE: f64 = 3.0;
+const ANOMALY_DROP_RATIO: f64 = 0.1;
+const ANOMALY_EVICT_AFTER_WINDOWS: i64 = 1440;
+/// `log_source` × `log_level` pairs that get their own `hog_volume_*` series
+/// before the rest are summed into `other`.
+const ANOMALY_METRICS_MAX_SERIES: usize = 200;
+const OTHER_LABEL: &str = "other";
+
+type VolumeKey = (Option<String>, Option<String>);
+
+/// Counts hogs per `log_source` × `log_level` in fixed windows of
+/// `hog_timestamp`, keeps an exponentially weighted baseline for each pair and
+/// records windows that stray too far from it in the `anomaly` collection.
+pub struct AnomalyService {
+    hogs: Collection<Document>,
+    anomalies: Collection<AnomalyRecord>,
+    baselines: Collection<VolumeBaselineRecord>,
+    settings: AnomalySettings,
+    state: Mutex<HashMap<VolumeKey, VolumeBaselineRecord>>,
+    /// Pairs that have their own `hog_volume_*` series.
+    series: Mutex<HashSet<VolumeKey>>,
+}
+
+impl AnomalyService {
+    pub fn new(db: &Database) -> Self {
+        AnomalyService {
+            hogs: db.collection::<Document>("hog"),
+            anomalies: db.collection::<AnomalyRecord>("anomaly"),
+            baselines: db.collection::<VolumeBaselineRecord>("anomaly_baseline"),
+            settings: AnomalySettings {
+                interval_secs: config::env_or("ANOMALY_INTERVAL_SECS", ANOMALY_INTERVAL_SECS)
+                    .max(1),
+                settle_secs: config::env_or("ANOMALY_SETTLE_SECS", ANOMALY_SETTLE_SECS),
+                ewma_alpha: config::env_or("ANOMALY_EWMA_ALPHA", ANOMALY_EWMA_ALPHA),
+                warmup_windows: config::env_or("ANOMALY_WARMUP_WINDOWS", ANOMALY_WARMUP_WINDOWS),
+                min_count: config::env_or("ANOMALY_MIN_COUNT", ANOMALY_MIN_COUNT),
+                spike_ratio: config::env_or("ANOMALY_SPIKE_RATIO", ANOMALY_SPIKE_RATIO),
+                spike_z_score: config::env_or("ANOMALY_SPIKE_Z_SCORE", ANOMALY_SPIKE_Z_SCORE),
+                drop_ratio: config::env_or("ANOMALY_DROP_RATIO", ANOMALY_DROP_RATIO),
+                evict_after_windows: config::env_or(
+                    "ANOMALY_EVICT_AFTER_WINDOWS",
+                    ANOMALY_EVICT_AFTER_WINDOWS,
+                )
+                .max(1),
+                max_series: config::env_or(
+                    "ANOMALY_METRICS_MAX_SERIES",
+                    ANOMALY_METRICS_MAX_SERIES,
+                ),
+            },
+            state: Mutex::new(HashMap::new()),
+            series: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Starts the detector unless `ANOMALY_DETECTION_ENABLED` is false.
+    pub fn spawn(self: &Arc<Self>) {
+        if !config::env_or("ANOMALY_DETECTION_ENABLED", true) {
+            return;
+        }
+        let service = Arc::clone(self);
+        tokio::spawn(async move { service.run().await });
+    }
+
+    async fn run(&self) {
+        if let Err(err) = self.load_baselines().await {
+            eprintln!("Failed to load volume baselines: {:?}", err);
+        }
+
+        let interval = self.settings.interval_secs;
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval as u64));
+        let mut last_window_end = None;
+        loop {
+            ticker.tick().await;
+            let settled =
+                utils::get_timestamp().timestamp_millis() - self.settings.settle_secs * 1000;
+            let window_end = settled - settled.rem_euclid(interval * 1000);
+            if last_window_end == Some(window_end) {
+                continue;
+            }
+            let window_start = window_end - interval * 1000;
+            match self
+                .evaluate_window(
+                    BsonDateTime::from_millis(window_start),
+                    BsonDateTime::from_millis(window_end),
+                )
+                .await
+            {
+                Ok(()) => last_window_end = Some(window_end),
+                Err(err) => eprintln!("Failed to evaluate log volume: {:?}", err),
+            }
+        }
+    }
+
+    async fn load_baselines(&self) -> Result<(), mongodb::error::Error> {
+        let baselines: Vec<VolumeBaselineRecord> =
+            self.baselines.find(doc! {}).await?.try_collect().await?;
+        let mut state = self.state.lock().unwrap();
+        for baseline in baselines {
+            state.insert(
+                (baseline.log_source.clone(), baseline.log_level.clone()),
+                baseline,
+            );
+        }
+        Ok(())
+    }
+
+    async fn count_window(
+        &self,
+        window_start: BsonDateTime,
+        window_end: BsonDateTime,
+    ) -> Result<HashMap<VolumeKey, i64>, mongodb::error::Error> {
+        let pipeline = vec![
+            doc! { "$match": { "hog_timestamp": { "$gte": window_start, "$lt": window_end } } },
+            doc! { "$group": {
+                "_id": { "log_source": "$log_source", "log_level": "$log_level" },
+                "count": { "$sum": 1 },
+            } },
+        ];
+        let mut cursor = self.hogs.aggregate(pipeline).await?;
+        let mut counts = HashMap::new();
+        while let Some(doc) = cursor.try_next().await? {
+            let id = doc.get_document("_id").ok();
+            let field = |name: &str| id.and_then(|id| id.get_str(name).ok()).map(String::from);
+            let count = doc
+                .get_i32("count")
+                .map(i64::from)
+                .or_else(|_| doc.get_i64("count"))
+                .unwrap_or(0);
+            counts.insert((field("log_source"), field("log_level")), count);
+        }
+        Ok(counts)
+    }
+
+    async fn evaluate_window(
+        &self,
+        window_start: BsonDateTime,
+        window_end: BsonDateTime,
+    ) -> Result<(), mongodb::error::Error> {
+        let counts = self.count_window(window_start, window_end).await?;
+        let detected_at = BsonDateTime::now();
+        let mut anomalies = Vec::new();
+        let mut evicted = Vec::new();
+        let updated: Vec<VolumeBaselineRecord> = {
+            let mut state = self.state.lock().unwrap();
+            let mut series = self.series.lock().unwrap();
+            for key in counts.keys() {
+                state
+                    .entry(key.clone())
+                    .or_insert_with(|| VolumeBaselineRecord::new(key.0.clone(), key.1.clone()));
+            }
+
+            let mut other = VolumeGauges::default();
+            for (key, baseline) in state.iter_mut() {
+                // Pairs that logged nothing this window count as zero.
+                let count = counts.get(key).copied().unwrap_or(0);
+                let anomaly = baseline.classify(count, &self.settings);
+                if let Some((kind, score)) = anomaly {
+                    HOG_ANOMALIES_TOTAL
+                        .with_label_values(&[kind.as_str()])
+                        .inc();
+                    anomalies.push(AnomalyRecord {
+                        id: None,
+                        log_source: key.0.clone(),
+                        log_level: key.1.clone(),
+                        kind,
+                        count,
+                        expected: baseline.mean,
+                        stddev: baseline.stddev(),
+                        score,
+                        window_start,
+                        window_end,
+                        detected_at,
+                    });
+                }
+
+                baseline.observe(count, self.settings.ewma_alpha);
+                baseline.updated_at = detected_at;
+                if baseline.quiet_windows >= self.settings.evict_after_windows {
+                    evicted.push(key.clone());
+                    continue;
+                }
+
+                let gauges = VolumeGauges {
+                    pairs: 1,
+                    observed: count as f64,
+                    expected: baseline.mean,
+                    anomalous: anomaly.map(|(kind, _)| kind).into_iter().collect(),
+                };
+                if series.contains(key) || series.len() < self.settings.max_series {
+                    series.insert(key.clone());
+                    gauges.set(&series_labels(key));
+                } else {
+                    other.add(gauges);
+                }
+            }
+
+            for key in &evicted {
+                state.remove(key);
+                if series.remove(key) {
+                    VolumeGauges::remove(&series_labels(key));
+                }
+            }
+            if other.pairs > 0 {
+                other.set(&[OTHER_LABEL, OTHER_LABEL]);
+            } else {
+                VolumeGauges::remove(&[OTHER_LABEL, OTHER_LABEL]);
+            }
+            state.values().cloned().collect()
+        };
+
+        if !anomalies.is_empty() {
+            self.anomalies.insert_many(anomalies).await?;
+        }
+        for (log_source, log_level) in evicted {
+            self.baselines
+                .delete_one(doc! { "log_source": log_source, "log_level": log_level })
+                .await?;
+        }
+        let upsert = ReplaceOptions::builder().upsert(true).build();
+        for baseline in updated {
+            self.baselines
+                .replace_one(
+                    doc! { "log_source": &baseline.log_source, "log_level": &baseline.log_level },
+                    VolumeBaselineRecord {
+                        id: None,
+                        ..baseline
+                    },
+                )
+                .with_options(upsert.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_anomalies(
+        &self,
+        query: &AnomalyQuery,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<Anomaly>, mongodb::error::Error> {
+        let mut filter = volume_filter(&query.log_source, &query.log_level);
+        if let Some(kind) = query.kind {
+            filter.insert("kind", kind.as_str());
+        }
+        if let Some(since) = since {
+            filter.insert(
+                "detected_at",
+                doc! { "$gte": BsonDateTime::from_chrono(since) },
+            );
+        }
+        let find_options = FindOptions::builder()
+            .sort(doc! { "detected_at": -1 })
+            .limit(limit)
+            .build();
+        let records: Vec<AnomalyRecord> = self
+            .anomalies
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_anomaly_record_to_anomaly)
+            .collect())
+    }
+
+    pub async fn list_baselines(
+        &self,
+        query: &AnomalyQuery,
+    ) -> Result<Vec<VolumeBaseline>, mongodb::error::Error> {
+        let filter = volume_filter(&query.log_source, &query.log_level);
+        let find_options = FindOptions::builder()
+            .sort(doc! { "log_source": 1, "log_level": 1 })
+            .build();
+        let records: Vec<VolumeBaselineRecord> = self
+            .baselines
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_volume_baseline_record_to_volume_baseline)
+            .collect())
+    }
+}
+
+fn series_labels(key: &VolumeKey) -> [&str; 2] {
+    [
+        key.0.as_deref().unwrap_or_default(),
+        key.1.as_deref().unwrap_or_default(),
+    ]
+}
+
+/// One window's `hog_volume_*` values for a pair, or summed over the pairs
+/// that share the `other` series.
+#[derive(Default)]
+struct VolumeGauges {
+    pairs: usize,
+    observed: f64,
+    expected: f64,
+    anomalous: Vec<AnomalyKind>,
+}
+
+impl VolumeGauges {
+    fn add(&mut self, pair: VolumeGauges) {
+        self.pairs += pair.pairs;
+        self.observed += pair.observed;
+        self.expected += pair.expected;
+        self.anomalous.extend(pair.anomalous);
+    }
+
+    fn set(&self, labels: &[&str; 2]) {
+        for kind in AnomalyKind::ALL {
+            HOG_VOLUME_ANOMALOUS
+                .with_label_values(&[labels[0], labels[1], kind.as_str()])
+                .set(if self.anomalous.contains(&kind) { 1 } else { 0 });
+        }
+        HOG_VOLUME_OBSERVED
+            .with_label_values(labels)
+            .set(self.observed);
+        HOG_VOLUME_EXPECTED
+            .with_label_values(labels)
+            .set(self.expected);
+    }
+
+    fn remove(labels: &[&str; 2]) {
+        for kind in AnomalyKind::ALL {
+            let _ =
+                HOG_VOLUME_ANOMALOUS.remove_label_values(&[labels[0], labels[1], kind.as_str()]);
+        }
+        let _ = HOG_VOLUME_OBSERVED.remove_label_values(labels);
+        let _ = HOG_VOLUME_EXPECTED.remove_label_values(labels);
+    }
+}
+
+fn volume_filter(log_source: &Option<String>, log_level: &Option<String>) -> Document {
+    let mut filter = Document::new();
+    if let Some(log_source) = log_source {
+        filter.insert("log_source", log_source);
+    }
+    if let Some(log_level) = log_level {
+        filter.insert("log_level", log_level);
+    }
+    filter
+}
@@ -0,0 +1,323 @@
+use crate::config;
+use crate::models::heartbeat::{
+    HeartbeatRule, HeartbeatRuleRecord, HeartbeatRuleRequest, SourceState, SourceStatus,
+};
+use crate::models::hog_record::HogRecord;
+use crate::models::notification::{Notification, NotificationStatus};
+use crate::notifications::Notifier;
+use crate::utils::utils;
+use bson::DateTime as BsonDateTime;
+use futures::TryStreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, ReturnDocument};
+use mongodb::{
+    bson::{doc, Document},
+    Collection, Database,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HEARTBEAT_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Keeps track of sources that are expected to log regularly and notifies
+/// when one goes silent or comes back.
+pub struct HeartbeatService {
+    rules: Collection<HeartbeatRuleRecord>,
+    hogs: Collection<HogRecord>,
+    notifier: Arc<Notifier>,
+}
+
+impl HeartbeatService {
+    pub fn new(db: &Database, notifier: Arc<Notifier>) -> Self {
+        HeartbeatService {
+            rules: db.collection::<HeartbeatRuleRecord>("heartbeat_rule"),
+            hogs: db.collection::<HogRecord>("hog"),
+            notifier,
+        }
+    }
+
+    /// Starts the scheduler that checks every enabled rule each
+    /// `HEARTBEAT_CHECK_INTERVAL_SECS`.
+    pub fn spawn(self: &Arc<Self>) {
+        let interval = config::env_or(
+            "HEARTBEAT_CHECK_INTERVAL_SECS",
+            HEARTBEAT_CHECK_INTERVAL_SECS,
+        );
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+            loop {
+                ticker.tick().await;
+                if let Err(err) = service.check_rules().await {
+                    eprintln!("Failed to check heartbeat rules: {:?}", err);
+                }
+            }
+        });
+    }
+
+    async fn check_rules(&self) -> Result<(), mongodb::error::Error> {
+        let rules: Vec<HeartbeatRuleRecord> = self
+            .rules
+            .find(doc! { "enabled": true })
+            .await?
+            .try_collect()
+            .await?;
+        for rule in rules {
+            // One broken rule shouldn't keep the others from being checked.
+            let name = rule.name.clone();
+            if let Err(err) = self.check_rule(rule).await {
+                eprintln!("Failed to check heartbeat rule \"{}\": {:?}", name, err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_rule(&self, rule: HeartbeatRuleRecord) -> Result<(), mongodb::error::Error> {
+        let Some(id) = rule.id else {
+            return Ok(());
+        };
+        let now = utils::get_timestamp();
+        let last_seen = self.last_seen(&rule).await?;
+        let silent = match last_seen {
+            Some(last_seen) => (now - last_seen.to_chrono()).num_seconds() > rule.max_silence_secs,
+            None => true,
+        };
+        let state = if silent {
+            SourceState::Silent
+        } else {
+            SourceState::Ok
+        };
+
+        let silent_since = match (state, rule.state) {
+            (SourceState::Silent, SourceState::Silent) => rule.silent_since,
+            (SourceState::Silent, _) => Some(
+                last_seen
+                    .map(|last_seen| {
+                        utils::convert_timestamp_chrono_to_bson(
+                            last_seen.to_chrono()
+                                + chrono::Duration::seconds(rule.max_silence_secs),
+                        )
+                    })
+                    .unwrap_or_else(|| utils::convert_timestamp_chrono_to_bson(now)),
+            ),
+            _ => None,
+        };
+
+        // Matching on the previous state means only one API instance wins a
+        // transition, so only one of them notifies.
+        let updated = self
+            .rules
+            .update_one(
+                doc! { "_id": id, "state": bson::to_bson(&rule.state)? },
+                doc! { "$set": {
+                    "state": bson::to_bson(&state)?,
+                    "last_seen": last_seen,
+                    "silent_since": silent_since,
+                    "checked_at": utils::convert_timestamp_chrono_to_bson(now),
+                } },
+            )
+            .await?;
+
+        let notify = match (rule.state, state) {
+            (SourceState::Silent, SourceState::Ok) => Some(NotificationStatus::Resolved),
+            (SourceState::Ok | SourceState::Unknown, SourceState::Silent) => {
+                Some(NotificationStatus::Firing)
+            }
+            _ => None,
+        };
+        if let Some(status) = notify
+            && updated.modified_count > 0
+        {
+            self.notifier
+                .notify(
+                    &rule.notifications,
+                    &heartbeat_notification(&rule, status, last_seen),
+                )
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn last_seen(
+        &self,
+        rule: &HeartbeatRuleRecord,
+    ) -> Result<Option<BsonDateTime>, mongodb::error::Error> {
+        let mut filter = doc! { "log_source": &rule.log_source };
+        if let Some(ref log_source_id) = rule.log_source_id {
+            filter.insert("log_source_id", log_source_id);
+        }
+        let find_options = FindOneOptions::builder()
+            .sort(doc! { "hog_timestamp": -1 })
+            .projection(doc! { "hog_timestamp": 1 })
+            .build();
+        let newest = self
+            .hogs
+            .clone_with_type::<Document>()
+            .find_one(filter)
+            .with_options(find_options)
+            .await?;
+        Ok(newest.and_then(|doc| doc.get_datetime("hog_timestamp").ok().copied()))
+    }
+
+    pub async fn create_rule(
+        &self,
+        req: HeartbeatRuleRequest,
+    ) -> Result<HeartbeatRule, mongodb::error::Error> {
+        let now = utils::convert_timestamp_chrono_to_bson(utils::get_timestamp());
+        let mut record = HeartbeatRuleRecord {
+            id: None,
+            name: req.name,
+            log_source: req.log_source,
+            log_source_id: req.log_source_id,
+            max_silence_secs: req.max_silence_secs,
+            notifications: req.notifications,
+            enabled: req.enabled,
+            state: SourceState::Unknown,
+            last_seen: None,
+            silent_since: None,
+            checked_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let result = self.rules.insert_one(&record).await?;
+        record.id = result.inserted_id.as_object_id();
+        Ok(utils::convert_heartbeat_rule_record_to_heartbeat_rule(
+            record,
+        ))
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<HeartbeatRule>, mongodb::error::Error> {
+        Ok(self
+            .find_rules(doc! {})
+            .await?
+            .into_iter()
+            .map(utils::convert_heartbeat_rule_record_to_heartbeat_rule)
+            .collect())
+    }
+
+    pub async fn get_rule(&self, id: &str) -> Result<Option<HeartbeatRule>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let record = self.rules.find_one(doc! { "_id": oid }).await?;
+        Ok(record.map(utils::convert_heartbeat_rule_record_to_heartbeat_rule))
+    }
+
+    /// Replaces the rule's settings. Its state starts over as `unknown`.
+    pub async fn update_rule(
+        &self,
+        id: &str,
+        req: HeartbeatRuleRequest,
+    ) -> Result<Option<HeartbeatRule>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let update = doc! { "$set": {
+            "name": req.name,
+            "log_source": req.log_source,
+            "log_source_id": req.log_source_id,
+            "max_silence_secs": req.max_silence_secs,
+            "notifications": bson::to_bson(&req.notifications)?,
+            "enabled": req.enabled,
+            "state": bson::to_bson(&SourceState::Unknown)?,
+            "silent_since": null,
+            "updated_at": utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let record = self
+            .rules
+            .find_one_and_update(doc! { "_id": oid }, update)
+            .with_options(options)
+            .await?;
+        Ok(record.map(utils::convert_heartbeat_rule_record_to_heartbeat_rule))
+    }
+
+    pub async fn delete_rule(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = self.rules.delete_one(doc! { "_id": oid }).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Last known state of every enabled rule, silent sources first.
+    pub async fn source_statuses(
+        &self,
+        state: Option<SourceState>,
+    ) -> Result<Vec<SourceStatus>, mongodb::error::Error> {
+        let mut filter = doc! { "enabled": true };
+        if let Some(state) = state {
+            filter.insert("state", bson::to_bson(&state)?);
+        }
+        let mut statuses: Vec<SourceStatus> = self
+            .find_rules(filter)
+            .await?
+            .into_iter()
+            .map(utils::convert_heartbeat_rule_record_to_source_status)
+            .collect();
+        statuses.sort_by_key(|status| status.state != SourceState::Silent);
+        Ok(statuses)
+    }
+
+    async fn find_rules(
+        &self,
+        filter: Document,
+    ) -> Result<Vec<HeartbeatRuleRecord>, mongodb::error::Error> {
+        let find_options = FindOptions::builder()
+            .sort(doc! { "log_source": 1, "log_source_id": 1, "name": 1 })
+            .build();
+        self.rules
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await
+    }
+}
+
+fn heartbeat_notification(
+    rule: &HeartbeatRuleRecord,
+    status: NotificationStatus,
+    last_seen: Option<BsonDateTime>,
+) -> Notification {
+    let source = match rule.log_source_id {
+        Some(ref log_source_id) => format!("{}/{}", rule.log_source, log_source_id),
+        None => rule.log_source.clone(),
+    };
+    let last_seen = last_seen
+        .map(utils::convert_timestamp_bson_to_string)
+        .unwrap_or_else(|| "never".to_string());
+    let (title, message) = match status {
+        NotificationStatus::Firing => (
+            format!("{} went silent", source),
+            format!(
+                "No hogs from {} for more than {}s (last seen: {})",
+                source, rule.max_silence_secs, last_seen
+            ),
+        ),
+        NotificationStatus::Resolved => (
+            format!("{} is logging again", source),
+            format!("{} logged again at {}", source, last_seen),
+        ),
+    };
+
+    let mut labels = BTreeMap::new();
+    labels.insert("rule".to_string(), rule.name.clone());
+    labels.insert("log_source".to_string(), rule.log_source.clone());
+    if let Some(ref log_source_id) = rule.log_source_id {
+        labels.insert("log_source_id".to_string(), log_source_id.clone());
+    }
+
+    Notification {
+        source: "heartbeat".to_string(),
+        status,
+        title,
+        message,
+        labels,
+        alerts: Vec::new(),
+        timestamp: utils::get_timestamp(),
+    }
+}
@@ -1,38 +1,132 @@
-use crate::errors::SomeCreateError;
+use crate::config;
+use crate::errors::{SearchError, SomeCreateError};
+use crate::metrics::{
+    HOGS_EXPORTED_TOTAL, HOGS_REDACTED_TOTAL, HOGS_SAMPLED_OUT_TOTAL, HOGS_TAIL_DROPPED_TOTAL,
+};
+use crate::models::annotations::{AnnotationsRequest, HogCommentRecord};
+use crate::models::context::HogContext;
+use crate::models::encryption::FieldCipher;
+use crate::models::export::ExportFormat;
+use crate::models::guardrails::{GuardrailReport, SearchExplain, SearchGuardrails};
 use crate::models::hog::Hog;
 use crate::models::hog_client_schema::HogRequest;
 use crate::models::hog_record::HogRecord;
+use crate::models::lag::SourceLag;
+use crate::models::matcher::HogMatcher;
+use crate::models::multiline::MultilineAggregator;
 use crate::models::options::{self, build_log_data_value_aggregation_pipeline, OptionsRequest};
+use crate::models::redaction::Redactor;
+use crate::models::sampling::{Sampler, SamplingDecision};
 use crate::models::statistics::HogStatistics;
-use crate::utils::utils;
-use futures::{StreamExt, TryStreamExt};
+use crate::models::tail::TailEvent;
+use crate::models::timestamp::TimestampParser;
+use crate::utils::{export, utils};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use lapin::{BasicProperties, Channel};
-use mongodb::options::FindOptions;
-use mongodb::{bson::doc, Collection, Database};
+use mongodb::bson::oid::ObjectId;
+use mongodb::options::{AggregateOptions, FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    Collection, Database,
+};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
 
 use uuid::Uuid;
 
+const TAIL_BUFFER_SIZE: usize = 1024;
+const TAIL_MAX_EVENTS_PER_SECOND: u32 = 100;
+const TAIL_DROP_NOTICE_INTERVAL_SECS: u64 = 1;
+const SEARCH_MAX_UNINDEXED_RANGE_SECS: i64 = 7 * 24 * 60 * 60;
+const SEARCH_MAX_TIME_MS: u64 = 30_000;
+const SEARCH_MAX_SCANNED_DOCS: u64 = 5_000_000;
+const EXPORT_CSV_SAMPLE_ROWS: usize = 1000;
+
 pub struct HogService {
     db: Database,
     collection: Collection<HogRecord>,
     rabbit_channel: Channel,
+    tail_sender: broadcast::Sender<HogRecord>,
+    tail_quota: Quota,
+    guardrails: SearchGuardrails,
+    redactor: Option<Redactor>,
+    cipher: Arc<FieldCipher>,
+    sampler: Option<Sampler>,
+    multiline: Option<MultilineAggregator>,
+    timestamps: TimestampParser,
 }
 
 impl HogService {
     pub fn new(db: &Database, rabbit_channel: Channel) -> Self {
         let collection = db.collection::<HogRecord>("hog");
+        let (tail_sender, _) =
+            broadcast::channel(config::env_or("TAIL_BUFFER_SIZE", TAIL_BUFFER_SIZE).max(1));
+        let tail_rate = config::env_or("TAIL_MAX_EVENTS_PER_SECOND", TAIL_MAX_EVENTS_PER_SECOND);
+        let tail_quota = Quota::per_second(
+            NonZeroU32::new(tail_rate)
+                .unwrap_or(NonZeroU32::new(TAIL_MAX_EVENTS_PER_SECOND).unwrap()),
+        );
+        let redactor =
+            Redactor::from_env().unwrap_or_else(|e| panic!("Failed to load redaction rules {}", e));
+        let cipher = FieldCipher::from_env()
+            .unwrap_or_else(|e| panic!("Failed to load field encryption keys: {}", e));
+        let sampler = std::env::var("HOG_SAMPLING_FILE").ok().map(|path| {
+            Sampler::load(&path).unwrap_or_else(|e| panic!("Failed to load sampling rules {}", e))
+        });
+        let multiline = std::env::var("HOG_MULTILINE_FILE").ok().map(|path| {
+            MultilineAggregator::load(&path)
+                .unwrap_or_else(|e| panic!("Failed to load multiline rules {}", e))
+        });
+        let timestamps = match std::env::var("HOG_TIMESTAMP_FILE") {
+            Ok(path) => TimestampParser::load(&path)
+                .unwrap_or_else(|e| panic!("Failed to load timestamp formats {}", e)),
+            Err(_) => TimestampParser::from_config(serde_json::json!({}))
+                .expect("default timestamp formats are valid"),
+        };
         HogService {
             db: db.clone(),
             collection,
             rabbit_channel,
+            tail_sender,
+            tail_quota,
+            guardrails: SearchGuardrails {
+                max_unindexed_range_secs: config::env_or(
+                    "SEARCH_MAX_UNINDEXED_RANGE_SECS",
+                    SEARCH_MAX_UNINDEXED_RANGE_SECS,
+                ),
+                max_time_ms: config::env_or("SEARCH_MAX_TIME_MS", SEARCH_MAX_TIME_MS),
+                max_scanned_docs: config::env_or(
+                    "SEARCH_MAX_SCANNED_DOCS",
+                    SEARCH_MAX_SCANNED_DOCS,
+                ),
+            },
+            redactor,
+            cipher: Arc::new(cipher),
+            sampler,
+            multiline,
+            timestamps,
         }
     }
 
-    pub async fn create_hog(&self, req: HogRequest) -> Result<Hog, SomeCreateError> {
+    /// How `log_timestamp` is read on ingest.
+    pub fn timestamps(&self) -> &TimestampParser {
+        &self.timestamps
+    }
+
+    /// Publishes a hog for the workers to store. `None` when a sampling rule
+    /// dropped it or a multiline rule is holding it to merge with the lines
+    /// after it.
+    pub async fn create_hog(&self, req: HogRequest) -> Result<Option<Hog>, SomeCreateError> {
         let uuid = Uuid::new_v4();
 
         let hog_record = HogRecord {
-            log_timestamp: utils::rfc3339_str_to_bson(&req.log_timestamp).unwrap(),
+            log_timestamp: utils::convert_timestamp_chrono_to_bson(req.log_timestamp),
             log_level: req.log_level,
             log_message: req.log_message,
             log_data: req.log_data,
@@ -44,9 +138,60 @@ impl HogService {
                 utils::get_timestamp(),
             )),
             created_at: None,
+            annotations: None,
+            pattern_id: None,
+            sample_rate: None,
+            repeat_count: None,
+            first_seen_at: None,
+            last_seen_at: None,
+            clock_skew: None,
+            content_hash: None,
             id: None,
         };
 
+        let Some(ref multiline) = self.multiline else {
+            return self.publish(hog_record).await;
+        };
+        let outcome = multiline.push(hog_record, utils::get_timestamp().timestamp_millis());
+        // The events this line completed belong to earlier requests; failing
+        // to publish one shouldn't fail this one.
+        for event in outcome.flushed {
+            if let Err(err) = self.publish(event.clone()).await {
+                eprintln!("Failed to publish multiline event: {:?}", err);
+                multiline.requeue(event);
+            }
+        }
+        match outcome.current {
+            Some(hog_record) => self.publish(hog_record).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Samples, redacts and publishes one event. `None` when a sampling rule
+    /// dropped it.
+    async fn publish(&self, mut hog_record: HogRecord) -> Result<Option<Hog>, SomeCreateError> {
+        let uuid = hog_record
+            .hog_uuid
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .unwrap_or_else(Uuid::new_v4);
+        if let Some(ref sampler) = self.sampler
+            && let SamplingDecision::Drop { rule, reason } =
+                sampler.decide(&mut hog_record, sample_roll(&uuid))
+        {
+            HOGS_SAMPLED_OUT_TOTAL
+                .with_label_values(&[&rule, reason.as_str()])
+                .inc();
+            return Ok(None);
+        }
+
+        // Secrets never reach the queue, Mongo or a live tail.
+        if let Some(ref redactor) = self.redactor {
+            for rule in redactor.redact(&mut hog_record) {
+                HOGS_REDACTED_TOTAL.with_label_values(&[&rule]).inc();
+            }
+        }
+
         let payload =
             serde_json::to_vec(&hog_record).map_err(|e| SomeCreateError::new(e.to_string()))?;
 
@@ -63,9 +208,62 @@ impl HogService {
             .await
             .map_err(|e| SomeCreateError::new(e.to_string()))?;
 
+        // Nobody tailing is not an error.
+        let _ = self.tail_sender.send(hog_record.clone());
+
         let hog = utils::convert_hog_record_to_hog(&hog_record);
 
-        Ok(hog)
+        Ok(Some(hog))
+    }
+
+    /// Publishes multiline events once nothing was appended to them for
+    /// their rule's `flush_after_ms`.
+    pub fn spawn(self: &Arc<Self>) {
+        let Some(ref multiline) = self.multiline else {
+            return;
+        };
+        let interval = Duration::from_millis(multiline.check_interval_ms());
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(ref multiline) = service.multiline else {
+                    return;
+                };
+                for event in multiline.flush_expired(utils::get_timestamp().timestamp_millis()) {
+                    if let Err(err) = service.publish(event.clone()).await {
+                        eprintln!("Failed to publish multiline event: {:?}", err);
+                        multiline.requeue(event);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Publishes every multiline event still open, once the server has
+    /// stopped taking requests.
+    pub async fn flush_multiline(&self) {
+        let Some(ref multiline) = self.multiline else {
+            return;
+        };
+        for event in multiline.flush_all() {
+            if let Err(err) = self.publish(event).await {
+                eprintln!("Failed to publish multiline event on shutdown: {:?}", err);
+            }
+        }
+    }
+
+    /// Whether `authorization` carries one of the `HOG_DECRYPT_TOKENS`.
+    pub fn can_decrypt(&self, authorization: Option<&str>) -> bool {
+        self.cipher.can_decrypt(authorization)
+    }
+
+    /// Decrypts the encrypted `log_data` fields of `hog`, or masks them when
+    /// the caller isn't allowed to read them.
+    pub fn reveal(&self, hog: &mut Hog, decrypt: bool) {
+        self.cipher
+            .reveal(&mut hog.client_request.log_data, decrypt);
     }
 
     pub async fn get_hogs(&self) -> Result<Vec<Hog>, mongodb::error::Error> {
@@ -86,7 +284,7 @@ impl HogService {
         while let Some(result) = cursor.next().await {
             match result {
                 Ok(hog_record) => hog_records.push(hog_record),
-                Err(e) => return Err(e.into()),
+                Err(e) => return Err(e),
             }
         }
 
@@ -95,18 +293,20 @@ impl HogService {
         Ok(hogs)
     }
 
-    pub async fn search_hogs(
-        &self,
-        options: OptionsRequest,
-    ) -> Result<Vec<Hog>, mongodb::error::Error> {
+    pub async fn search_hogs(&self, options: OptionsRequest) -> Result<Vec<Hog>, SearchError> {
+        let report = self.evaluate_guardrails(&options).await?;
+        if !report.allowed {
+            return Err(SearchError::Rejected {
+                field: report.field.unwrap_or_else(|| "search".to_string()),
+                message: report.reason.unwrap_or_default(),
+            });
+        }
+        let max_time = self.max_time();
+
         let need_aggregation = options.log_data_value.is_some() && options.log_data_field.is_none();
 
-        if need_aggregation && options.log_data_value.is_some() {
+        if need_aggregation && let Some(log_data_value) = options.log_data_value.as_ref() {
             // Use aggregation pipeline
-            let log_data_value = options
-                .log_data_value
-                .as_ref()
-                .expect("log_data_value must be Some");
             let pipeline = build_log_data_value_aggregation_pipeline(
                 log_data_value
                     .as_ref()
@@ -114,7 +314,12 @@ impl HogService {
                 &options,
             );
 
-            let mut cursor = self.collection.aggregate(pipeline).await?;
+            let aggregate_options = AggregateOptions::builder().max_time(max_time).build();
+            let mut cursor = self
+                .collection
+                .aggregate(pipeline)
+                .with_options(aggregate_options)
+                .await?;
 
             let mut hog_records: Vec<HogRecord> = Vec::new();
 
@@ -125,7 +330,7 @@ impl HogService {
 
             let hogs = utils::convert_hog_records_to_hogs(hog_records);
 
-            return Ok(hogs);
+            Ok(hogs)
         } else {
             let filter = options::build_filter(&options.clone());
             let limit = options.hog_limit.unwrap_or(1000);
@@ -133,6 +338,7 @@ impl HogService {
             let find_options = FindOptions::builder()
                 .limit(limit)
                 .sort(doc! { "_id": -1 })
+                .max_time(max_time)
                 .build();
 
             let mut cursor = self
@@ -156,6 +362,514 @@ impl HogService {
         }
     }
 
+    fn max_time(&self) -> Option<Duration> {
+        (self.guardrails.max_time_ms > 0)
+            .then(|| Duration::from_millis(self.guardrails.max_time_ms))
+    }
+
+    /// Decides whether `search_hogs` may run `options`. Searches that only hit
+    /// indexed fields always pass; anything that makes Mongo look at documents
+    /// (regex, `log_data` matching, the `$objectToArray` aggregation, ...) must
+    /// stay inside the configured time range, and those without one inside the
+    /// scan estimate.
+    pub async fn evaluate_guardrails(
+        &self,
+        options: &OptionsRequest,
+    ) -> Result<GuardrailReport, mongodb::error::Error> {
+        let limits = self.guardrails.clone();
+        let reject = |field: &str, reason: String, estimate: Option<u64>| GuardrailReport {
+            allowed: false,
+            field: Some(field.to_string()),
+            reason: Some(reason),
+            estimated_scanned_docs: estimate,
+            limits: limits.clone(),
+        };
+
+        let needs_scan = options.log_level.is_some()
+            || options.log_source_id.is_some()
+            || options.log_message.is_some()
+            || options.log_data.is_some()
+            || options.log_data_field.is_some()
+            || options.log_data_value.is_some()
+            || matches!(options.log_data_values, Some(Some(_)));
+        if !needs_scan {
+            return Ok(GuardrailReport {
+                allowed: true,
+                field: None,
+                reason: None,
+                estimated_scanned_docs: None,
+                limits,
+            });
+        }
+
+        let has_indexed_field = options.hog_uuid.is_some()
+            || options.pattern_id.is_some()
+            || options.log_type.is_some()
+            || options.log_source.is_some();
+        if !has_indexed_field && limits.max_unindexed_range_secs > 0 {
+            let max = limits.max_unindexed_range_secs;
+            match time_range_secs(options) {
+                None => {
+                    return Ok(reject(
+                        "log_timestamp_start",
+                        format!(
+                            "Searches on unindexed fields need one of hog_uuid, pattern_id, log_type, log_source or a log_timestamp/hog_timestamp range of at most {}s",
+                            max
+                        ),
+                        None,
+                    ));
+                }
+                Some(range) if range > max => {
+                    return Ok(reject(
+                        "log_timestamp_start",
+                        format!(
+                            "Time range of {}s exceeds the {}s allowed without hog_uuid, pattern_id, log_type or log_source",
+                            range, max
+                        ),
+                        None,
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Counting costs about as much as the search itself, so only searches
+        // whose index bounds aren't already narrowed to an allowed time range
+        // pay for it.
+        let range_bounded = limits.max_unindexed_range_secs > 0
+            && time_range_secs(options)
+                .is_some_and(|range| range <= limits.max_unindexed_range_secs);
+        let mut estimate = None;
+        if limits.max_scanned_docs > 0 && !range_bounded {
+            let bounds = index_bounds(options);
+            let scanned = if bounds.is_empty() {
+                self.collection.estimated_document_count().await?
+            } else {
+                self.collection
+                    .count_documents(bounds)
+                    .limit(limits.max_scanned_docs + 1)
+                    .max_time(Duration::from_millis(limits.max_time_ms.max(1000)))
+                    .await?
+            };
+            estimate = Some(scanned);
+            if scanned > limits.max_scanned_docs {
+                return Ok(reject(
+                    "search",
+                    format!(
+                        "Search would examine more than {} documents (SEARCH_MAX_SCANNED_DOCS)",
+                        limits.max_scanned_docs
+                    ),
+                    estimate,
+                ));
+            }
+        }
+
+        Ok(GuardrailReport {
+            allowed: true,
+            field: None,
+            reason: None,
+            estimated_scanned_docs: estimate,
+            limits,
+        })
+    }
+
+    /// Asks Mongo for the query plan `search_hogs` would get, without running it.
+    pub async fn explain_search(
+        &self,
+        options: &OptionsRequest,
+    ) -> Result<SearchExplain, mongodb::error::Error> {
+        let (command, explain_command) = match (&options.log_data_value, &options.log_data_field) {
+            (Some(Some(log_data_value)), None) => (
+                "aggregate",
+                doc! {
+                    "aggregate": self.collection.name(),
+                    "pipeline": build_log_data_value_aggregation_pipeline(log_data_value, options),
+                    "cursor": {},
+                },
+            ),
+            _ => (
+                "find",
+                doc! {
+                    "find": self.collection.name(),
+                    "filter": options::build_filter(options),
+                    "sort": { "_id": -1 },
+                    "limit": options.hog_limit.unwrap_or(1000),
+                },
+            ),
+        };
+
+        let explain = self
+            .db
+            .run_command(doc! { "explain": explain_command, "verbosity": "queryPlanner" })
+            .await?;
+
+        let mut winning_plans = Vec::new();
+        find_winning_plans(&explain, &mut winning_plans);
+        let mut indexes_used = Vec::new();
+        let mut collection_scan = false;
+        if winning_plans.is_empty() {
+            walk_plan(&explain, &mut indexes_used, &mut collection_scan);
+        }
+        for plan in &winning_plans {
+            walk_plan(plan, &mut indexes_used, &mut collection_scan);
+        }
+
+        Ok(SearchExplain {
+            command: command.to_string(),
+            collection_scan,
+            indexes_used,
+            guardrails: self.evaluate_guardrails(options).await?,
+            winning_plan: winning_plans.into_iter().next(),
+            explain,
+        })
+    }
+
+    /// Same query as `search_hogs`, but handed back as a cursor-backed stream
+    /// instead of being collected. No limit is applied unless `hog_limit` is set.
+    pub async fn stream_hog_records(
+        &self,
+        options: &OptionsRequest,
+    ) -> Result<BoxStream<'static, Result<HogRecord, mongodb::error::Error>>, mongodb::error::Error>
+    {
+        if let (Some(Some(log_data_value)), None) =
+            (&options.log_data_value, &options.log_data_field)
+        {
+            let mut pipeline = build_log_data_value_aggregation_pipeline(log_data_value, options);
+            pipeline.push(doc! { "$sort": { "_id": -1 } });
+            if let Some(limit) = options.hog_limit {
+                pipeline.push(doc! { "$limit": limit });
+            }
+            let cursor = self.collection.aggregate(pipeline).await?;
+            return Ok(cursor
+                .map(|doc| doc.and_then(|doc| Ok(bson::from_document::<HogRecord>(doc)?)))
+                .boxed());
+        }
+
+        let find_options = FindOptions::builder()
+            .limit(options.hog_limit)
+            .sort(doc! { "_id": -1 })
+            .build();
+        let cursor = self
+            .collection
+            .find(options::build_filter(options))
+            .with_options(find_options)
+            .await?;
+        Ok(cursor.boxed())
+    }
+
+    /// Streams every hog matching `options` straight from the cursor, rendered
+    /// in `format`. CSV gets one column per entry in `log_data_columns`, or
+    /// per `log_data` field seen in the first `EXPORT_CSV_SAMPLE_ROWS` hogs
+    /// when there are none.
+    pub async fn export_hogs(
+        &self,
+        options: &OptionsRequest,
+        format: ExportFormat,
+        log_data_columns: Option<Vec<String>>,
+        decrypt: bool,
+    ) -> Result<BoxStream<'static, Result<Bytes, std::io::Error>>, mongodb::error::Error> {
+        let mut records = self.stream_hog_records(options).await?;
+        let cipher = Arc::clone(&self.cipher);
+
+        // The header goes out first, so the columns come from the rows at
+        // hand rather than a second pass over the whole result set.
+        let (log_data_columns, sampled) = match log_data_columns {
+            Some(columns) => (columns, Vec::new()),
+            None if format == ExportFormat::Csv => {
+                let sample_rows = config::env_or("EXPORT_CSV_SAMPLE_ROWS", EXPORT_CSV_SAMPLE_ROWS);
+                let sampled: Vec<_> = records.by_ref().take(sample_rows).collect().await;
+                let columns = export::log_data_columns(
+                    sampled
+                        .iter()
+                        .filter_map(|record| record.as_ref().ok())
+                        .filter_map(|record| record.log_data.as_ref()),
+                );
+                (columns, sampled)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        let records = stream::iter(sampled).chain(records);
+
+        let prefix = export::export_prefix(format, &log_data_columns);
+        let suffix = export::export_suffix(format);
+
+        let body = records.enumerate().map(move |(index, record)| {
+            let record = record.map_err(std::io::Error::other)?;
+            let mut hog = utils::convert_hog_record_to_hog(&record);
+            cipher.reveal(&mut hog.client_request.log_data, decrypt);
+            let chunk = export::export_hog(format, &hog, index, &log_data_columns)
+                .map_err(std::io::Error::other)?;
+            HOGS_EXPORTED_TOTAL.inc();
+            Ok(Bytes::from(chunk))
+        });
+
+        Ok(stream::once(async move { Ok(Bytes::from(prefix)) })
+            .chain(body)
+            .chain(stream::once(async move { Ok(Bytes::from(suffix)) }))
+            .boxed())
+    }
+
+    pub async fn get_hog(&self, id: &str) -> Result<Option<Hog>, mongodb::error::Error> {
+        let hog_record = self.collection.find_one(hog_id_filter(id)).await?;
+        Ok(hog_record.map(|record| utils::convert_hog_record_to_hog(&record)))
+    }
+
+    pub async fn delete_hog(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let result = self.collection.delete_one(hog_id_filter(id)).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Updates the operator annotations of a hog. The original log fields are
+    /// never touched.
+    pub async fn annotate_hog(
+        &self,
+        id: &str,
+        req: AnnotationsRequest,
+    ) -> Result<Option<Hog>, mongodb::error::Error> {
+        let filter = hog_id_filter(id);
+
+        // Only atomic operators, so concurrent requests can't undo each
+        // other. Mongo won't add to and pull from one path in one update, so
+        // a request that does both takes two.
+        let mut updates = Vec::new();
+        let mut update = Document::new();
+        match (req.tags, req.add_tags, req.remove_tags) {
+            (Some(mut tags), add_tags, remove_tags) => {
+                for tag in add_tags.unwrap_or_default() {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                if let Some(remove_tags) = remove_tags {
+                    tags.retain(|tag| !remove_tags.contains(tag));
+                }
+                update.insert("$set", doc! { "annotations.tags": tags });
+            }
+            (None, Some(add_tags), remove_tags) => {
+                update.insert(
+                    "$addToSet",
+                    doc! { "annotations.tags": { "$each": add_tags } },
+                );
+                if let Some(remove_tags) = remove_tags {
+                    updates.push(std::mem::take(&mut update));
+                    update.insert("$pull", doc! { "annotations.tags": { "$in": remove_tags } });
+                }
+            }
+            (None, None, Some(remove_tags)) => {
+                update.insert("$pull", doc! { "annotations.tags": { "$in": remove_tags } });
+            }
+            (None, None, None) => {}
+        }
+        if let Some(comment) = req.comment {
+            let comment = HogCommentRecord {
+                author: req.author,
+                comment,
+                created_at: utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+            };
+            update.insert(
+                "$push",
+                doc! { "annotations.comments": bson::to_bson(&comment)? },
+            );
+        }
+
+        for earlier in updates {
+            let result = self.collection.update_one(filter.clone(), earlier).await?;
+            if result.matched_count == 0 {
+                return Ok(None);
+            }
+        }
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let updated = self
+            .collection
+            .find_one_and_update(filter, update)
+            .with_options(options)
+            .await?;
+
+        Ok(updated.map(|record| utils::convert_hog_record_to_hog(&record)))
+    }
+
+    /// Loads the hog identified by `id` (ObjectId hex or `hog_uuid`) together
+    /// with up to `before` and `after` neighbouring records from the same
+    /// `log_source`/`log_source_id`, ordered by `log_timestamp` (ties broken by
+    /// `_id`).
+    pub async fn hog_context(
+        &self,
+        id: &str,
+        before: i64,
+        after: i64,
+    ) -> Result<Option<HogContext>, mongodb::error::Error> {
+        let Some(anchor) = self.collection.find_one(hog_id_filter(id)).await? else {
+            return Ok(None);
+        };
+        let hog_uuid = anchor.hog_uuid.clone();
+
+        let source_filter = doc! {
+            "log_source": &anchor.log_source,
+            "log_source_id": &anchor.log_source_id,
+        };
+        let timestamp = anchor.log_timestamp;
+
+        let neighbours = |operator: &str, direction: i32, limit: i64| {
+            let mut filter = source_filter.clone();
+            let tie_break = match anchor.id {
+                Some(id) => doc! { "log_timestamp": timestamp, "_id": { operator: id } },
+                None => doc! { "log_timestamp": timestamp, "hog_uuid": { "$ne": &hog_uuid } },
+            };
+            filter.insert(
+                "$or",
+                vec![doc! { "log_timestamp": { operator: timestamp } }, tie_break],
+            );
+            let find_options = FindOptions::builder()
+                .limit(limit)
+                .sort(doc! { "log_timestamp": direction, "_id": direction })
+                .build();
+            self.collection.find(filter).with_options(find_options)
+        };
+
+        let mut before_records: Vec<HogRecord> = if before > 0 {
+            neighbours("$lt", -1, before).await?.try_collect().await?
+        } else {
+            Vec::new()
+        };
+        before_records.reverse();
+
+        let after_records: Vec<HogRecord> = if after > 0 {
+            neighbours("$gt", 1, after).await?.try_collect().await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Some(HogContext {
+            anchor: utils::convert_hog_record_to_hog(&anchor),
+            before: utils::convert_hog_records_to_hogs(before_records),
+            after: utils::convert_hog_records_to_hogs(after_records),
+        }))
+    }
+
+    /// Streams hogs matching `options` as they are published by `create_hog`.
+    /// Each subscriber is rate limited; anything it can't keep up with is
+    /// counted and reported back as a `TailEvent::Dropped` notice.
+    pub fn tail_hogs(
+        &self,
+        options: OptionsRequest,
+        decrypt: bool,
+    ) -> Result<impl Stream<Item = TailEvent> + use<>, String> {
+        struct TailState {
+            receiver: broadcast::Receiver<HogRecord>,
+            cipher: Arc<FieldCipher>,
+            decrypt: bool,
+            matcher: HogMatcher,
+            limiter: DefaultDirectRateLimiter,
+            notice_interval: tokio::time::Interval,
+            dropped: u64,
+        }
+
+        let state = TailState {
+            receiver: self.tail_sender.subscribe(),
+            cipher: Arc::clone(&self.cipher),
+            decrypt,
+            matcher: HogMatcher::new(options)?,
+            limiter: RateLimiter::direct(self.tail_quota),
+            notice_interval: tokio::time::interval(Duration::from_secs(
+                TAIL_DROP_NOTICE_INTERVAL_SECS,
+            )),
+            dropped: 0,
+        };
+
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                tokio::select! {
+                    received = state.receiver.recv() => match received {
+                        Ok(hog_record) => {
+                            if !state.matcher.matches(&hog_record) {
+                                continue;
+                            }
+                            if state.limiter.check().is_err() {
+                                state.dropped += 1;
+                                HOGS_TAIL_DROPPED_TOTAL.inc();
+                                continue;
+                            }
+                            let mut hog = utils::convert_hog_record_to_hog(&hog_record);
+                            state.cipher.reveal(&mut hog.client_request.log_data, state.decrypt);
+                            return Some((TailEvent::Hog { hog: Box::new(hog) }, state));
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            state.dropped += skipped;
+                            HOGS_TAIL_DROPPED_TOTAL.inc_by(skipped);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    },
+                    _ = state.notice_interval.tick(), if state.dropped > 0 => {
+                        let dropped = std::mem::take(&mut state.dropped);
+                        return Some((TailEvent::Dropped { dropped }, state));
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Producer-to-API and API-to-storage lag per `log_source`, over the hogs
+    /// received since `since`.
+    pub async fn source_lag(
+        &self,
+        log_source: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<SourceLag>, mongodb::error::Error> {
+        let mut filter =
+            doc! { "hog_timestamp": { "$gte": utils::convert_timestamp_chrono_to_bson(since) } };
+        if let Some(log_source) = log_source {
+            filter.insert("log_source", log_source);
+        }
+        let skewed = |direction: &str| doc! { "$sum": { "$cond": [{ "$eq": ["$clock_skew", direction] }, 1, 0] } };
+        let secs = |field: &str| doc! { "$divide": [field, 1000] };
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$project": {
+                "log_source": 1,
+                "hog_timestamp": 1,
+                "clock_skew": 1,
+                "producer_lag_ms": { "$subtract": ["$hog_timestamp", "$log_timestamp"] },
+                "storage_lag_ms": { "$subtract": ["$created_at", "$hog_timestamp"] },
+            } },
+            doc! { "$group": {
+                "_id": "$log_source",
+                "hogs": { "$sum": 1 },
+                "producer_lag_avg_ms": { "$avg": "$producer_lag_ms" },
+                "producer_lag_max_ms": { "$max": "$producer_lag_ms" },
+                "storage_lag_avg_ms": { "$avg": "$storage_lag_ms" },
+                "storage_lag_max_ms": { "$max": "$storage_lag_ms" },
+                "clock_skew_future": skewed("future"),
+                "clock_skew_past": skewed("past"),
+                "last_received_at": { "$max": "$hog_timestamp" },
+            } },
+            doc! { "$sort": { "_id": 1 } },
+            doc! { "$project": {
+                "_id": 0,
+                "log_source": "$_id",
+                "hogs": 1,
+                "producer_lag_avg_secs": secs("$producer_lag_avg_ms"),
+                "producer_lag_max_secs": secs("$producer_lag_max_ms"),
+                "storage_lag_avg_secs": secs("$storage_lag_avg_ms"),
+                "storage_lag_max_secs": secs("$storage_lag_max_ms"),
+                "clock_skew_future": 1,
+                "clock_skew_past": 1,
+                "last_received_at": { "$dateToString": {
+                    "date": "$last_received_at",
+                    "format": "%Y-%m-%dT%H:%M:%S.%LZ",
+                } },
+            } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let mut lags = Vec::new();
+        while let Some(document) = cursor.try_next().await? {
+            lags.push(bson::from_document(document)?);
+        }
+        Ok(lags)
+    }
+
     pub async fn hog_stats(&self) -> Result<bson::Document, mongodb::error::Error> {
         let stats = self.db.run_command(doc! { "collStats": "hog" }).await?;
         Ok(stats)
@@ -189,3 +903,177 @@ impl HogService {
         })
     }
 }
+
+/// A number in `[0, 1)` taken from the random bits of a v4 uuid; the top two
+/// bits of the low half are the fixed variant.
+fn sample_roll(uuid: &Uuid) -> f64 {
+    const RANDOM_BITS: u64 = (1 << 62) - 1;
+    (uuid.as_u128() as u64 & RANDOM_BITS) as f64 / (RANDOM_BITS as f64 + 1.0)
+}
+
+/// The part of a search Mongo can answer from the `hog` indexes alone.
+fn index_bounds(options: &OptionsRequest) -> Document {
+    let mut bounds = Document::new();
+    if let Some(ref hog_uuid) = options.hog_uuid {
+        bounds.insert("hog_uuid", hog_uuid);
+    }
+    if let Some(ref pattern_id) = options.pattern_id {
+        bounds.insert("pattern_id", pattern_id);
+    }
+    if let Some(ref log_type) = options.log_type {
+        bounds.insert("log_type", log_type);
+    }
+    if let Some(ref log_source) = options.log_source {
+        bounds.insert("log_source", log_source);
+    }
+    let filter = options::build_filter(&OptionsRequest {
+        log_level: None,
+        log_message: None,
+        log_data: None,
+        log_data_field: None,
+        log_data_value: None,
+        log_data_values: None,
+        log_source_id: None,
+        ..options.clone()
+    });
+    for field in ["log_timestamp", "hog_timestamp"] {
+        if let Some(value) = filter.get(field) {
+            bounds.insert(field, value.clone());
+        }
+    }
+    bounds
+}
+
+/// Narrowest closed time range in the search, in seconds. `None` if neither
+/// timestamp is bounded on both ends.
+fn time_range_secs(options: &OptionsRequest) -> Option<i64> {
+    let range = |timestamp: Option<chrono::DateTime<chrono::Utc>>,
+                 start: Option<chrono::DateTime<chrono::Utc>>,
+                 end: Option<chrono::DateTime<chrono::Utc>>| {
+        match (timestamp, start, end) {
+            (_, Some(start), Some(end)) => Some((end - start).num_seconds().max(0)),
+            (Some(_), None, None) => Some(0),
+            _ => None,
+        }
+    };
+    let log_range = range(
+        options.log_timestamp,
+        options.log_timestamp_start,
+        options.log_timestamp_end,
+    );
+    let hog_range = match options.hog_timestamp {
+        Some(_) => Some(0),
+        None => range(None, options.hog_timestamp_start, options.hog_timestamp_end),
+    };
+    match (log_range, hog_range) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn find_winning_plans(doc: &Document, plans: &mut Vec<Document>) {
+    for (key, value) in doc {
+        match value {
+            Bson::Document(inner) if key == "winningPlan" => plans.push(inner.clone()),
+            Bson::Document(inner) => find_winning_plans(inner, plans),
+            Bson::Array(items) => {
+                for item in items {
+                    if let Bson::Document(inner) = item {
+                        find_winning_plans(inner, plans);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn walk_plan(doc: &Document, indexes_used: &mut Vec<String>, collection_scan: &mut bool) {
+    for (key, value) in doc {
+        match value {
+            Bson::String(index) if key == "indexName" && !indexes_used.contains(index) => {
+                indexes_used.push(index.clone());
+            }
+            Bson::String(stage) if key == "stage" && stage == "COLLSCAN" => *collection_scan = true,
+            Bson::Document(inner) => walk_plan(inner, indexes_used, collection_scan),
+            Bson::Array(items) => {
+                for item in items {
+                    if let Bson::Document(inner) = item {
+                        walk_plan(inner, indexes_used, collection_scan);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Hogs are addressable by either their Mongo ObjectId hex or their `hog_uuid`.
+/// A repeat folded into an earlier hog resolves to that hog.
+fn hog_id_filter(id: &str) -> Document {
+    match ObjectId::parse_str(id) {
+        Ok(oid) => doc! { "_id": oid },
+        Err(_) => doc! { "$or": [{ "hog_uuid": id }, { "repeat_hog_uuids": id }] },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::dedup::Deduplicator;
+    use mongodb::bson::Bson;
+
+    /// Just enough of Mongo's matching for `hog_id_filter`: `$or`, and
+    /// equality that also matches an element of an array field.
+    fn filter_matches(filter: &Document, document: &Document) -> bool {
+        filter
+            .iter()
+            .all(|(key, expected)| match (key.as_str(), expected) {
+                ("$or", Bson::Array(branches)) => branches.iter().any(|branch| {
+                    branch
+                        .as_document()
+                        .is_some_and(|branch| filter_matches(branch, document))
+                }),
+                (field, expected) => match document.get(field) {
+                    Some(Bson::Array(values)) => values.contains(expected),
+                    Some(value) => value == expected,
+                    None => false,
+                },
+            })
+    }
+
+    fn hog(uuid: &str) -> HogRecord {
+        bson::from_document(doc! {
+            "log_timestamp": bson::DateTime::from_millis(1_747_582_114_549),
+            "log_message": "payment provider timeout",
+            "log_source": "checkout",
+            "hog_uuid": uuid,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn folded_repeats_resolve_to_the_surviving_hog() {
+        let dedup = Deduplicator::new(300);
+        let folded = dedup.fold(&[hog("first"), hog("second"), hog("third")]);
+        assert_eq!(folded.len(), 1);
+
+        // What `Deduplicator::store` leaves in Mongo for the batch.
+        let (survivor, uuids) = folded.into_iter().next().unwrap();
+        let mut stored = bson::to_document(&survivor).unwrap();
+        stored.insert("repeat_hog_uuids", uuids);
+        let other = bson::to_document(&hog("unrelated")).unwrap();
+
+        for uuid in ["first", "second", "third"] {
+            assert!(filter_matches(&hog_id_filter(uuid), &stored), "{}", uuid);
+            assert!(!filter_matches(&hog_id_filter(uuid), &other), "{}", uuid);
+        }
+        assert!(!filter_matches(&hog_id_filter("missing"), &stored));
+    }
+
+    #[test]
+    fn object_ids_are_matched_by_id() {
+        let oid = ObjectId::new();
+        assert_eq!(hog_id_filter(&oid.to_hex()), doc! { "_id": oid });
+    }
+}
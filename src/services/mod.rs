@@ -1 +1,7 @@
-pub mod hog_service;
\ No newline at end of file
+pub mod alert_service;
+pub mod anomaly_service;
+pub mod heartbeat_service;
+pub mod hog_service;
+pub mod pattern_service;
+pub mod saved_search_service;
+pub mod search_job_service;
\ No newline at end of file
@@ -0,0 +1,85 @@
+use crate::config;
+use crate::models::options::OptionsRequest;
+use crate::models::pattern::{
+    HogPattern, HogPatternRecord, HogPatterns, PatternMiner, PATTERN_MAX_GROUPS, PATTERN_SIMILARITY,
+};
+use crate::services::hog_service::HogService;
+use crate::utils::utils;
+use futures::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::{bson::doc, Collection, Database};
+use std::sync::Arc;
+
+const HOG_PATTERNS_MAX_SCAN: i64 = 100_000;
+
+/// Log message templates, either as stored by the workers in `hog_pattern` or
+/// mined on the spot over a search.
+pub struct PatternService {
+    patterns: Collection<HogPatternRecord>,
+    hog_service: Arc<HogService>,
+    max_scan: i64,
+    similarity: f64,
+    max_groups: usize,
+}
+
+impl PatternService {
+    pub fn new(db: &Database, hog_service: Arc<HogService>) -> Self {
+        PatternService {
+            patterns: db.collection::<HogPatternRecord>("hog_pattern"),
+            hog_service,
+            max_scan: config::env_or("HOG_PATTERNS_MAX_SCAN", HOG_PATTERNS_MAX_SCAN),
+            similarity: config::env_or("HOG_PATTERN_SIMILARITY", PATTERN_SIMILARITY),
+            max_groups: config::env_or("HOG_PATTERN_MAX_GROUPS", PATTERN_MAX_GROUPS),
+        }
+    }
+
+    pub async fn list_patterns(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<HogPattern>, mongodb::error::Error> {
+        let find_options = FindOptions::builder()
+            .sort(doc! { "count": -1 })
+            .limit(limit)
+            .build();
+        let records: Vec<HogPatternRecord> = self
+            .patterns
+            .find(doc! {})
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_hog_pattern_record_to_hog_pattern)
+            .collect())
+    }
+
+    /// Clusters the messages of up to `HOG_PATTERNS_MAX_SCAN` of the newest
+    /// hogs matching `options`. Nothing is stored.
+    pub async fn mine_patterns(
+        &self,
+        options: OptionsRequest,
+        limit: usize,
+    ) -> Result<HogPatterns, mongodb::error::Error> {
+        let options = OptionsRequest {
+            hog_limit: Some(
+                options
+                    .hog_limit
+                    .map_or(self.max_scan, |limit| limit.min(self.max_scan)),
+            ),
+            ..options
+        };
+
+        let mut miner = PatternMiner::new(self.similarity, self.max_groups);
+        let mut scanned = 0;
+        let mut records = self.hog_service.stream_hog_records(&options).await?;
+        while let Some(record) = records.try_next().await? {
+            miner.add(&record);
+            scanned += 1;
+        }
+
+        let mut patterns = miner.patterns();
+        patterns.truncate(limit);
+        Ok(HogPatterns { scanned, patterns })
+    }
+}
@@ -0,0 +1,116 @@
+use crate::models::saved_search::{SavedSearch, SavedSearchRecord, SavedSearchRequest};
+use crate::utils::utils;
+use futures::TryStreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{bson::doc, Collection, Database};
+
+pub struct SavedSearchService {
+    collection: Collection<SavedSearchRecord>,
+}
+
+impl SavedSearchService {
+    pub fn new(db: &Database) -> Self {
+        SavedSearchService {
+            collection: db.collection::<SavedSearchRecord>("saved_search"),
+        }
+    }
+
+    pub async fn create_saved_search(
+        &self,
+        req: SavedSearchRequest,
+    ) -> Result<SavedSearch, mongodb::error::Error> {
+        let now = utils::convert_timestamp_chrono_to_bson(utils::get_timestamp());
+        let mut record = SavedSearchRecord {
+            id: None,
+            name: req.name,
+            description: req.description,
+            owner: req.owner,
+            options: req.options,
+            created_at: now,
+            updated_at: now,
+        };
+        let result = self.collection.insert_one(&record).await?;
+        record.id = result.inserted_id.as_object_id();
+        Ok(utils::convert_saved_search_record_to_saved_search(record))
+    }
+
+    pub async fn list_saved_searches(
+        &self,
+        owner: Option<String>,
+    ) -> Result<Vec<SavedSearch>, mongodb::error::Error> {
+        let filter = match owner {
+            Some(owner) => doc! { "owner": owner },
+            None => doc! {},
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "owner": 1, "name": 1 })
+            .build();
+        let records: Vec<SavedSearchRecord> = self
+            .collection
+            .find(filter)
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(utils::convert_saved_search_record_to_saved_search)
+            .collect())
+    }
+
+    pub async fn get_saved_search(
+        &self,
+        id: &str,
+    ) -> Result<Option<SavedSearch>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let record = self.collection.find_one(doc! { "_id": oid }).await?;
+        Ok(record.map(utils::convert_saved_search_record_to_saved_search))
+    }
+
+    pub async fn update_saved_search(
+        &self,
+        id: &str,
+        req: SavedSearchRequest,
+    ) -> Result<Option<SavedSearch>, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let update = doc! { "$set": {
+            "name": req.name,
+            "description": req.description,
+            "owner": req.owner,
+            "options": bson::to_bson(&req.options)?,
+            "updated_at": utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        } };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let record = self
+            .collection
+            .find_one_and_update(doc! { "_id": oid }, update)
+            .with_options(options)
+            .await?;
+        Ok(record.map(utils::convert_saved_search_record_to_saved_search))
+    }
+
+    pub async fn delete_saved_search(&self, id: &str) -> Result<bool, mongodb::error::Error> {
+        let Ok(oid) = ObjectId::parse_str(id) else {
+            return Ok(false);
+        };
+        let result = self.collection.delete_one(doc! { "_id": oid }).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+/// Saved search names are unique per owner.
+pub fn is_duplicate_key_error(err: &mongodb::error::Error) -> bool {
+    match *err.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref write_error)) => write_error.code == 11000,
+        ErrorKind::Command(ref command_error) => command_error.code == 11000,
+        _ => false,
+    }
+}
@@ -0,0 +1,272 @@
+use crate::config;
+use crate::models::hog_record::HogRecord;
+use crate::models::options::OptionsRequest;
+use crate::models::search_job::{
+    SearchJob, SearchJobRecord, SearchJobResultRecord, SearchJobResults, SearchJobStatus,
+};
+use crate::services::hog_service::HogService;
+use crate::utils::utils;
+use bson::DateTime as BsonDateTime;
+use futures::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::{bson::doc, Collection, Database};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+const SEARCH_JOB_TTL_SECS: i64 = 3600;
+const SEARCH_JOB_BATCH_SIZE: usize = 1000;
+
+/// Runs searches in the background and parks their results in the
+/// `search_job_result` collection, where they expire after `SEARCH_JOB_TTL_SECS`.
+pub struct SearchJobService {
+    jobs: Collection<SearchJobRecord>,
+    results: Collection<SearchJobResultRecord>,
+    hog_service: Arc<HogService>,
+    running: Mutex<HashMap<String, AbortHandle>>,
+    ttl: chrono::Duration,
+}
+
+impl SearchJobService {
+    pub fn new(db: &Database, hog_service: Arc<HogService>) -> Self {
+        SearchJobService {
+            jobs: db.collection::<SearchJobRecord>("search_job"),
+            results: db.collection::<SearchJobResultRecord>("search_job_result"),
+            hog_service,
+            running: Mutex::new(HashMap::new()),
+            ttl: chrono::Duration::seconds(config::env_or(
+                "SEARCH_JOB_TTL_SECS",
+                SEARCH_JOB_TTL_SECS,
+            )),
+        }
+    }
+
+    pub async fn create_job(
+        self: &Arc<Self>,
+        options: OptionsRequest,
+    ) -> Result<SearchJob, mongodb::error::Error> {
+        let now = utils::get_timestamp();
+        let job = SearchJobRecord {
+            id: Uuid::new_v4().to_string(),
+            status: SearchJobStatus::Pending,
+            options,
+            result_count: 0,
+            error: None,
+            created_at: utils::convert_timestamp_chrono_to_bson(now),
+            started_at: None,
+            finished_at: None,
+            expires_at: utils::convert_timestamp_chrono_to_bson(now + self.ttl),
+        };
+        self.jobs.insert_one(&job).await?;
+
+        let service = Arc::clone(self);
+        let job_id = job.id.clone();
+        let options = job.options.clone();
+        // Hold the lock while spawning so the task can't deregister itself
+        // before it has been registered.
+        let mut running = self.running.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            if let Err(err) = service.run_job(&job_id, &options).await {
+                eprintln!("Search job {} failed: {:?}", job_id, err);
+                let _ = service
+                    .finish_job(&job_id, SearchJobStatus::Failed, Some(err.to_string()))
+                    .await;
+            }
+            service.running.lock().unwrap().remove(&job_id);
+        });
+        running.insert(job.id.clone(), handle.abort_handle());
+        drop(running);
+
+        Ok(utils::convert_search_job_record_to_search_job(job))
+    }
+
+    async fn run_job(
+        &self,
+        job_id: &str,
+        options: &OptionsRequest,
+    ) -> Result<(), mongodb::error::Error> {
+        self.jobs
+            .update_one(
+                doc! { "_id": job_id, "status": "pending" },
+                doc! { "$set": {
+                    "status": "running",
+                    "started_at": utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+                } },
+            )
+            .await?;
+
+        let mut expires_at = self.extend_expiry(job_id).await?;
+        let mut records = self.hog_service.stream_hog_records(options).await?;
+        let mut batch: Vec<SearchJobResultRecord> = Vec::with_capacity(SEARCH_JOB_BATCH_SIZE);
+        let mut seq: i64 = 0;
+
+        loop {
+            let record: Option<HogRecord> = records.try_next().await?;
+            let done = record.is_none();
+            if let Some(hog) = record {
+                seq += 1;
+                batch.push(SearchJobResultRecord {
+                    job_id: job_id.to_string(),
+                    seq,
+                    hog,
+                    expires_at,
+                });
+            }
+
+            if batch.len() >= SEARCH_JOB_BATCH_SIZE || (done && !batch.is_empty()) {
+                // Another instance may have cancelled the job in the meantime.
+                if !self.is_running(job_id).await? {
+                    return Ok(());
+                }
+                // A job that runs longer than half the TTL keeps its results
+                // from expiring under it.
+                let now = utils::get_timestamp();
+                if utils::convert_timestamp_bson_to_chrono(expires_at) - now < self.ttl / 2 {
+                    expires_at = self.extend_expiry(job_id).await?;
+                    for result in &mut batch {
+                        result.expires_at = expires_at;
+                    }
+                }
+                self.results.insert_many(batch.drain(..)).await?;
+                self.jobs
+                    .update_one(
+                        doc! { "_id": job_id, "status": "running" },
+                        doc! { "$set": { "result_count": seq } },
+                    )
+                    .await?;
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        self.finish_job(job_id, SearchJobStatus::Completed, None)
+            .await
+    }
+
+    /// Moves the expiry of the job and the results stored so far to a full
+    /// TTL from now.
+    async fn extend_expiry(&self, job_id: &str) -> Result<BsonDateTime, mongodb::error::Error> {
+        let expires_at = utils::convert_timestamp_chrono_to_bson(utils::get_timestamp() + self.ttl);
+        self.jobs
+            .update_one(
+                doc! { "_id": job_id },
+                doc! { "$set": { "expires_at": expires_at } },
+            )
+            .await?;
+        self.results
+            .update_many(
+                doc! { "job_id": job_id },
+                doc! { "$set": { "expires_at": expires_at } },
+            )
+            .await?;
+        Ok(expires_at)
+    }
+
+    async fn is_running(&self, job_id: &str) -> Result<bool, mongodb::error::Error> {
+        let count = self
+            .jobs
+            .count_documents(doc! { "_id": job_id, "status": "running" })
+            .await?;
+        Ok(count > 0)
+    }
+
+    async fn finish_job(
+        &self,
+        job_id: &str,
+        status: SearchJobStatus,
+        error: Option<String>,
+    ) -> Result<(), mongodb::error::Error> {
+        let mut set = doc! {
+            "status": bson::to_bson(&status)?,
+            "finished_at": utils::convert_timestamp_chrono_to_bson(utils::get_timestamp()),
+        };
+        if let Some(error) = error {
+            set.insert("error", error);
+        }
+        let result = self
+            .jobs
+            .update_one(
+                doc! { "_id": job_id, "status": { "$in": ["pending", "running"] } },
+                doc! { "$set": set },
+            )
+            .await?;
+        // The results are readable for a full TTL after the job ends, however
+        // long it ran.
+        if result.modified_count > 0 {
+            self.extend_expiry(job_id).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_job(&self, job_id: &str) -> Result<Option<SearchJob>, mongodb::error::Error> {
+        let job = self.jobs.find_one(doc! { "_id": job_id }).await?;
+        Ok(job.map(utils::convert_search_job_record_to_search_job))
+    }
+
+    /// Pages through a job's results by `seq`. Works while the job is still
+    /// running; results show up as each batch is stored.
+    pub async fn get_results(
+        &self,
+        job_id: &str,
+        after: i64,
+        limit: i64,
+    ) -> Result<Option<SearchJobResults>, mongodb::error::Error> {
+        let Some(job) = self.get_job(job_id).await? else {
+            return Ok(None);
+        };
+
+        let find_options = FindOptions::builder()
+            .sort(doc! { "seq": 1 })
+            .limit(limit)
+            .build();
+        let results: Vec<SearchJobResultRecord> = self
+            .results
+            .find(doc! { "job_id": job_id, "seq": { "$gt": after } })
+            .with_options(find_options)
+            .await?
+            .try_collect()
+            .await?;
+
+        // Batches are stored before `result_count` is bumped, so while the
+        // job runs there may always be more.
+        let last_seq = results.last().map(|result| result.seq);
+        let next_after = match job.status {
+            SearchJobStatus::Pending | SearchJobStatus::Running => Some(last_seq.unwrap_or(after)),
+            _ => last_seq.filter(|seq| *seq < job.result_count),
+        };
+        let hogs = utils::convert_hog_records_to_hogs(results.into_iter().map(|r| r.hog).collect());
+
+        Ok(Some(SearchJobResults {
+            job,
+            hogs,
+            next_after,
+        }))
+    }
+
+    /// Stops a pending or running job. Results stored so far stay readable
+    /// until the job expires.
+    pub async fn cancel_job(
+        &self,
+        job_id: &str,
+    ) -> Result<Option<SearchJob>, mongodb::error::Error> {
+        self.finish_job(job_id, SearchJobStatus::Cancelled, None)
+            .await?;
+        if let Some(handle) = self.running.lock().unwrap().remove(job_id) {
+            handle.abort();
+        }
+        self.get_job(job_id).await
+    }
+
+    /// Cancels the job and removes it together with its results.
+    pub async fn delete_job(&self, job_id: &str) -> Result<bool, mongodb::error::Error> {
+        if self.cancel_job(job_id).await?.is_none() {
+            return Ok(false);
+        }
+        self.results.delete_many(doc! { "job_id": job_id }).await?;
+        let result = self.jobs.delete_one(doc! { "_id": job_id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::models::export::ExportFormat;
+use crate::models::hog::Hog;
+use crate::models::matcher::lookup;
+
+const CSV_BASE_COLUMNS: [&str; 10] = [
+    "id",
+    "hog_uuid",
+    "hog_timestamp",
+    "created_at",
+    "log_timestamp",
+    "log_level",
+    "log_type",
+    "log_source",
+    "log_source_id",
+    "log_message",
+];
+
+/// CSV columns for every `log_data` field in `sample`, sorted. Nested objects
+/// are flattened into dotted paths (`request.path`); arrays stay one column.
+pub fn log_data_columns<'a>(sample: impl Iterator<Item = &'a Value>) -> Vec<String> {
+    fn collect(prefix: &str, value: &Value, columns: &mut BTreeSet<String>) {
+        let Some(map) = value.as_object() else {
+            columns.insert(prefix.to_string());
+            return;
+        };
+        for (key, value) in map {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            collect(&path, value, columns);
+        }
+    }
+
+    let mut columns = BTreeSet::new();
+    for log_data in sample.filter(|log_data| log_data.is_object()) {
+        collect("", log_data, &mut columns);
+    }
+    columns.into_iter().collect()
+}
+
+/// Bytes written before the first hog.
+pub fn export_prefix(format: ExportFormat, log_data_columns: &[String]) -> String {
+    match format {
+        ExportFormat::Ndjson => String::new(),
+        ExportFormat::Json => "[".to_string(),
+        ExportFormat::Csv => {
+            let header = CSV_BASE_COLUMNS
+                .iter()
+                .map(|column| column.to_string())
+                .chain(
+                    log_data_columns
+                        .iter()
+                        .map(|column| format!("log_data.{}", column)),
+                )
+                .map(|column| csv_escape(&column))
+                .collect::<Vec<_>>();
+            format!("{}\r\n", header.join(","))
+        }
+    }
+}
+
+/// Bytes written after the last hog.
+pub fn export_suffix(format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => "]".to_string(),
+        ExportFormat::Ndjson | ExportFormat::Csv => String::new(),
+    }
+}
+
+/// Renders one hog; `index` is its position in the export.
+pub fn export_hog(
+    format: ExportFormat,
+    hog: &Hog,
+    index: usize,
+    log_data_columns: &[String],
+) -> Result<String, serde_json::Error> {
+    match format {
+        ExportFormat::Ndjson => Ok(format!("{}\n", serde_json::to_string(hog)?)),
+        ExportFormat::Json => {
+            let separator = if index == 0 { "" } else { "," };
+            Ok(format!("{}{}", separator, serde_json::to_string(hog)?))
+        }
+        ExportFormat::Csv => Ok(csv_row(hog, log_data_columns)),
+    }
+}
+
+fn csv_row(hog: &Hog, log_data_columns: &[String]) -> String {
+    let request = &hog.client_request;
+    let timestamp = |ts: Option<chrono::DateTime<chrono::Utc>>| {
+        ts.map(|ts| ts.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_default()
+    };
+
+    let mut cells = vec![
+        hog.id.clone().unwrap_or_default(),
+        hog.hog_uuid.clone().unwrap_or_default(),
+        timestamp(hog.hog_timestamp),
+        timestamp(hog.created_at),
+        request.log_timestamp.clone(),
+        request.log_level.clone().unwrap_or_default(),
+        request.log_type.clone().unwrap_or_default(),
+        request.log_source.clone().unwrap_or_default(),
+        request.log_source_id.clone().unwrap_or_default(),
+        request.log_message.clone(),
+    ];
+    cells.extend(log_data_columns.iter().map(|column| {
+        match lookup(request.log_data.as_ref(), column) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        }
+    }));
+
+    let cells = cells
+        .iter()
+        .map(|cell| csv_escape(cell))
+        .collect::<Vec<_>>();
+    format!("{}\r\n", cells.join(","))
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
@@ -1 +1,3 @@
+pub mod export;
+#[allow(clippy::module_inception)]
 pub mod utils;
\ No newline at end of file
@@ -2,6 +2,18 @@ use bson::DateTime as BsonDateTime;
 use chrono::{DateTime, Timelike, Utc};
 use mongodb::bson::oid::ObjectId;
 
+use crate::models::alert::{
+    Alert, AlertRecord, AlertRule, AlertRuleRecord, Silence, SilenceRecord,
+};
+use crate::models::annotations::{
+    HogAnnotations, HogAnnotationsRecord, HogComment, HogCommentRecord,
+};
+use crate::models::anomaly::{Anomaly, AnomalyRecord, VolumeBaseline, VolumeBaselineRecord};
+use crate::models::heartbeat::{HeartbeatRule, HeartbeatRuleRecord, SourceStatus};
+use crate::models::notification::{NotificationChannel, NotificationChannelRecord};
+use crate::models::pattern::{HogPattern, HogPatternRecord};
+use crate::models::saved_search::{SavedSearch, SavedSearchRecord};
+use crate::models::search_job::{SearchJob, SearchJobRecord};
 use crate::models::{client_request::ClientRequest, hog::Hog, hog_record::HogRecord};
 
 pub fn get_timestamp() -> DateTime<Utc> {
@@ -48,11 +60,21 @@ pub fn convert_hog_records_to_hogs(hog_records: Vec<HogRecord>) -> Vec<Hog> {
                 hog_timestamp: record.hog_timestamp.map(|dt| dt.to_chrono()),
                 id: record.id.map(|oid| oid.to_hex()), // ObjectId -> String
                 created_at: record.created_at.map(convert_timestamp_bson_to_chrono),
+                annotations: record
+                    .annotations
+                    .map(convert_annotations_record_to_annotations),
+                pattern_id: record.pattern_id,
+                sample_rate: record.sample_rate,
+                repeat_count: record.repeat_count,
+                first_seen_at: record.first_seen_at.map(convert_timestamp_bson_to_chrono),
+                last_seen_at: record.last_seen_at.map(convert_timestamp_bson_to_chrono),
+                clock_skew: record.clock_skew,
             }
         })
         .collect()
 }
 
+#[allow(dead_code)]
 pub fn convert_hogs_to_hog_records(hogs: Vec<Hog>) -> Vec<HogRecord> {
     hogs.into_iter()
         .map(|hog| {
@@ -67,12 +89,23 @@ pub fn convert_hogs_to_hog_records(hogs: Vec<Hog>) -> Vec<HogRecord> {
                 hog_uuid: hog.hog_uuid,
                 hog_timestamp: hog.hog_timestamp.map(convert_timestamp_chrono_to_bson),
                 created_at: Some(BsonDateTime::from_chrono(get_timestamp())),
+                annotations: hog
+                    .annotations
+                    .map(convert_annotations_to_annotations_record),
+                pattern_id: hog.pattern_id,
+                sample_rate: hog.sample_rate,
+                repeat_count: hog.repeat_count,
+                first_seen_at: hog.first_seen_at.map(convert_timestamp_chrono_to_bson),
+                last_seen_at: hog.last_seen_at.map(convert_timestamp_chrono_to_bson),
+                clock_skew: hog.clock_skew,
+                content_hash: None,
                 id: hog.id.and_then(|s| ObjectId::parse_str(&s).ok()), // String -> ObjectId
             }
         })
         .collect()
 }
 
+#[allow(dead_code)]
 pub fn convert_hog_to_hog_record(hog: &Hog) -> HogRecord {
     HogRecord {
         log_timestamp: rfc3339_str_to_bson(&hog.client_request.log_timestamp).unwrap(),
@@ -85,6 +118,17 @@ pub fn convert_hog_to_hog_record(hog: &Hog) -> HogRecord {
         hog_uuid: hog.hog_uuid.clone(),
         hog_timestamp: hog.hog_timestamp.map(convert_timestamp_chrono_to_bson),
         created_at: Some(BsonDateTime::from_chrono(get_timestamp())),
+        annotations: hog
+            .annotations
+            .clone()
+            .map(convert_annotations_to_annotations_record),
+        pattern_id: hog.pattern_id.clone(),
+        sample_rate: hog.sample_rate,
+        repeat_count: hog.repeat_count,
+        first_seen_at: hog.first_seen_at.map(convert_timestamp_chrono_to_bson),
+        last_seen_at: hog.last_seen_at.map(convert_timestamp_chrono_to_bson),
+        clock_skew: hog.clock_skew,
+        content_hash: None,
         id: hog.id.as_ref().and_then(|s| ObjectId::parse_str(s).ok()), // String -> ObjectId
     }
 }
@@ -104,5 +148,210 @@ pub fn convert_hog_record_to_hog(hog_record: &HogRecord) -> Hog {
         hog_timestamp: hog_record.hog_timestamp.map(|dt| dt.to_chrono()),
         id: hog_record.id.as_ref().map(|oid| oid.to_hex()), // ObjectId -> String
         created_at: hog_record.created_at.map(convert_timestamp_bson_to_chrono),
+        annotations: hog_record
+            .annotations
+            .clone()
+            .map(convert_annotations_record_to_annotations),
+        pattern_id: hog_record.pattern_id.clone(),
+        sample_rate: hog_record.sample_rate,
+        repeat_count: hog_record.repeat_count,
+        first_seen_at: hog_record
+            .first_seen_at
+            .map(convert_timestamp_bson_to_chrono),
+        last_seen_at: hog_record
+            .last_seen_at
+            .map(convert_timestamp_bson_to_chrono),
+        clock_skew: hog_record.clock_skew,
+    }
+}
+
+pub fn convert_annotations_record_to_annotations(record: HogAnnotationsRecord) -> HogAnnotations {
+    HogAnnotations {
+        tags: record.tags,
+        comments: record
+            .comments
+            .into_iter()
+            .map(|comment| HogComment {
+                author: comment.author,
+                comment: comment.comment,
+                created_at: convert_timestamp_bson_to_chrono(comment.created_at),
+            })
+            .collect(),
+    }
+}
+
+pub fn convert_annotations_to_annotations_record(
+    annotations: HogAnnotations,
+) -> HogAnnotationsRecord {
+    HogAnnotationsRecord {
+        tags: annotations.tags,
+        comments: annotations
+            .comments
+            .into_iter()
+            .map(|comment| HogCommentRecord {
+                author: comment.author,
+                comment: comment.comment,
+                created_at: convert_timestamp_chrono_to_bson(comment.created_at),
+            })
+            .collect(),
+    }
+}
+
+pub fn convert_search_job_record_to_search_job(record: SearchJobRecord) -> SearchJob {
+    SearchJob {
+        id: record.id,
+        status: record.status,
+        options: record.options,
+        result_count: record.result_count,
+        error: record.error,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
+        started_at: record.started_at.map(convert_timestamp_bson_to_chrono),
+        finished_at: record.finished_at.map(convert_timestamp_bson_to_chrono),
+        expires_at: convert_timestamp_bson_to_chrono(record.expires_at),
+    }
+}
+
+pub fn convert_saved_search_record_to_saved_search(record: SavedSearchRecord) -> SavedSearch {
+    SavedSearch {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        name: record.name,
+        description: record.description,
+        owner: record.owner,
+        options: record.options,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
+        updated_at: convert_timestamp_bson_to_chrono(record.updated_at),
+    }
+}
+
+pub fn convert_hog_pattern_record_to_hog_pattern(record: HogPatternRecord) -> HogPattern {
+    HogPattern {
+        pattern_id: record.id,
+        template: record.template,
+        count: record.count,
+        sample_hog_uuids: record.sample_hog_uuids,
+        first_seen: convert_timestamp_bson_to_chrono(record.first_seen),
+        last_seen: convert_timestamp_bson_to_chrono(record.last_seen),
+    }
+}
+
+pub fn convert_anomaly_record_to_anomaly(record: AnomalyRecord) -> Anomaly {
+    Anomaly {
+        id: record.id.map(|oid| oid.to_hex()),
+        log_source: record.log_source,
+        log_level: record.log_level,
+        kind: record.kind,
+        count: record.count,
+        expected: record.expected,
+        stddev: record.stddev,
+        score: record.score,
+        window_start: convert_timestamp_bson_to_chrono(record.window_start),
+        window_end: convert_timestamp_bson_to_chrono(record.window_end),
+        detected_at: convert_timestamp_bson_to_chrono(record.detected_at),
+    }
+}
+
+pub fn convert_volume_baseline_record_to_volume_baseline(
+    record: VolumeBaselineRecord,
+) -> VolumeBaseline {
+    VolumeBaseline {
+        stddev: record.stddev(),
+        log_source: record.log_source,
+        log_level: record.log_level,
+        mean: record.mean,
+        windows: record.windows,
+        last_count: record.last_count,
+        updated_at: convert_timestamp_bson_to_chrono(record.updated_at),
+    }
+}
+
+pub fn convert_heartbeat_rule_record_to_heartbeat_rule(
+    record: HeartbeatRuleRecord,
+) -> HeartbeatRule {
+    HeartbeatRule {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        name: record.name,
+        log_source: record.log_source,
+        log_source_id: record.log_source_id,
+        max_silence_secs: record.max_silence_secs,
+        notifications: record.notifications,
+        enabled: record.enabled,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
+        updated_at: convert_timestamp_bson_to_chrono(record.updated_at),
+    }
+}
+
+pub fn convert_heartbeat_rule_record_to_source_status(record: HeartbeatRuleRecord) -> SourceStatus {
+    SourceStatus {
+        rule_id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        name: record.name,
+        log_source: record.log_source,
+        log_source_id: record.log_source_id,
+        state: record.state,
+        max_silence_secs: record.max_silence_secs,
+        last_seen: record.last_seen.map(convert_timestamp_bson_to_chrono),
+        silent_since: record.silent_since.map(convert_timestamp_bson_to_chrono),
+        checked_at: record.checked_at.map(convert_timestamp_bson_to_chrono),
+    }
+}
+
+pub fn convert_notification_channel_record_to_notification_channel(
+    record: NotificationChannelRecord,
+) -> NotificationChannel {
+    NotificationChannel {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        name: record.name,
+        target: record.target,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
+    }
+}
+
+pub fn convert_alert_rule_record_to_alert_rule(record: AlertRuleRecord) -> AlertRule {
+    AlertRule {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        name: record.name,
+        description: record.description,
+        options: record.options,
+        kind: record.kind,
+        window_secs: record.window_secs,
+        condition: record.condition,
+        for_secs: record.for_secs,
+        repeat_interval_secs: record.repeat_interval_secs,
+        group_by: record.group_by,
+        channels: record.channels,
+        labels: record.labels,
+        enabled: record.enabled,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
+        updated_at: convert_timestamp_bson_to_chrono(record.updated_at),
+    }
+}
+
+pub fn convert_alert_record_to_alert(record: AlertRecord) -> Alert {
+    Alert {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        rule_id: record.rule_id,
+        rule_name: record.rule_name,
+        labels: record.labels,
+        state: record.state,
+        value: record.value,
+        active_since: convert_timestamp_bson_to_chrono(record.active_since),
+        fired_at: record.fired_at.map(convert_timestamp_bson_to_chrono),
+        resolved_at: record.resolved_at.map(convert_timestamp_bson_to_chrono),
+        last_evaluated_at: convert_timestamp_bson_to_chrono(record.last_evaluated_at),
+        last_notified_at: record
+            .last_notified_at
+            .map(convert_timestamp_bson_to_chrono),
+        silenced: record.silenced,
+    }
+}
+
+pub fn convert_silence_record_to_silence(record: SilenceRecord) -> Silence {
+    Silence {
+        id: record.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+        matchers: record.matchers,
+        starts_at: convert_timestamp_bson_to_chrono(record.starts_at),
+        ends_at: convert_timestamp_bson_to_chrono(record.ends_at),
+        comment: record.comment,
+        created_by: record.created_by,
+        created_at: convert_timestamp_bson_to_chrono(record.created_at),
     }
 }
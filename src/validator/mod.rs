@@ -1,22 +1,40 @@
 use crate::{
     errors::{ApiErrorField, ValidationError},
-    models::hog_client_schema::HogRequest,
+    models::{hog_client_schema::HogRequest, timestamp::TimestampParser},
 };
+use chrono::DateTime;
 use serde_json::Value;
 
 #[allow(dead_code)]
-pub async fn validate_hog_client_schema(req: Value) -> Result<HogRequest, ValidationError> {
+pub async fn validate_hog_client_schema(
+    req: Value,
+    timestamps: &TimestampParser,
+) -> Result<HogRequest, ValidationError> {
     let mut errors = Vec::new();
 
-    let log_timestamp = match req.get("log_timestamp").and_then(|v| v.as_str()) {
-        Some(ts) => ts.to_string(),
-        None => {
+    let log_source = req
+        .get("log_source")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let log_timestamp = match req.get("log_timestamp") {
+        None | Some(Value::Null) => {
             errors.push(ApiErrorField {
                 field: "log_timestamp".to_string(),
                 message: "log_timestamp is required".to_string(),
             });
-            String::new()
+            DateTime::UNIX_EPOCH
         }
+        Some(value) => match timestamps.parse(value, log_source.as_deref()) {
+            Ok(ts) => ts,
+            Err(message) => {
+                errors.push(ApiErrorField {
+                    field: "log_timestamp".to_string(),
+                    message,
+                });
+                DateTime::UNIX_EPOCH
+            }
+        },
     };
 
     let log_message = match req.get("log_message").and_then(|v| v.as_str()) {
@@ -43,10 +61,6 @@ pub async fn validate_hog_client_schema(req: Value) -> Result<HogRequest, Valida
         .get("log_type")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    let log_source = req
-        .get("log_source")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
     let log_source_id = req
         .get("log_source_id")
         .and_then(|v| v.as_str())
@@ -1,5 +1,6 @@
 mod config;
 mod models;
+mod worker_metrics;
 
 use bson::DateTime as BsonDateTime;
 use chrono::Utc;
@@ -7,13 +8,24 @@ use futures::StreamExt;
 use governor::{Quota, RateLimiter};
 use lapin::{message::Delivery, options::*, types::FieldTable};
 use mongodb::Collection;
-use serde_json;
 use std::env;
 use std::num::NonZeroU32;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use crate::models::dedup::{Deduplicator, DEDUP_WINDOW_SECS};
+use crate::models::encryption::FieldCipher;
 use crate::models::hog_record::HogRecord;
+use crate::models::lag::ClockSkewLimits;
+use crate::models::log_metrics::LogMetrics;
+use crate::models::pattern::{
+    HogPatternRecord, PatternMiner, PATTERN_MAX_GROUPS, PATTERN_SIMILARITY,
+};
+use crate::models::pipeline::{Pipeline, PipelineOutcome};
+use crate::models::redaction::Redactor;
+use crate::models::stream_alert::{
+    StreamAlerter, ALERT_EVENT_RETENTION_SECS, STREAM_ALERT_REFRESH_SECS,
+};
 
 #[tokio::main]
 async fn main() {
@@ -58,14 +70,80 @@ async fn main() {
 
     println!("✅ Connected to MongoDB and initialized indexes");
 
+    worker_metrics::spawn_server();
+
     let collection: Collection<HogRecord> = db.collection("hog");
+    let patterns: Collection<HogPatternRecord> = db.collection("hog_pattern");
     let limiter = RateLimiter::direct(Quota::per_second(NonZeroU32::new(400).unwrap()));
+    let pipeline = match env::var("HOG_PIPELINE_FILE") {
+        Ok(path) => Some(
+            Pipeline::load(&path)
+                .await
+                .unwrap_or_else(|e| panic!("Failed to load ingest pipeline {}", e)),
+        ),
+        Err(_) => None,
+    };
+    // The pipeline can copy or lift text into new fields after the API
+    // redacted it, so the same rules run again on what it produces.
+    let redactor =
+        Redactor::from_env().unwrap_or_else(|e| panic!("Failed to load redaction rules {}", e));
+    let cipher = FieldCipher::from_env()
+        .unwrap_or_else(|e| panic!("Failed to load field encryption keys: {}", e));
+    let metrics = match env::var("HOG_METRICS_FILE") {
+        Ok(path) => Some(
+            LogMetrics::load(&path, &cipher.encrypted_fields())
+                .await
+                .unwrap_or_else(|e| panic!("Failed to load log metrics {}", e)),
+        ),
+        Err(_) => None,
+    };
+    let cipher = cipher.is_enabled().then_some(cipher);
+    let clock_skew = ClockSkewLimits::from_env();
+    let dedup = config::env_or("HOG_DEDUP_ENABLED", false)
+        .then(|| Deduplicator::new(config::env_or("HOG_DEDUP_WINDOW_SECS", DEDUP_WINDOW_SECS)));
+    let mut miner = config::env_or("HOG_PATTERNS_ENABLED", false).then(|| {
+        PatternMiner::new(
+            config::env_or("HOG_PATTERN_SIMILARITY", PATTERN_SIMILARITY),
+            config::env_or("HOG_PATTERN_MAX_GROUPS", PATTERN_MAX_GROUPS),
+        )
+    });
+    let mut alerter = if config::env_or("STREAM_ALERTS_ENABLED", false) {
+        Some(
+            StreamAlerter::new(
+                &db,
+                channel.clone(),
+                Duration::from_secs(config::env_or(
+                    "STREAM_ALERT_REFRESH_SECS",
+                    STREAM_ALERT_REFRESH_SECS,
+                )),
+                config::env_or("ALERT_EVENT_RETENTION_SECS", ALERT_EVENT_RETENTION_SECS),
+            )
+            .await
+            .expect("Failed to declare the alerts exchange"),
+        )
+    } else {
+        None
+    };
 
     while let Some(delivery_result) = consumer.next().await {
         match delivery_result {
             Ok(delivery) => {
                 limiter.until_ready().await;
-                if let Err(e) = process_message(&collection, delivery).await {
+                if let Err(e) = process_message(
+                    &collection,
+                    &patterns,
+                    pipeline.as_ref(),
+                    redactor.as_ref(),
+                    metrics.as_ref(),
+                    miner.as_mut(),
+                    alerter.as_mut(),
+                    cipher.as_ref(),
+                    dedup.as_ref(),
+                    &clock_skew,
+                    delivery,
+                )
+                .await
+                {
                     eprintln!("Error processing message: {:?}", e);
                 }
             }
@@ -78,26 +156,96 @@ async fn main() {
     std::process::exit(1);
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_message(
     collection: &Collection<HogRecord>,
+    patterns: &Collection<HogPatternRecord>,
+    pipeline: Option<&Pipeline>,
+    redactor: Option<&Redactor>,
+    metrics: Option<&LogMetrics>,
+    mut miner: Option<&mut PatternMiner>,
+    alerter: Option<&mut StreamAlerter>,
+    cipher: Option<&FieldCipher>,
+    dedup: Option<&Deduplicator>,
+    clock_skew: &ClockSkewLimits,
     delivery: Delivery,
 ) -> anyhow::Result<()> {
     let mut hog_record: HogRecord = serde_json::from_slice(&delivery.data)?;
+    if let Some(pipeline) = pipeline
+        && pipeline.process(&mut hog_record) == PipelineOutcome::Drop
+    {
+        delivery.ack(BasicAckOptions::default()).await?;
+        return Ok(());
+    }
+    if let Some(redactor) = redactor {
+        worker_metrics::observe_redactions(redactor.redact(&mut hog_record));
+    }
+    if let Some(skew) = clock_skew.flag(&mut hog_record) {
+        worker_metrics::observe_clock_skew(&hog_record, skew);
+    }
+    if let Some(miner) = miner.as_deref_mut() {
+        hog_record.pattern_id = Some(miner.add(&hog_record).pattern_id);
+    }
+    if let Some(alerter) = alerter {
+        alerter.evaluate(&hog_record).await;
+    }
+    let samples = metrics.map(|metrics| metrics.sample(&hog_record));
+    if let Some(dedup) = dedup {
+        dedup.stamp(&mut hog_record);
+    }
+    // Last, so everything above still sees the plaintext.
+    if let Some(cipher) = cipher
+        && let Err(e) = cipher.encrypt(&mut hog_record)
+    {
+        // Retrying can't fix it, so don't requeue.
+        worker_metrics::observe_rejected("encryption");
+        delivery
+            .nack(BasicNackOptions {
+                requeue: false,
+                ..Default::default()
+            })
+            .await?;
+        return Err(anyhow::anyhow!("Failed to encrypt fields: {}", e));
+    }
 
     const MAX_RETRIES: usize = 5;
     for attempt in 1..=MAX_RETRIES {
-        hog_record.created_at = Some(BsonDateTime::from_chrono(Utc::now()));
+        let now = BsonDateTime::from_chrono(Utc::now());
+        hog_record.created_at = Some(now);
 
-        match collection.insert_one(&hog_record).await {
-            Ok(_) => {
+        let stored = match dedup {
+            Some(dedup) => {
+                let uuids: Vec<String> = hog_record.hog_uuid.iter().cloned().collect();
+                dedup.store(collection, &hog_record, &uuids, now).await
+            }
+            None => collection
+                .insert_one(&hog_record)
+                .await
+                .map(|_| ())
+                .map_err(Into::into),
+        };
+        match stored {
+            Ok(()) => {
+                // Repeats count too, whether or not they were folded.
+                if let Some(metrics) = metrics
+                    && let Some(samples) = samples
+                {
+                    metrics.record(samples);
+                }
                 delivery.ack(BasicAckOptions::default()).await?;
+                worker_metrics::observe_lag(&hog_record, now);
+                if let Some(miner) = miner.as_deref_mut()
+                    && let Err(e) = miner.flush(patterns, collection).await
+                {
+                    eprintln!("Failed to store log patterns: {:?}", e);
+                }
                 return Ok(());
             }
             Err(e) => {
                 eprintln!("Attempt {}: Failed to insert document: {:?}", attempt, e);
                 if attempt == MAX_RETRIES {
                     delivery.nack(BasicNackOptions::default()).await?;
-                    return Err(e.into());
+                    return Err(e);
                 } else {
                     sleep(Duration::from_millis(500 * attempt as u64)).await;
                 }
@@ -0,0 +1,108 @@
+use axum::{routing::get, Router};
+use bson::DateTime as BsonDateTime;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+    TextEncoder,
+};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use crate::config;
+use crate::models::hog_record::HogRecord;
+use crate::models::lag::{self, ClockSkew};
+
+pub const WORKER_METRICS_PORT: u16 = 9100;
+/// `log_source` values that get their own series before the rest share one.
+pub const WORKER_METRICS_MAX_SOURCES: usize = 200;
+const OTHER_SOURCE: &str = "other";
+
+const LAG_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 3600.0,
+];
+
+lazy_static::lazy_static! {
+    static ref MAX_SOURCES: usize =
+        config::env_or("WORKER_METRICS_MAX_SOURCES", WORKER_METRICS_MAX_SOURCES);
+    static ref SOURCES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    pub static ref HOG_PRODUCER_LAG_SECONDS: HistogramVec =
+        register_histogram_vec!("hog_producer_lag_seconds", "Seconds from log_timestamp to the API receiving the hog", &["log_source"], LAG_BUCKETS.to_vec()).unwrap();
+    pub static ref HOG_STORAGE_LAG_SECONDS: HistogramVec =
+        register_histogram_vec!("hog_storage_lag_seconds", "Seconds from the API receiving a hog to a worker storing it", &["log_source"], LAG_BUCKETS.to_vec()).unwrap();
+    pub static ref HOGS_CLOCK_SKEWED_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hogs_clock_skewed_total", "Total number of hogs whose log_timestamp was too far in the future or past", &["log_source", "direction"]).unwrap();
+    pub static ref HOGS_REDACTED_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hogs_redacted_total", "Total number of values redacted after the ingest pipeline", &["rule"]).unwrap();
+    pub static ref HOGS_REJECTED_TOTAL: IntCounterVec =
+        register_int_counter_vec!("hogs_rejected_total", "Total number of messages dropped without being stored", &["reason"]).unwrap();
+}
+
+/// Serves the workers' `/metrics` on `WORKER_METRICS_PORT`. Failing to bind
+/// is logged, not fatal: storing hogs matters more than being scraped.
+pub fn spawn_server() {
+    let port = config::env_or("WORKER_METRICS_PORT", WORKER_METRICS_PORT);
+    let app = Router::new().route("/metrics", get(metrics));
+    tokio::spawn(async move {
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        if let Err(e) = axum_server::bind(addr).serve(app.into_make_service()).await {
+            eprintln!("Failed to serve worker metrics on {}: {:?}", addr, e);
+        }
+    });
+}
+
+async fn metrics() -> String {
+    let metric_families = prometheus::gather();
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder.encode(&metric_families, &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+pub fn observe_clock_skew(record: &HogRecord, skew: ClockSkew) {
+    HOGS_CLOCK_SKEWED_TOTAL
+        .with_label_values(&[&source_label(record), skew.as_str()])
+        .inc();
+}
+
+pub fn observe_redactions(rules: Vec<String>) {
+    for rule in rules {
+        HOGS_REDACTED_TOTAL.with_label_values(&[&rule]).inc();
+    }
+}
+
+/// Counts a message that can never be stored, so it was dropped rather than
+/// requeued.
+pub fn observe_rejected(reason: &str) {
+    HOGS_REJECTED_TOTAL.with_label_values(&[reason]).inc();
+}
+
+/// Records both lags of a hog once it's stored. A producer clock that runs
+/// ahead counts as no lag rather than a negative one.
+pub fn observe_lag(record: &HogRecord, stored_at: BsonDateTime) {
+    let source = source_label(record);
+    if let Some(secs) = lag::producer_lag_secs(record) {
+        HOG_PRODUCER_LAG_SECONDS
+            .with_label_values(&[&source])
+            .observe(secs.max(0.0));
+    }
+    if let Some(secs) = lag::storage_lag_secs(record, stored_at) {
+        HOG_STORAGE_LAG_SECONDS
+            .with_label_values(&[&source])
+            .observe(secs.max(0.0));
+    }
+}
+
+/// `log_source` comes from clients, so only the first
+/// `WORKER_METRICS_MAX_SOURCES` seen get their own label; later ones are
+/// counted as `other`.
+fn source_label(record: &HogRecord) -> String {
+    let source = record.log_source.as_deref().unwrap_or_default();
+    let mut sources = SOURCES.lock().unwrap();
+    if !sources.contains(source) {
+        if sources.len() >= *MAX_SOURCES {
+            return OTHER_SOURCE.to_string();
+        }
+        sources.insert(source.to_string());
+    }
+    source.to_string()
+}